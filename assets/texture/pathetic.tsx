@@ -1,4 +1,106 @@
 <?xml version="1.0" encoding="UTF-8"?>
-<tileset version="1.2" tiledversion="1.3.5" name="pathetic" tilewidth="64" tileheight="64" tilecount="20" columns="4">
- <image source="pathetic.png" width="256" height="320"/>
+<tileset version="1.2" tiledversion="1.3.5" name="pathetic" tilewidth="64" tileheight="64" tilecount="24" columns="4">
+ <image source="pathetic.png" width="256" height="384"/>
+ <tile id="4">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="5">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="6">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="7">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="8">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="9">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="10">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="11">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="12">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="13">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="14">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="15">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="16">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="17">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="18">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="19">
+  <properties>
+   <property name="drop" value="wood"/>
+   <property name="hits" type="int" value="3"/>
+  </properties>
+ </tile>
+ <tile id="22">
+  <animation>
+   <frame tileid="22" duration="600"/>
+   <frame tileid="23" duration="600"/>
+  </animation>
+ </tile>
 </tileset>
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+use bountiful::resources::topology::SQUARE_8;
+use bountiful::resources::{Point, Map, Square8, Tile, Topology};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use pathfinding::prelude::astar;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const SIZES: [usize; 2] = [512, 2048];
+const WALL_CHANCE: f64 = 0.2;
+
+// Same walls every run so runs (and branches) can be compared.
+fn make_map(size: usize) -> Map {
+    let mut map = Map::new(size, size, '.', 1);
+    let mut rng = StdRng::seed_from_u64(512);
+
+    for y in 0..size {
+        for x in 0..size {
+            if rng.gen_bool(WALL_CHANCE) {
+                map.set_at(&Point::new(x, y), Tile::new('#', 1)).unwrap();
+            }
+        }
+    }
+
+    let (start, end) = corners(size);
+    map.set_at(&start, Tile::new('.', 1)).unwrap();
+    map.set_at(&end, Tile::new('.', 1)).unwrap();
+    map
+}
+
+// How Map stored cells before the palette: one Tile per cell.  Kept here so the two can
+// be compared on the same walls.  Searches work the same way Map's do.
+struct Baseline {
+    width: usize,
+    height: usize,
+    tiles: Vec<Tile>,
+}
+
+impl Baseline {
+    fn from_map(map: &Map) -> Self {
+        Self { width: map.width(), height: map.height(), tiles: map.iter().map(|(_, tile)| tile.clone()).collect() }
+    }
+
+    fn build(size: usize) -> Self {
+        let mut tiles = vec![Tile::new('.', 1); size * size];
+        let mut rng = StdRng::seed_from_u64(512);
+
+        for tile in tiles.iter_mut() {
+            if rng.gen_bool(WALL_CHANCE) {
+                *tile = Tile::new('#', 1);
+            }
+        }
+
+        tiles[0] = Tile::new('.', 1);
+        tiles[size * size - 1] = Tile::new('.', 1);
+        Self { width: size, height: size, tiles }
+    }
+
+    fn neighbours<'a>(&'a self, loc: Point) -> impl Iterator<Item = (Point, &'a Tile)> + 'a {
+        SQUARE_8.iter().filter_map(move |(dx, dy)| {
+            let (x, y) = (loc.x as isize + dx, loc.y as isize + dy);
+            if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                return None;
+            }
+            let next = Point::new(x as usize, y as usize);
+            Some((next, &self.tiles[next.y * self.width + next.x]))
+        })
+    }
+
+    fn shortest_path(&self, start: &Point, end: &Point) -> Option<(Vec<Point>, usize)> {
+        astar(start,
+              |i| self.neighbours(*i).filter(|(_, tile)| tile.id == '.').map(|(i, tile)| (i, tile.weight())),
+              |i| Square8.distance(i, end),
+              |i| i == end)
+    }
+
+    fn regions(&self) -> Vec<Vec<Point>> {
+        let mut labelled = vec![false; self.tiles.len()];
+        let mut regions: Vec<Vec<Point>> = vec![];
+
+        for index in 0..self.tiles.len() {
+            if labelled[index] || self.tiles[index].id != '.' {
+                continue;
+            }
+
+            let mut region = vec![];
+            let mut queue = VecDeque::new();
+            labelled[index] = true;
+            queue.push_back(Point::new(index % self.width, index / self.width));
+
+            while let Some(loc) = queue.pop_front() {
+                for (next, tile) in self.neighbours(loc) {
+                    let next_index = next.y * self.width + next.x;
+                    if !labelled[next_index] && tile.id == '.' {
+                        labelled[next_index] = true;
+                        queue.push_back(next);
+                    }
+                }
+                region.push(loc);
+            }
+
+            regions.push(region);
+        }
+
+        regions.sort_by(|a, b| b.len().cmp(&a.len()));
+        regions
+    }
+
+    fn walls(&self) -> usize {
+        self.tiles.iter().filter(|tile| tile.id == '#').count()
+    }
+
+    fn clear_row(&mut self, y: usize) {
+        for tile in self.tiles[y * self.width..(y + 1) * self.width].iter_mut() {
+            *tile = Tile::new('.', 1);
+        }
+    }
+}
+
+fn corners(size: usize) -> (Point, Point) {
+    (Point::new(0, 0), Point::new(size - 1, size - 1))
+}
+
+pub fn large_maps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_maps");
+    group.sample_size(10);
+
+    for size in SIZES.iter() {
+        let map = make_map(*size);
+        let baseline = Baseline::from_map(&map);
+        let (start, end) = corners(*size);
+
+        // Where the memory goes: a palette index per cell against a whole Tile per cell.
+        assert!(map.cell_bytes() < size_of::<Tile>());
+        println!("large_maps/{}: {} bytes of cells against {} as Vec<Tile> ({} and {} per cell)", size,
+                 size * size * map.cell_bytes(), size * size * size_of::<Tile>(), map.cell_bytes(), size_of::<Tile>());
+
+        // Both have to be doing the same work for the timings to mean anything.
+        assert_eq!(map.shortest_path(&start, &end).map(|(_, cost)| cost),
+                   baseline.shortest_path(&start, &end).map(|(_, cost)| cost));
+        assert_eq!(map.regions(|tile| tile.id == '.').len(), baseline.regions().len());
+
+        group.bench_with_input(BenchmarkId::new("shortest_path", size), &map, |b, map| {
+            b.iter(|| black_box(map.shortest_path(&start, &end)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("shortest_path_vec", size), &baseline, |b, baseline| {
+            b.iter(|| black_box(baseline.shortest_path(&start, &end)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("regions", size), &map, |b, map| {
+            b.iter(|| black_box(map.regions(|tile| tile.id == '.')))
+        });
+
+        group.bench_with_input(BenchmarkId::new("regions_vec", size), &baseline, |b, baseline| {
+            b.iter(|| black_box(baseline.regions()))
+        });
+
+        group.bench_with_input(BenchmarkId::new("build", size), size, |b, size| {
+            b.iter(|| black_box(make_map(*size)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("build_vec", size), size, |b, size| {
+            b.iter(|| black_box(Baseline::build(*size)))
+        });
+
+        group.bench_with_input(BenchmarkId::new("walls", size), &map, |b, map| {
+            b.iter(|| black_box(map.iter().filter(|(_, tile)| tile.id == '#').count()))
+        });
+
+        group.bench_with_input(BenchmarkId::new("walls_vec", size), &baseline, |b, baseline| {
+            b.iter(|| black_box(baseline.walls()))
+        });
+
+        group.bench_function(BenchmarkId::new("clear_row", size), |b| {
+            let mut map = make_map(*size);
+            b.iter(|| map.modify_row(size / 2, |_, tile| *tile = Tile::new('.', 1)).unwrap())
+        });
+
+        group.bench_function(BenchmarkId::new("clear_row_vec", size), |b| {
+            let mut baseline = Baseline::from_map(&map);
+            b.iter(|| baseline.clear_row(size / 2))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, large_maps);
+criterion_main!(benches);
@@ -1,6 +1,8 @@
 use amethyst::{
+    assets::HotReloadBundle,
     core::transform::TransformBundle,
     input::{InputBundle, StringBindings},
+    config::Config,
     renderer::{
         plugins::{RenderFlat2D, RenderToWindow},
         types::DefaultBackend,
@@ -11,52 +13,92 @@ use amethyst::{
         application_root_dir,
         fps_counter::FpsCounterBundle,
     },
+    window::DisplayConfig,
 };
 
 use amethyst::prelude::{GameDataBuilder, Application};
 
 
 use amethyst_imgui::RenderImgui;
-use bountiful::systems::{CollisionSystem, DebugSystem, InputSystem};
+use bountiful::systems::{AutoTileSystemDesc, ChunkStreamingSystem, ClockSystem, CollisionSystemDesc, DebugSystem, DepthSortSystem, EditorSystem, GrowthSystemDesc, HudSystem, InputSystem, InventorySystem, LightingSystem, MapChangeSystem, MapWatchSystem, TileAnimationSystem, ToolSystem, WarpSystem};
+use bountiful::headless_bundle::HeadlessBundle;
+use bountiful::options::Options;
 use bountiful::welcome::WelcomeScreen;
 use bountiful::setup_bundle::SetupBundle;
 
 fn main() -> amethyst::Result<()> {
     amethyst::start_logger(Default::default());
 
+    let options = Options::from_args();
+
     let app_root = application_root_dir()?;
     let assets_dir = app_root.join("assets");
     let config_dir = app_root.join("config");
     let display_config_path = config_dir.join("display.ron");
     let binding_path = config_dir.join("bindings.ron");
 
+    let mut display_config = DisplayConfig::load(&display_config_path)?;
+    if let Some(dimensions) = options.window {
+        display_config.dimensions = Some(dimensions);
+    }
+
     let input_bundle = InputBundle::<StringBindings>::new()
         .with_bindings_from_file(binding_path)?;
 
-    let game_data = GameDataBuilder::default()
+    let mut game_data = GameDataBuilder::default();
+    if cfg!(debug_assertions) {
+        // Sprite sheets and textures reload when their files change.  Maps watch themselves.
+        game_data = game_data.with_bundle(HotReloadBundle::default())?;
+    }
+
+    let game_data = game_data
         .with_bundle(input_bundle)?
         .with_bundle(FpsCounterBundle::default())?
         .with_bundle(TransformBundle::new())?
-        .with_bundle(UiBundle::<StringBindings>::new())?
-        .with_bundle(
-            RenderingBundle::<DefaultBackend>::new()
-                .with_plugin(
-                    RenderToWindow::from_config_path(display_config_path)?
-                        .with_clear([0.008, 0.043, 0.067, 1.0]),
-                )
-                .with_plugin(RenderImgui::<amethyst::input::StringBindings>::default())
-                .with_plugin(RenderUi::default())
-                .with_plugin(RenderFlat2D::default())
-        )?
+        .with_bundle(UiBundle::<StringBindings>::new())?;
+
+    // Unattended runs have no window to draw in (or to take imgui's input from).
+    let (game_data, input_deps): (_, &[&str]) = if options.unattended() {
+        let dimensions = display_config.dimensions.unwrap_or((800, 600));
+        (game_data.with_bundle(HeadlessBundle { dimensions })?, &["inventory"])
+    } else {
+        let rendering = RenderingBundle::<DefaultBackend>::new()
+            .with_plugin(
+                RenderToWindow::from_config(display_config)
+                    .with_clear([0.008, 0.043, 0.067, 1.0]),
+            )
+            .with_plugin(RenderImgui::<amethyst::input::StringBindings>::default())
+            .with_plugin(RenderUi::default())
+            .with_plugin(RenderFlat2D::default());
+        (game_data.with_bundle(rendering)?, &["imgui_input_system", "inventory"])
+    };
+
+    let mut game_data = game_data
         .with_bundle(SetupBundle)?
-        .with(InputSystem::default(), "player_input", &["imgui_input_system"])
-        .with(CollisionSystem, "collisions", &["player_input"])
+        .with(InventorySystem::default(), "inventory", &[])
+        .with(InputSystem::default(), "player_input", input_deps)
+        .with_system_desc(CollisionSystemDesc::default(), "collisions", &["player_input"])
+        .with(WarpSystem, "warp", &["collisions"])
+        .with(EditorSystem::default(), "editor", &["warp"])
+        .with(ToolSystem::default(), "tools", &["warp"])
+        .with(ClockSystem::default(), "clock", &["inventory"])
+        .with_system_desc(GrowthSystemDesc::default(), "growth", &["clock", "tools"])
+        .with(LightingSystem, "lighting", &["clock"])
+        .with(HudSystem, "hud", &["clock"])
+        .with(MapChangeSystem, "map_changes", &["editor", "tools"])
+        .with_system_desc(AutoTileSystemDesc::default(), "auto_tile", &["map_changes"])
+        .with(ChunkStreamingSystem, "chunk_streaming", &["auto_tile"])
+        .with(DepthSortSystem, "depth_sort", &["chunk_streaming"])
+        .with(TileAnimationSystem, "tile_animation", &[])
         .with(DebugSystem::new(), "debug", &[]);
+    if cfg!(debug_assertions) {
+        // Maps being edited in tiled get read again when they are saved.
+        game_data = game_data.with(MapWatchSystem, "map_watch", &["warp"]);
+    }
 
-    let mut game = Application::new(
-        assets_dir,
-        WelcomeScreen::default(),
-        game_data)?;
+    let mut game = Application::build(assets_dir, WelcomeScreen::default())?
+        .with_resource(options)
+        .build(game_data)?;
     game.run();
 
     Ok(())
@@ -1,42 +1,130 @@
 use amethyst::{
-    SimpleState, GameData, StateData,
+    SimpleState, SimpleTrans, GameData, StateData, StateEvent, Trans,
     assets::{AssetStorage, Loader, Handle},
     core::transform::Transform,
     ecs::{Builder, Entity, World, WorldExt},
-    renderer::{Camera, ImageFormat, Sprite, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture},
+    input::{is_close_requested, is_key_down},
+    renderer::{Camera, Hidden, ImageFormat, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture},
+    shrev::{EventChannel, ReaderId},
+    ui::{Anchor, Stretch, UiImage, UiTransform},
+    winit::VirtualKeyCode,
 };
 use amethyst_core::transform::components::Parent;
 use amethyst_window::ScreenDimensions;
-use nalgebra::{Point3, Vector2, Vector3};
-
-use crate::components::{Player, Pointer, Position, Solid, Bound, SpriteAnimation, HotbarGui, Loose};
-use std::fs::File;
-use std::path::Path;
-use std::io::BufReader;
-use tiled::{parse_with_path, Tileset, Map};
+use log::error;
+use nalgebra::{Point3, Vector2};
+use std::fs;
+
+use crate::components::{Player, Pointer, Position, Bound, DepthSort, SpriteAnimation, HotbarGui, Inventory, ItemStack, Loose};
+use crate::generation::WALL;
+use crate::map_editor::MapEditor;
+use crate::map_loader::{load_map, reload_map};
+use crate::options::Options;
 use crate::resources::hotbar::HotbarSlot;
-use crate::resources::{Hotbar, Items};
+use crate::resources::items::{read_catalogue, sprite_count};
+use crate::resources::{slot_gui, AutoTiler, FileWatcher, GameClock, Hotbar, InventoryScreen, Items, Lighting, MapManager, NewDay, Point, Terrain,
+                       Transition, Warp, FENCE_SPRITES, MINUTES_PER_SECOND};
+use crate::save_game::{prepare, read_save, restore, save_game, save_path};
 
 #[derive(Default)]
-pub struct Bountiful;
+pub struct Bountiful {
+    player: Option<Entity>,
+    frames: u64,
+    new_days: Option<ReaderId<NewDay>>,
+}
 
 impl SimpleState for Bountiful {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         let world = data.world;
 
         world.register::<Position>();
+        let (start_map, clock_rate, slot) = {
+            let options = world.read_resource::<Options>();
+            (options.start_map(START_MAP), options.clock_rate.unwrap_or(MINUTES_PER_SECOND), options.save_slot)
+        };
+        // A bad save is left alone for whoever wants to look at it and the game starts over.
+        let save = match slot.map(|slot| (slot, read_save(slot))) {
+            Some((slot, Err(e))) => {
+                error!("Bad save {}: {}.  Starting a new game which will not be saved.", save_path(slot).display(), e);
+                world.write_resource::<Options>().save_slot = None;
+                None
+            },
+            Some((_, Ok(save))) => save,
+            None => None,
+        };
 
-        initialize_map(world);
-        let (player, player_transform) = initialize_player(world);
+        world.insert(FileWatcher::default());
+        world.insert(GameClock::new(clock_rate));
+        world.insert(AutoTiler::default().with_terrain(Terrain::four_bit(WALL, 1, FENCE_SPRITES)));
+        if let Some(save) = &save {
+            prepare(world, save);
+        }
+        let start_map = save.as_ref().map_or(start_map, |save| save.player.map_id.clone());
+        load_map(world, &start_map);
+        let (player, player_transform) = initialize_player(world, &start_map, save.as_ref().map(|save| save.player.loc()));
         let camera= initialise_camera(world, player);
         initialize_pointer(world);
-        let hotbar = Hotbar { selected: None, contents: initialize_hotbar(world, &camera, player, &player_transform) };
+        let (hotbar_slots, screen_slots) = initialize_hotbar(world, &camera, player, &player_transform);
+        let hotbar = Hotbar { selected: None, contents: hotbar_slots };
         let items = load_items(world);
+        let fade_overlay = initialize_fade_overlay(world);
+        let tint_overlay = initialize_tint_overlay(world);
 
         world.insert(items);
         world.insert(hotbar);
+        world.insert(InventoryScreen { open: false, slots: screen_slots, dragged: None });
+        world.write_resource::<MapManager>().fade_overlay = Some(fade_overlay);
+        world.write_resource::<Lighting>().overlay = Some(tint_overlay);
+
+        match &save {
+            Some(save) => restore(world, player, save),
+            None => equip_player(world, player),
+        }
+        carry_inventory(world, player);
+        self.player = Some(player);
+        self.new_days = Some(world.fetch_mut::<EventChannel<NewDay>>().register_reader());
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let warp = {
+            let mut manager = data.world.write_resource::<MapManager>();
+            if manager.transition == Transition::Swap { manager.pending.take() } else { None }
+        };
+
+        if let (Some(warp), Some(player)) = (warp, self.player) {
+            warp_player(data.world, player, &warp);
+            autosave(data.world);
+        }
+
+        let slept = match &mut self.new_days {
+            Some(reader) => data.world.read_resource::<EventChannel<NewDay>>().read(reader).any(|new_day| new_day.slept),
+            None => false,
+        };
+        if slept {
+            autosave(data.world);
+        }
+
+        self.frames += 1;
+        match data.world.read_resource::<Options>().frames {
+            Some(frames) if self.frames >= frames => Trans::Quit,
+            _ => Trans::None,
+        }
+    }
 
-        equip_player(world, player);
+    // Also runs while the `MapEditor` is on top so a map saved from tiled shows up there too.
+    fn shadow_update(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let reload = std::mem::replace(&mut data.world.write_resource::<MapManager>().reload, false);
+        if reload {
+            reload_map(data.world);
+        }
+    }
+
+    fn handle_event(&mut self, _: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match &event {
+            StateEvent::Window(event) if is_close_requested(&event) => Trans::Quit,
+            StateEvent::Window(event) if is_key_down(&event, EDITOR_KEY) => Trans::Push(Box::new(MapEditor)),
+            _ => Trans::None,
+        }
     }
 }
 
@@ -45,38 +133,77 @@ pub const TILE_HEIGHT: f32 = 64.;
 pub const WIDTH: f32 = 1000.;
 pub const HEIGHT: f32 = 1000.;
 pub const HOTBAR_SLOTS: usize = 9;
-
+pub const INVENTORY_ROWS: usize = 4; // first one is the hotbar
+pub const TILESET_PATH: &str = "assets/texture/pathetic.tsx";
+pub const ITEMS_PATH: &str = "assets/items.ron";
+pub const ITEM_SPRITES_PATH: &str = "assets/texture/items.ron";
+pub const STARTING_KIT: [(&str, u32); 4] = [("pick_axe", 1), ("hoe", 1), ("watering_can", 1), ("turnip_seeds", 10)];
+pub const START_MAP: &str = "bountiful";
+pub const START_ENTRY: &str = "start";
+pub const EDITOR_KEY: VirtualKeyCode = VirtualKeyCode::F2;
+
+pub const FADE_Z: f32 = 10.0; // ui
+pub const TINT_Z: f32 = 9.0; // ui, under the fade
 pub const CAMERA_Z: f32 = 1.0;
-pub const HOTBAR_CONTENTS_Z: f32 = 0.15;
-pub const HOTBAR_Z: f32 = 0.1;
+pub const HOTBAR_CONTENTS_Z: f32 = 0.15; // kept there by DepthSortSystem though children of the player
+pub const HOTBAR_Z: f32 = 0.1; // ditto
 pub const POINTER_Z: f32 = 0.05;
-pub const PLAYERS_Z: f32 = 0.0;
-pub const MAP_LAYERS_Z: [f32; 3] = [-0.3, -0.2, -0.1]; // base, solid, iso
+pub const SWING_Z: f32 = 0.03; // tools being used, over anything on the map
+pub const PLAYERS_Z: f32 = 0.0; // until DepthSortSystem puts them in the objects band
+pub const MAP_LAYERS_Z: [f32; 3] = [-0.3, -0.2, -0.1]; // base, solid, objects
+pub const GROUND_ITEMS_Z: f32 = -0.25; // dropped items lie on the ground under solids and objects
+pub const LAYER_NAMES: [&str; 3] = ["ground", "solids", "objects"];
+pub const OBJECTS_Z_DEPTH: f32 = 0.09; // objects band is MAP_LAYERS_Z[2] up to this much above
 
 // FIXME: Lots wrong here but this is just temporary to work in item interaction.
 fn equip_player(world: &mut World, player: Entity) {
-    let (textures, texture_id) = {
-        let items = world.read_resource::<Items>();
-        (items.textures.clone(), items.items.iter().find(|e| e.name == "Pick Axe").unwrap().texture_id)
-    };
+    let mut inventories = world.write_component::<Inventory>();
+    let inventory = inventories.get_mut(player).unwrap();
 
-    let slot_translation = world.read_resource::<Hotbar>().translation_of(world, 0).unwrap();
-    let mut transform= Transform::default();
-    transform.set_translation_xyz(slot_translation.x, slot_translation.y,HOTBAR_CONTENTS_Z);
+    for (id, quantity) in STARTING_KIT.iter() {
+        inventory.insert(ItemStack::new(id, *quantity)).unwrap_or_else(|_| panic!("No room for {}", id));
+    }
+}
 
-    let sprite_render = SpriteRender {
-        sprite_sheet: textures,
-        sprite_number: texture_id, // stationary
-    };
+// Every stack the player carries gets an entity in its slot.
+fn carry_inventory(world: &mut World, player: Entity) {
+    let stacks: Vec<(usize, ItemStack)> = world.read_component::<Inventory>().get(player).unwrap().slots.iter()
+        .enumerate()
+        .filter_map(|(slot, stack)| stack.clone().map(|stack| (slot, stack)))
+        .collect();
+
+    for (slot, stack) in stacks {
+        let (textures, texture_id) = {
+            let items = world.read_resource::<Items>();
+            (items.textures.clone(), items.get(&stack.id).unwrap_or_else(|| panic!("Catalogue without {}", stack.id)).sprite)
+        };
 
-    //println!("AX AT: {}, {}", x, y);
-    let _ax = world
-        .create_entity()
-        .with(sprite_render)
-        .with(Loose {})
-        .with(Parent { entity: player })
-        .with(transform)
-        .build();
+        let gui = {
+            let mut hotbar = world.write_resource::<Hotbar>();
+            let mut screen = world.write_resource::<InventoryScreen>();
+            slot_gui(&mut hotbar, &mut screen, slot).map(|gui| gui.hotbar_gui).expect("Inventory bigger than its screen")
+        };
+        let slot_translation = *world.read_component::<Transform>().get(gui).unwrap().translation();
+        let mut transform= Transform::default();
+        transform.set_translation_xyz(slot_translation.x, slot_translation.y,HOTBAR_CONTENTS_Z);
+
+        let sprite_render = SpriteRender {
+            sprite_sheet: textures,
+            sprite_number: texture_id, // stationary
+        };
+
+        let item = world
+            .create_entity()
+            .with(sprite_render)
+            .with(Loose {})
+            .with(Parent { entity: player })
+            .with(transform)
+            .build();
+
+        let mut hotbar = world.write_resource::<Hotbar>();
+        let mut screen = world.write_resource::<InventoryScreen>();
+        slot_gui(&mut hotbar, &mut screen, slot).unwrap().contents = Some(item);
+    }
 }
 
 fn initialise_camera(world: &mut World, player: Entity) -> Camera {
@@ -115,7 +242,9 @@ fn initialize_pointer(world: &mut World) {
     world.write_component().insert(entity, Pointer {}).unwrap();
 }
 
-fn initialize_hotbar(world: &mut World, camera: &Camera, player: Entity, player_transform: &Transform) -> Vec<HotbarSlot> {
+// The hotbar is the bottom row of slots.  The rest of the inventory sits in rows above it
+// which are hidden until the inventory screen is opened.
+fn initialize_hotbar(world: &mut World, camera: &Camera, player: Entity, player_transform: &Transform) -> (Vec<HotbarSlot>, Vec<HotbarSlot>) {
     let dims = {
         let sd = world.read_resource::<ScreenDimensions>();
         Vector2::new(sd.width(), sd.height())
@@ -126,39 +255,55 @@ fn initialize_hotbar(world: &mut World, camera: &Camera, player: Entity, player_
     let point = Point3::new(width, dims.y - TILE_HEIGHT / 2., 0.);
     let pos = camera.projection().screen_to_world_point(point, dims, player_transform);
     let mut hotbars= Vec::<HotbarSlot>::with_capacity(HOTBAR_SLOTS);
+    let mut screen = Vec::<HotbarSlot>::with_capacity((INVENTORY_ROWS - 1) * HOTBAR_SLOTS);
 
     world.register::<HotbarGui>();
-    for slot in 0..HOTBAR_SLOTS {
+    for slot in 0..INVENTORY_ROWS * HOTBAR_SLOTS {
+        let (row, column) = (slot / HOTBAR_SLOTS, slot % HOTBAR_SLOTS);
         let sprite_render = SpriteRender {
             sprite_sheet: sprite_sheet_handle.clone(),
             sprite_number: 0, // stationary
         };
 
         let mut transform = Transform::default();
-        transform.set_translation_xyz(pos.x + slot as f32 * TILE_WIDTH, pos.y, HOTBAR_Z);
+        transform.set_translation_xyz(pos.x + column as f32 * TILE_WIDTH, pos.y + row as f32 * TILE_HEIGHT, HOTBAR_Z);
 
-        let entity = world
+        let mut builder = world
             .create_entity()
             .with(sprite_render)
             .with(Parent { entity: player })
-            .with(transform)
-            .build();
+            .with(transform);
+        if row > 0 {
+            builder = builder.with(Hidden);
+        }
+        let entity = builder.build();
 
-        hotbars.push(HotbarSlot { hotbar_gui: entity, contents: None });
+        let hotbar_slot = HotbarSlot { hotbar_gui: entity, contents: None };
+        if row == 0 {
+            hotbars.push(hotbar_slot);
+        } else {
+            screen.push(hotbar_slot);
+        }
 
         world.write_component().insert(entity, HotbarGui { slot }).unwrap();
     }
 
-    hotbars
+    (hotbars, screen)
 }
 
-// FIXME: Placement/Transform should be set how once map is defined?  This will also happen when
-// changing maps.
-fn initialize_player(world: &mut World) -> (Entity, Transform) {
+// Players start at the start entry unless they were somewhere else when the game was saved.
+fn initialize_player(world: &mut World, map_id: &str, saved_loc: Option<Point>) -> (Entity, Transform) {
     let sprite_sheet_handle = load_sprite_sheet(world, "texture/player");
+    let (loc, translation) = {
+        let manager = world.read_resource::<MapManager>();
+        let info = manager.info().expect("Player created before a map was loaded");
+        let loc = saved_loc
+            .or_else(|| info.entries.get(START_ENTRY).cloned())
+            .unwrap_or_else(|| Point::new(1, info.height - 2));
+        (loc, info.world_of(&loc, PLAYERS_Z))
+    };
     let mut transform = Transform::default();
-    // FIXME: Should be a position from map to start.
-    transform.set_translation(center_of_tile(&Point3::new(TILE_WIDTH, TILE_HEIGHT, PLAYERS_Z), None));
+    transform.set_translation(translation);
 
     let sprite_render = SpriteRender {
         sprite_sheet: sprite_sheet_handle,
@@ -171,6 +316,9 @@ fn initialize_player(world: &mut World) -> (Entity, Transform) {
         .with(sprite_render)
         .with(SpriteAnimation::new_directional(1,17,9, 25, 8, 0.05))
         .with(Bound::new(28., 54.))
+        .with(DepthSort::new(27.))
+        .with(Position::new(map_id.to_string(), loc))
+        .with(Inventory::new(INVENTORY_ROWS * HOTBAR_SLOTS))
         .with(transform.clone())
         .build();
 
@@ -178,129 +326,92 @@ fn initialize_player(world: &mut World) -> (Entity, Transform) {
     (entity, transform)
 }
 
-fn load_items(world: &mut World) -> Items {
-    let sprite_sheet = load_sprite_sheet(world, "texture/items");
-    let mut items = Items::new(sprite_sheet);
-
-    items.add("Pick Axe".to_string(), 0);
+// Black screen used to fade out and back in when warping between maps.
+fn initialize_fade_overlay(world: &mut World) -> Entity {
+    let transform = UiTransform::new(
+        "fade".to_string(), Anchor::Middle, Anchor::Middle, 0., 0., FADE_Z, 1., 1.)
+        .with_stretch(Stretch::XY { x_margin: 0., y_margin: 0., keep_aspect_ratio: false })
+        .into_transparent();
 
-    items
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiImage::SolidColor([0., 0., 0., 0.]))
+        .build()
 }
 
-fn initialize_map(world: &mut World) {
-    let texture_handle = load_texture_handle(world, "texture/pathetic");
-    let map = load_tiled_map();
-    let map_tileset = map.get_tileset_by_gid(1).expect("Missing first tileset in tiled map");
-    let (tile_width, tile_height) = (map_tileset.tile_width, map_tileset.tile_height);
-    let tile_sprites = load_sprites(map_tileset, tile_width, tile_height);
+// Colored by LightingSystem for the time of day.
+fn initialize_tint_overlay(world: &mut World) -> Entity {
+    let transform = UiTransform::new(
+        "tint".to_string(), Anchor::Middle, Anchor::Middle, 0., 0., TINT_Z, 1., 1.)
+        .with_stretch(Stretch::XY { x_margin: 0., y_margin: 0., keep_aspect_ratio: false })
+        .into_transparent();
 
-    let sprite_sheet = SpriteSheet {
-        texture: texture_handle,
-        sprites: tile_sprites
-    };
+    world
+        .create_entity()
+        .with(transform)
+        .with(UiImage::SolidColor([0., 0., 0., 0.]))
+        .build()
+}
+
+// A warp to an entry the map does not have goes back to the map we came from and leaves
+// the player standing on the warp.
+fn warp_player(world: &mut World, player: Entity, warp: &Warp) {
+    let from = world.read_resource::<MapManager>().current.clone();
+    load_map(world, &warp.map_id);
 
-    let sprite_sheet_handle = {
-        let sprite_sheet_storage = world.read_resource::<AssetStorage<SpriteSheet>>();
-        world.read_resource::<Loader>().load_from_data(sprite_sheet, (), &sprite_sheet_storage)
+    let arrival = {
+        let manager = world.read_resource::<MapManager>();
+        manager.info().and_then(|info| info.entries.get(&warp.entry).map(|loc| (*loc, info.world_of(loc, PLAYERS_Z))))
     };
 
-    // Now that all the tile sprites/textures are loaded in
-    // we can start drawing the tiles for our viewing pleasure
-    // NOTE: Only rendering the first layer
-    for layer_i in 0..2 {
-        let solid = layer_i == 1;
-
-        // Reverse because screen y is bottom at 0 and tile is 0 at top.
-        for (j, row) in map.layers[layer_i].tiles.iter().rev().enumerate() {
-            for (i, tile) in row.iter().enumerate() {
-                if tile.gid == 0 { continue; } // gids 1-based. 0 means nothing.
-
-                let tile_sprite = SpriteRender {
-                    sprite_sheet: sprite_sheet_handle.clone(),
-                    sprite_number: (tile.gid - 1) as usize, // sprites are 0-based.
-                };
-
-                // adjust x/y to account for sprites being centered in amethyst.
-                let center_x_offset = tile_width as f32 / 2.0;
-                let center_y_offset = -(tile_height as i32) as f32 / 2.0;
-                let x = (i * tile_width as usize) as f32 + center_x_offset;
-                let y = (j as f32 * tile_height as f32) + tile_height as f32 + center_y_offset;
-
-                let mut tile_transform = Transform::default();
-                tile_transform.set_translation_xyz(x, y, MAP_LAYERS_Z[layer_i] as f32);
-
-                let mut tile = world
-                    .create_entity()
-                    .with(tile_transform)
-                    .with(tile_sprite);
-
-                if solid {
-                    tile = tile
-                        .with(Solid {})
-                        .with(Bound::new(tile_width as f32, tile_height as f32));
-                }
-
-                tile.build();
+    match arrival {
+        Some((loc, translation)) => {
+            world.write_component::<Transform>().get_mut(player).unwrap().set_translation(translation);
+            world.write_component().insert(player, Position::new(warp.map_id.clone(), loc)).unwrap();
+        },
+        None => {
+            error!("Missing entry {} in map {}.  Not warping.", warp.entry, warp.map_id);
+            if let Some(from) = from.filter(|from| *from != warp.map_id) {
+                load_map(world, &from);
             }
-        }
+        },
     }
+    world.write_resource::<MapManager>().transition = Transition::FadeIn;
 }
 
-fn load_sprites(map_tileset: &Tileset, sprite_w: u32, sprite_h: u32) -> Vec<Sprite> {
-    let mut tile_sprites = Vec::new();
-    let image = &map_tileset.images[0];
-    let (tileset_width, tileset_height) = (image.width, image.height);
-    let columns = (tileset_width / sprite_w as i32) as u32;
-    let rows = (tileset_height / sprite_h as i32) as u32;
-
-    for x in 0..rows {
-        for y in 0..columns {
-            // For some reason rows are columns???
-            let (pixel_top, pixel_left) = ((x * sprite_w), (y * sprite_h));
-            let offsets = [0.0; 2];
-
-            tile_sprites.push(Sprite::from_pixel_values(
-                tileset_width as u32,
-                tileset_height as u32,
-                sprite_w,
-                sprite_h,
-                pixel_left,
-                pixel_top,
-                offsets,
-                false,
-                false
-            ));
+// Saves into the slot the game was started with (if any).  A failed autosave is not worth
+// stopping the game over.
+fn autosave(world: &World) {
+    if let Some(slot) = world.read_resource::<Options>().save_slot {
+        if let Err(e) = save_game(world, slot) {
+            error!("Autosave to {} failed: {}", save_path(slot).display(), e);
         }
     }
+}
+
+pub(crate) fn load_items(world: &mut World) -> Items {
+    let sprite_sheet = load_sprite_sheet(world, "texture/items");
+    let sheet = fs::read_to_string(ITEM_SPRITES_PATH).expect("Missing item sprites");
+    let catalogue = fs::read_to_string(ITEMS_PATH).expect("Missing item catalogue");
+    let catalogue = sprite_count(&sheet)
+        .and_then(|sprite_count| read_catalogue(&catalogue, sprite_count))
+        .unwrap_or_else(|e| panic!("Bad item catalogue {}: {}", ITEMS_PATH, e));
 
-    tile_sprites
+    Items::new(sprite_sheet, catalogue)
 }
 
-fn load_texture_handle(world: &mut World, prefix: &str) -> Handle<Texture> {
+pub(crate) fn load_texture_handle(world: &mut World, prefix: &str) -> Handle<Texture> {
     let texture_storage = world.read_resource::<AssetStorage<Texture>>();
     let name = prefix.to_string() + ".png";
 
     world.read_resource::<Loader>().load(name, ImageFormat::default(), (), &texture_storage)
 }
 
-fn load_sprite_sheet(world: &mut World, prefix: &str) -> Handle<SpriteSheet> {
+pub(crate) fn load_sprite_sheet(world: &mut World, prefix: &str) -> Handle<SpriteSheet> {
     let texture_handle= load_texture_handle(world, prefix);
     let name = prefix.to_string() + ".ron";
     let sprite_sheet_store = world.read_resource::<AssetStorage<SpriteSheet>>();
 
     world.read_resource::<Loader>().load(name, SpriteSheetFormat(texture_handle), (), &sprite_sheet_store)
 }
-
-fn load_tiled_map() -> Map {
-    let file = File::open(&Path::new("assets/texture/bountiful.tmx")).unwrap();
-    let path = Path::new("assets/texture/pathetic.tsx");
-
-    parse_with_path(BufReader::new(file), path)
-        .expect("Assets missing while loading tmx")
-}
-
-pub fn center_of_tile(pos: &Point3<f32>, alternate_z: Option<f32>) -> Vector3<f32> {
-    Vector3::new((pos.x / TILE_WIDTH).floor() * TILE_WIDTH + TILE_WIDTH / 2.,
-                 (pos.y / TILE_HEIGHT).floor() * TILE_HEIGHT + TILE_HEIGHT / 2.,
-                 alternate_z.or_else(|| Some(pos.z)).unwrap())
-}
\ No newline at end of file
@@ -1,7 +1,7 @@
 use amethyst::ecs::{Component, DenseVecStorage};
 
 /// Rectangular boundary for collision detection.
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug)]
 #[storage(DenseVecStorage)]
 pub struct Bound {
     pub x: f32,
@@ -0,0 +1,62 @@
+use amethyst::ecs::{Component, DenseVecStorage};
+use serde::{Deserialize, Serialize};
+
+use crate::resources::Growth;
+
+/// Something planted from a seed.  How it grows comes from the seed's catalogue entry.
+#[derive(Component, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[storage(DenseVecStorage)]
+pub struct Crop {
+    pub seed: String, // item id
+    pub stage: usize,
+    pub days: u32, // watered days it has grown
+}
+
+impl Crop {
+    pub fn new(seed: &str) -> Self {
+        Self { seed: seed.to_string(), stage: 0, days: 0 }
+    }
+
+    /// Grow for a day.  Returns true if it reached a new stage.
+    pub fn grow(&mut self, growth: &Growth, watered: bool) -> bool {
+        if !watered || self.ready(growth) {
+            return false;
+        }
+
+        self.days += 1;
+        let stage = growth.stage_after(self.days);
+        let changed = stage != self.stage;
+        self.stage = stage;
+
+        changed
+    }
+
+    pub fn ready(&self, growth: &Growth) -> bool {
+        self.days >= growth.days
+    }
+
+    pub fn sprite(&self, growth: &Growth) -> usize {
+        growth.stages[self.stage.min(growth.stages.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::Crop;
+    use crate::resources::Growth;
+
+    #[test]
+    fn test_grow() {
+        let growth = Growth { stages: vec![5, 6, 7], days: 2, harvest: "turnip".to_string(), amount: 1 };
+        let mut crop = Crop::new("turnip_seeds");
+
+        assert!(!crop.grow(&growth, false));
+        assert!(crop.grow(&growth, true));
+        assert_eq!((crop.stage, crop.sprite(&growth)), (1, 6));
+        assert!(!crop.ready(&growth));
+        assert!(crop.grow(&growth, true));
+        assert!(crop.ready(&growth));
+        assert!(!crop.grow(&growth, true));
+        assert_eq!((crop.days, crop.sprite(&growth)), (2, 7));
+    }
+}
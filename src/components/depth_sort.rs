@@ -0,0 +1,40 @@
+use amethyst::ecs::{Component, DenseVecStorage};
+
+use crate::bountiful::{MAP_LAYERS_Z, OBJECTS_Z_DEPTH};
+
+/// Sprites on the objects layer.  Their z is worked out from where their feet are so
+/// whatever is lower on the screen gets drawn in front.
+#[derive(Component, Clone, Debug)]
+#[storage(DenseVecStorage)]
+pub struct DepthSort {
+    pub foot: f32, // distance from the center of the sprite down to its feet
+}
+
+impl DepthSort {
+    pub fn new(foot: f32) -> Self {
+        Self { foot }
+    }
+
+    /// z for a sprite centered at y in a map world_height pixels high.
+    pub fn z_for(&self, y: f32, world_height: f32) -> f32 {
+        let height = (y - self.foot) / world_height.max(1.);
+
+        MAP_LAYERS_Z[2] + (1. - height.max(0.).min(1.)) * OBJECTS_Z_DEPTH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bountiful::{MAP_LAYERS_Z, OBJECTS_Z_DEPTH};
+    use crate::components::DepthSort;
+
+    #[test]
+    fn test_z_for() {
+        let sort = DepthSort::new(32.);
+
+        assert!(sort.z_for(100., 1000.) > sort.z_for(200., 1000.));
+        assert_eq!(sort.z_for(32., 1000.), MAP_LAYERS_Z[2] + OBJECTS_Z_DEPTH);
+        assert_eq!(sort.z_for(1032., 1000.), MAP_LAYERS_Z[2]);
+        assert_eq!(sort.z_for(5000., 1000.), MAP_LAYERS_Z[2]); // clamped to the band
+    }
+}
@@ -0,0 +1,197 @@
+use amethyst::ecs::{Component, DenseVecStorage};
+use serde::{Deserialize, Serialize};
+
+/// Some number of one kind of item (by catalogue id).  As a component it is a stack lying
+/// on the map.
+#[derive(Component, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[storage(DenseVecStorage)]
+pub struct ItemStack {
+    pub id: String,
+    pub quantity: u32,
+}
+
+impl ItemStack {
+    pub fn new(id: &str, quantity: u32) -> Self {
+        Self { id: id.to_string(), quantity }
+    }
+}
+
+/// Everything someone carries.  For the player the first row of slots is the hotbar.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self { slots: vec![None; size] }
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot).and_then(|stack| stack.as_ref())
+    }
+
+    pub fn first_free(&self) -> Option<usize> {
+        self.slots.iter().position(|stack| stack.is_none())
+    }
+
+    /// Put a stack in the first free slot.  Returns the slot or gives the stack back when
+    /// there is no room.
+    pub fn insert(&mut self, stack: ItemStack) -> Result<usize, ItemStack> {
+        match self.first_free() {
+            Some(slot) => {
+                self.slots[slot] = Some(stack);
+                Ok(slot)
+            },
+            None => Err(stack),
+        }
+    }
+
+    /// Add a stack topping up stacks of the same item (up to max_stack) before using a free
+    /// slot.  Returns the last slot used or what did not fit.
+    pub fn add(&mut self, mut stack: ItemStack, max_stack: u32) -> Result<usize, ItemStack> {
+        for (slot, existing) in self.slots.iter_mut().enumerate() {
+            if let Some(existing) = existing {
+                if existing.id == stack.id && existing.quantity < max_stack {
+                    let moved = stack.quantity.min(max_stack - existing.quantity);
+                    existing.quantity += moved;
+                    stack.quantity -= moved;
+                    if stack.quantity == 0 {
+                        return Ok(slot);
+                    }
+                }
+            }
+        }
+
+        self.insert(stack)
+    }
+
+    pub fn take(&mut self, slot: usize) -> Option<ItemStack> {
+        self.slots.get_mut(slot).and_then(|stack| stack.take())
+    }
+
+    /// Take up to quantity out of a slot.  Taking all of it empties the slot.
+    pub fn take_some(&mut self, slot: usize, quantity: u32) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        if quantity < stack.quantity {
+            stack.quantity -= quantity;
+            return Some(ItemStack::new(&stack.id, quantity));
+        }
+
+        self.take(slot)
+    }
+
+    /// Move up to quantity from one slot onto another which is empty or has the same item
+    /// without going over max_stack.  Returns how many moved.
+    pub fn transfer(&mut self, from: usize, to: usize, quantity: u32, max_stack: u32) -> u32 {
+        let (id, available) = match self.get(from) {
+            Some(stack) if from != to && to < self.slots.len() => (stack.id.clone(), stack.quantity),
+            _ => return 0,
+        };
+        let room = match self.get(to) {
+            None => max_stack,
+            Some(stack) if stack.id == id => max_stack.saturating_sub(stack.quantity),
+            Some(_) => return 0,
+        };
+        let moved = quantity.min(available).min(room);
+        if moved == 0 {
+            return 0;
+        }
+
+        self.take_some(from, moved);
+        match &mut self.slots[to] {
+            Some(stack) => stack.quantity += moved,
+            empty => *empty = Some(ItemStack::new(&id, moved)),
+        }
+
+        moved
+    }
+
+    /// Move a stack into an empty slot.  Returns false if nothing moved.
+    pub fn move_stack(&mut self, from: usize, to: usize) -> bool {
+        if to >= self.slots.len() || self.get(to).is_some() {
+            return false;
+        }
+
+        match self.take(from) {
+            Some(stack) => {
+                self.slots[to] = Some(stack);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Swap what is in two slots (either may be empty).  Returns false if either is missing.
+    pub fn swap(&mut self, a: usize, b: usize) -> bool {
+        if a >= self.slots.len() || b >= self.slots.len() {
+            return false;
+        }
+
+        self.slots.swap(a, b);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::inventory::*;
+
+    #[test]
+    fn test_inventory() {
+        let mut inventory = Inventory::new(2);
+
+        assert_eq!(inventory.insert(ItemStack::new("pick_axe", 1)), Ok(0));
+        assert!(inventory.move_stack(0, 1));
+        assert_eq!(inventory.insert(ItemStack::new("stone", 3)), Ok(0));
+        assert_eq!(inventory.insert(ItemStack::new("stone", 1)), Err(ItemStack::new("stone", 1)));
+
+        // Occupied, missing and empty slots do not move.
+        assert!(!inventory.move_stack(0, 1));
+        assert!(!inventory.move_stack(0, 2));
+        assert_eq!(inventory.take(1), Some(ItemStack::new("pick_axe", 1)));
+        assert!(!inventory.move_stack(1, 0));
+        assert_eq!(inventory.first_free(), Some(1));
+    }
+
+    #[test]
+    fn test_add() {
+        let mut inventory = Inventory::new(2);
+
+        assert_eq!(inventory.add(ItemStack::new("stone", 7), 10), Ok(0));
+        assert_eq!(inventory.add(ItemStack::new("stone", 5), 10), Ok(1)); // 3 on top, 2 in a new stack
+        assert_eq!(inventory.add(ItemStack::new("stone", 9), 10), Err(ItemStack::new("stone", 1)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new("stone", 10)));
+        assert_eq!(inventory.add(ItemStack::new("wood", 1), 10), Err(ItemStack::new("wood", 1)));
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut inventory = Inventory::new(3);
+        inventory.insert(ItemStack::new("stone", 9)).unwrap();
+        inventory.insert(ItemStack::new("wood", 2)).unwrap();
+
+        assert_eq!(inventory.transfer(0, 2, 4, 10), 4); // split into an empty slot
+        assert_eq!(inventory.transfer(0, 2, 5, 8), 4); // merge back up to the limit
+        assert_eq!(inventory.get(2), Some(&ItemStack::new("stone", 8)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 1)));
+        assert_eq!(inventory.transfer(2, 1, 1, 10), 0); // different items
+        assert_eq!(inventory.take_some(2, 3), Some(ItemStack::new("stone", 3)));
+        assert_eq!(inventory.take_some(2, 10), Some(ItemStack::new("stone", 5)));
+        assert_eq!(inventory.get(2), None);
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut inventory = Inventory::new(3);
+        inventory.insert(ItemStack::new("pick_axe", 1)).unwrap();
+        inventory.insert(ItemStack::new("stone", 3)).unwrap();
+
+        assert!(inventory.swap(0, 1));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new("stone", 3)));
+        assert!(inventory.swap(1, 2)); // into an empty slot is a move
+        assert_eq!(inventory.get(2), Some(&ItemStack::new("pick_axe", 1)));
+        assert!(!inventory.swap(0, 3));
+    }
+}
@@ -1,5 +1,8 @@
 pub mod bound;
+pub mod crop;
+pub mod depth_sort;
 pub mod hotbar_gui;
+pub mod inventory;
 pub mod loose;
 pub mod position;
 pub mod proposed_move;
@@ -7,13 +10,18 @@ pub mod player;
 pub mod pointer;
 pub mod solid;
 pub mod sprite_animation;
+pub mod tile_animation;
 
 pub use self::bound::Bound;
+pub use self::crop::Crop;
+pub use self::depth_sort::DepthSort;
 pub use self::hotbar_gui::HotbarGui;
+pub use self::inventory::{Inventory, ItemStack};
 pub use self::loose::Loose;
 pub use self::player::Player;
 pub use self::pointer::Pointer;
 pub use self::position::Position;
 pub use self::proposed_move::{ProposedMove, ProposedMoveType};
 pub use self::solid::Solid;
-pub use self::sprite_animation::SpriteAnimation;
\ No newline at end of file
+pub use self::sprite_animation::SpriteAnimation;
+pub use self::tile_animation::{TileAnimation, TileFrame};
\ No newline at end of file
@@ -1,7 +1,7 @@
 use crate::resources::Point;
 use amethyst::ecs::{Component, DenseVecStorage};
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug, PartialEq)]
 #[storage(DenseVecStorage)]
 pub struct Position {
     pub map_id: String,
@@ -1,5 +1,5 @@
 use amethyst::ecs::{Component, DenseVecStorage};
 
-#[derive(Component, Debug)]
+#[derive(Component, Clone, Debug)]
 #[storage(DenseVecStorage)]
 pub struct Solid {}
\ No newline at end of file
@@ -0,0 +1,67 @@
+use amethyst::ecs::{Component, DenseVecStorage};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct TileFrame {
+    pub sprite_number: usize,
+    pub duration: u32, // milliseconds
+}
+
+/// Frame animation of a map tile as defined by `<animation>` in a Tiled tileset.
+/// Frames are shared between every tile using the same animation and the current
+/// frame is derived from the absolute game time so they all stay in sync.
+#[derive(Component, Clone, Debug)]
+#[storage(DenseVecStorage)]
+pub struct TileAnimation {
+    frames: Arc<Vec<TileFrame>>,
+    total_duration: u64,
+}
+
+impl TileAnimation {
+    pub fn new(frames: Vec<TileFrame>) -> Self {
+        let total_duration = frames.iter().map(|f| f.duration as u64).sum();
+
+        Self {
+            frames: Arc::new(frames),
+            total_duration,
+        }
+    }
+
+    /// Sprite to render at some absolute time (in milliseconds).
+    pub fn sprite_at(&self, millis: u64) -> usize {
+        if self.total_duration == 0 {
+            return self.frames[0].sprite_number;
+        }
+
+        let mut elapsed = millis % self.total_duration;
+        for frame in self.frames.iter() {
+            if elapsed < frame.duration as u64 {
+                return frame.sprite_number;
+            }
+            elapsed -= frame.duration as u64;
+        }
+
+        self.frames[self.frames.len() - 1].sprite_number
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::{TileAnimation, TileFrame};
+
+    #[test]
+    fn test_sprite_at() {
+        let animation = TileAnimation::new(vec![
+            TileFrame { sprite_number: 4, duration: 100 },
+            TileFrame { sprite_number: 5, duration: 50 },
+            TileFrame { sprite_number: 6, duration: 100 },
+        ]);
+
+        assert_eq!(animation.sprite_at(0), 4);
+        assert_eq!(animation.sprite_at(99), 4);
+        assert_eq!(animation.sprite_at(100), 5);
+        assert_eq!(animation.sprite_at(149), 5);
+        assert_eq!(animation.sprite_at(150), 6);
+        assert_eq!(animation.sprite_at(250), 4); // wraps
+    }
+}
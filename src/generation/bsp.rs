@@ -0,0 +1,98 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::generation::{Generator, FLOOR, WALL};
+use crate::resources::{Map, Point, Tile};
+
+/// Rooms and corridors from binary space partitioning.  The map is split in two until the
+/// pieces get too small, each piece gets a room, and siblings are joined by corridors.
+pub struct BspGenerator {
+    pub min_leaf: usize,
+    pub min_room: usize,
+}
+
+impl Default for BspGenerator {
+    fn default() -> Self {
+        Self {
+            min_leaf: 10,
+            min_room: 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Rect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl BspGenerator {
+    // Returns center of a room within rect so our parent can run a corridor to it.
+    fn split(&self, map: &mut Map, rect: Rect, rng: &mut StdRng) -> Point {
+        let can_split_x = rect.width >= self.min_leaf * 2;
+        let can_split_y = rect.height >= self.min_leaf * 2;
+
+        let (first, second) = match (can_split_x, can_split_y) {
+            (false, false) => return self.carve_room(map, rect, rng),
+            (true, true) if rng.gen_bool(0.5) => Self::split_x(rect, rng.gen_range(self.min_leaf, rect.width - self.min_leaf + 1)),
+            (true, _) => Self::split_x(rect, rng.gen_range(self.min_leaf, rect.width - self.min_leaf + 1)),
+            (_, true) => Self::split_y(rect, rng.gen_range(self.min_leaf, rect.height - self.min_leaf + 1)),
+        };
+
+        let a = self.split(map, first, rng);
+        let b = self.split(map, second, rng);
+        Self::carve_corridor(map, &a, &b, rng.gen_bool(0.5));
+
+        if rng.gen_bool(0.5) { a } else { b }
+    }
+
+    fn split_x(rect: Rect, at: usize) -> (Rect, Rect) {
+        (Rect { width: at, ..rect }, Rect { x: rect.x + at, width: rect.width - at, ..rect })
+    }
+
+    fn split_y(rect: Rect, at: usize) -> (Rect, Rect) {
+        (Rect { height: at, ..rect }, Rect { y: rect.y + at, height: rect.height - at, ..rect })
+    }
+
+    // Rooms keep a wall between themselves and the edge of their rect.
+    fn carve_room(&self, map: &mut Map, rect: Rect, rng: &mut StdRng) -> Point {
+        let max_width = rect.width.saturating_sub(2).max(1);
+        let max_height = rect.height.saturating_sub(2).max(1);
+        let width = rng.gen_range(self.min_room.min(max_width), max_width + 1);
+        let height = rng.gen_range(self.min_room.min(max_height), max_height + 1);
+        let x = rect.x + 1 + rng.gen_range(0, max_width - width + 1);
+        let y = rect.y + 1 + rng.gen_range(0, max_height - height + 1);
+
+        for j in y..(y + height).min(map.height() - 1) {
+            for i in x..(x + width).min(map.width() - 1) {
+                map.set_at(&Point::new(i, j), Tile::new(FLOOR, 1)).unwrap();
+            }
+        }
+
+        Point::new(x + width / 2, y + height / 2)
+    }
+
+    fn carve_corridor(map: &mut Map, a: &Point, b: &Point, horizontal_first: bool) {
+        let corner = if horizontal_first { Point::new(b.x, a.y) } else { Point::new(a.x, b.y) };
+
+        for (from, to) in &[(*a, corner), (corner, *b)] {
+            for x in from.x.min(to.x)..=from.x.max(to.x) {
+                for y in from.y.min(to.y)..=from.y.max(to.y) {
+                    map.set_at(&Point::new(x, y), Tile::new(FLOOR, 1)).unwrap();
+                }
+            }
+        }
+    }
+}
+
+impl Generator for BspGenerator {
+    fn generate(&self, width: usize, height: usize, rng: &mut StdRng) -> Map {
+        let mut map = Map::new(width, height, WALL, 1);
+
+        self.split(&mut map, Rect { x: 0, y: 0, width, height }, rng);
+
+        map
+    }
+}
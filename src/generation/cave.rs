@@ -0,0 +1,75 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::generation::{Generator, FLOOR, WALL};
+use crate::resources::{Map, Point, Tile};
+
+/// Caves from cellular automata.  Start with random noise and repeatedly turn a tile into
+/// wall when enough of its neighbours are walls (the classic 4-5 rule).
+pub struct CaveGenerator {
+    pub wall_chance: f64,
+    pub iterations: usize,
+    pub wall_limit: usize,
+}
+
+impl Default for CaveGenerator {
+    fn default() -> Self {
+        Self {
+            wall_chance: 0.45,
+            iterations: 5,
+            wall_limit: 5,
+        }
+    }
+}
+
+impl CaveGenerator {
+    // Edges of the map count as walls so caves do not leak out of the map.
+    fn walls_around(walls: &[bool], width: usize, height: usize, x: usize, y: usize) -> usize {
+        let mut count = 0;
+
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height ||
+                    walls[ny as usize * width + nx as usize] {
+                    count += 1;
+                }
+            }
+        }
+
+        count
+    }
+}
+
+impl Generator for CaveGenerator {
+    fn generate(&self, width: usize, height: usize, rng: &mut StdRng) -> Map {
+        let on_edge = |x: usize, y: usize| x == 0 || y == 0 || x == width - 1 || y == height - 1;
+        let mut walls: Vec<bool> = (0..width * height)
+            .map(|i| on_edge(i % width, i / width) || rng.gen_bool(self.wall_chance))
+            .collect();
+
+        for _ in 0..self.iterations {
+            walls = (0..width * height)
+                .map(|i| {
+                    let (x, y) = (i % width, i / width);
+                    let around = Self::walls_around(&walls, width, height, x, y);
+                    // Walls survive with one less neighbouring wall than it takes to grow one.
+                    on_edge(x, y) || around >= self.wall_limit || (walls[i] && around + 1 >= self.wall_limit)
+                })
+                .collect();
+        }
+
+        let mut map = Map::new(width, height, FLOOR, 1);
+        for (i, wall) in walls.iter().enumerate() {
+            if *wall {
+                map.set_at(&Point::new(i % width, i / width), Tile::new(WALL, 1)).unwrap();
+            }
+        }
+
+        map
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::VecDeque;
+
+use crate::generation::{FLOOR, WALL};
+use crate::resources::{Map, Point, Tile};
+
+/// All groups of floor tiles which can reach each other, largest first.
+pub fn floor_regions(map: &Map) -> Vec<Vec<Point>> {
+    map.regions(|tile| tile.id == FLOOR)
+}
+
+/// Carve corridors through walls until there is only one region of floor left.  Each pass
+/// digs the shortest tunnel from the smallest region to whichever floor it reaches first.
+pub fn connect_regions(map: &mut Map) {
+    loop {
+        let regions = floor_regions(map);
+        if regions.len() <= 1 {
+            return;
+        }
+
+        let region = regions.last().unwrap();
+        let mut parents: Vec<Option<Point>> = vec![None; map.width() * map.height()];
+        let mut visited = vec![false; map.width() * map.height()];
+        let mut queue: VecDeque<Point> = region.iter().cloned().collect();
+        let mut found = None;
+
+        for loc in region {
+            visited[loc.y * map.width() + loc.x] = true;
+        }
+
+        'search: while let Some(loc) = queue.pop_front() {
+            for next in map.neighbours(&loc) {
+                let index = next.y * map.width() + next.x;
+                if visited[index] {
+                    continue;
+                }
+
+                visited[index] = true;
+                parents[index] = Some(loc);
+
+                if map.at(&next).map_or(false, |tile| tile.id == FLOOR) {
+                    found = Some(next);
+                    break 'search;
+                }
+                queue.push_back(next);
+            }
+        }
+
+        // Walk back from the floor we found to the region digging as we go.
+        let mut current = found.expect("No other region to connect to");
+        while let Some(parent) = parents[current.y * map.width() + current.x] {
+            if map.at(&parent).map_or(false, |tile| tile.id == WALL) {
+                map.set_at(&parent, Tile::new(FLOOR, 1)).unwrap();
+            }
+            current = parent;
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Procedural map generation.  A `Generator` fills in a `Map` of floors and walls from a
+//! seeded rng so the same seed always builds the same map.  After generating we run a
+//! connectivity pass so every floor tile can be walked to from every other one.
+pub mod bsp;
+pub mod cave;
+pub mod connectivity;
+pub mod terrain;
+
+pub use self::bsp::BspGenerator;
+pub use self::cave::CaveGenerator;
+pub use self::connectivity::{connect_regions, floor_regions};
+pub use self::terrain::TerrainGenerator;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::resources::{Map, Square4};
+
+pub const FLOOR: char = '.';
+pub const WALL: char = '#';
+/// Blocks like a wall but is not a fence so auto tiling never touches it.
+pub const SOLID: char = '%';
+
+pub const GENERATED_WIDTH: usize = 64;
+pub const GENERATED_HEIGHT: usize = 64;
+
+pub trait Generator {
+    fn generate(&self, width: usize, height: usize, rng: &mut StdRng) -> Map;
+}
+
+/// Generated maps only connect through tile sides.  A gap between two corners is too
+/// narrow to walk through.
+pub fn generate(generator: &dyn Generator, width: usize, height: usize, seed: u64) -> Map {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut map = generator.generate(width, height, &mut rng).with_topology(Box::new(Square4));
+
+    connect_regions(&mut map);
+
+    map
+}
+
+/// Maps with an id of `<generator>:<seed>` (e.g. `cave:42`) are generated instead of
+/// being read from a tmx.
+pub fn generate_from_id(map_id: &str) -> Option<Map> {
+    let (generator, seed) = parse_id(map_id)?;
+
+    Some(generate(generator.as_ref(), GENERATED_WIDTH, GENERATED_HEIGHT, seed))
+}
+
+/// Would `generate_from_id` make a map for map_id.  Without generating it.
+pub fn is_generated(map_id: &str) -> bool {
+    parse_id(map_id).is_some()
+}
+
+fn parse_id(map_id: &str) -> Option<(Box<dyn Generator>, u64)> {
+    let mut parts = map_id.splitn(2, ':');
+    let generator = generator(parts.next()?)?;
+    let seed = parts.next()?.parse().ok()?;
+
+    Some((generator, seed))
+}
+
+/// The generator used for map ids starting with `kind`.
+pub fn generator(kind: &str) -> Option<Box<dyn Generator>> {
+    match kind {
+        "cave" => Some(Box::new(CaveGenerator::default())),
+        "bsp" => Some(Box::new(BspGenerator::default())),
+        "terrain" => Some(Box::new(TerrainGenerator::default())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generation::{floor_regions, generate, generate_from_id, is_generated, BspGenerator, CaveGenerator, Generator, TerrainGenerator};
+
+    fn generators() -> Vec<Box<dyn Generator>> {
+        vec![Box::new(CaveGenerator::default()), Box::new(BspGenerator::default()), Box::new(TerrainGenerator::default())]
+    }
+
+    #[test]
+    fn test_same_seed_same_map() {
+        for generator in generators() {
+            let first = generate(generator.as_ref(), 40, 30, 7);
+            let second = generate(generator.as_ref(), 40, 30, 7);
+
+            assert_eq!(first.to_string(), second.to_string());
+            assert_eq!((first.width(), first.height()), (40, 30));
+        }
+    }
+
+    #[test]
+    fn test_all_floors_connected() {
+        for generator in generators() {
+            for seed in 0..5 {
+                let map = generate(generator.as_ref(), 48, 48, seed);
+                let regions = floor_regions(&map);
+
+                assert_eq!(regions.len(), 1, "seed {} has disconnected floors:\n{}", seed, map);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_from_id() {
+        assert!(generate_from_id("cave:12").is_some());
+        assert!(generate_from_id("bountiful").is_none());
+        assert!(generate_from_id("cave:abc").is_none());
+        assert!(generate_from_id("volcano:12").is_none());
+        assert!(is_generated("bsp:3") && !is_generated("bsp:") && !is_generated("bountiful"));
+    }
+}
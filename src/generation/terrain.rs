@@ -0,0 +1,79 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::generation::{Generator, FLOOR, WALL};
+use crate::resources::{Map, Point, Tile};
+
+/// Open terrain from fractal value noise.  Anything higher than `rock_level` becomes rock.
+pub struct TerrainGenerator {
+    pub scale: f64,
+    pub octaves: usize,
+    pub rock_level: f64,
+}
+
+impl Default for TerrainGenerator {
+    fn default() -> Self {
+        Self {
+            scale: 12.,
+            octaves: 4,
+            rock_level: 0.62,
+        }
+    }
+}
+
+impl TerrainGenerator {
+    // Random but repeatable value in [0, 1) for a lattice point.
+    fn lattice(seed: u64, x: i64, y: i64) -> f64 {
+        let mut h = seed
+            .wrapping_add((x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            .wrapping_add((y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+
+        (h >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn value_noise(seed: u64, x: f64, y: f64) -> f64 {
+        let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+        let smooth = |t: f64| t * t * (3. - 2. * t);
+        let (tx, ty) = (smooth(x - x0 as f64), smooth(y - y0 as f64));
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        let top = lerp(Self::lattice(seed, x0, y0), Self::lattice(seed, x0 + 1, y0), tx);
+        let bottom = lerp(Self::lattice(seed, x0, y0 + 1), Self::lattice(seed, x0 + 1, y0 + 1), tx);
+
+        lerp(top, bottom, ty)
+    }
+
+    fn height_at(&self, seed: u64, x: usize, y: usize) -> f64 {
+        let (mut total, mut amplitude, mut frequency, mut max) = (0., 1., 1. / self.scale, 0.);
+
+        for octave in 0..self.octaves {
+            total += Self::value_noise(seed.wrapping_add(octave as u64), x as f64 * frequency, y as f64 * frequency) * amplitude;
+            max += amplitude;
+            amplitude /= 2.;
+            frequency *= 2.;
+        }
+
+        total / max
+    }
+}
+
+impl Generator for TerrainGenerator {
+    fn generate(&self, width: usize, height: usize, rng: &mut StdRng) -> Map {
+        let seed = rng.gen::<u64>();
+        let mut map = Map::new(width, height, FLOOR, 1);
+
+        for y in 0..height {
+            for x in 0..width {
+                let edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+                if edge || self.height_at(seed, x, y) > self.rock_level {
+                    map.set_at(&Point::new(x, y), Tile::new(WALL, 1)).unwrap();
+                }
+            }
+        }
+
+        map
+    }
+}
@@ -0,0 +1,23 @@
+use amethyst::assets::{AssetStorage, Processor};
+use amethyst::core::SystemBundle;
+use amethyst::ecs::{DispatcherBuilder, World};
+use amethyst::renderer::{SpriteSheet, Texture};
+use amethyst::Result;
+use amethyst_window::ScreenDimensions;
+
+/// Takes the place of the `RenderingBundle` for unattended runs so they need neither a
+/// window nor a GPU.  Nothing gets drawn but everything which asks about the screen or
+/// sprite sheets gets an answer.  Textures are never uploaded so they stay loading.
+pub struct HeadlessBundle {
+    pub dimensions: (u32, u32),
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for HeadlessBundle {
+    fn build(self, world: &mut World, builder: &mut DispatcherBuilder<'a, 'b>) -> Result<()> {
+        let (width, height) = self.dimensions;
+        world.insert(ScreenDimensions::new(width, height, 1.));
+        world.insert(AssetStorage::<Texture>::new());
+        builder.add(Processor::<SpriteSheet>::new(), "sprite_sheet_processor", &[]);
+        Ok(())
+    }
+}
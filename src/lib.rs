@@ -1,6 +1,13 @@
 pub mod bountiful;
 pub mod components;
+pub mod generation;
+pub mod headless_bundle;
+pub mod map_editor;
+pub mod map_loader;
+pub mod map_writer;
+pub mod options;
 pub mod resources;
+pub mod save_game;
 pub mod setup_bundle;
 pub mod systems;
 pub mod welcome;
\ No newline at end of file
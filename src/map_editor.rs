@@ -0,0 +1,76 @@
+use amethyst::{
+    input::{is_close_requested, is_key_down},
+    prelude::{GameData, SimpleState, SimpleTrans, StateData, StateEvent, Trans, WorldExt},
+};
+use amethyst_imgui::ImguiState;
+
+use crate::bountiful::{load_texture_handle, EDITOR_KEY};
+use crate::map_loader::{apply_edits, map_file_source, nav_map};
+use crate::map_writer::save_map;
+use crate::resources::{AutoTiler, Editor, MapManager, WorldChunks};
+
+/// Pushed on top of `Bountiful` to change the current map in place.  The `EditorSystem`
+/// does the actual editing while this is on top.
+pub struct MapEditor;
+
+impl SimpleState for MapEditor {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        // The palette shows tiles straight from the tileset so imgui needs its texture.
+        if data.world.read_resource::<Editor>().palette.is_none() {
+            let texture = load_texture_handle(data.world, "texture/pathetic");
+            let mut imgui = data.world.write_resource::<ImguiState>();
+            imgui.textures.push(texture);
+            data.world.write_resource::<Editor>().palette = Some(imgui.textures.len() - 1);
+        }
+
+        data.world.write_resource::<Editor>().active = true;
+    }
+
+    fn on_stop(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let mut editor = data.world.write_resource::<Editor>();
+        editor.active = false;
+        editor.anchor = None;
+    }
+
+    fn handle_event(&mut self, _: StateData<'_, GameData<'_, '_>>, event: StateEvent) -> SimpleTrans {
+        match &event {
+            StateEvent::Window(event) if is_close_requested(&event) => Trans::Quit,
+            StateEvent::Window(event) if is_key_down(&event, EDITOR_KEY) => Trans::Pop,
+            _ => Trans::None,
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let save_requested = std::mem::replace(&mut data.world.write_resource::<Editor>().save_requested, false);
+
+        if save_requested {
+            let status = {
+                let manager = data.world.read_resource::<MapManager>();
+                let tiler = data.world.read_resource::<AutoTiler>();
+                let mut chunks = data.world.write_resource::<WorldChunks>();
+                // Edits go on top of the map file as it is now so farm plots and mined tiles
+                // never get saved.  Fences next to edited solids are tiled again.
+                let saved = manager.info().map(|info| {
+                    let mut source = map_file_source(&tiler, &info.id);
+                    let mut nav = nav_map(&tiler, &source);
+                    let changed = apply_edits(&tiler, &mut source, &mut nav, &chunks.edits_of(&info.id));
+                    for update in tiler.refresh(&nav, &source, &changed) {
+                        source.set_gid(update.layer, &update.loc, update.gid);
+                    }
+                    (info.id.clone(), save_map(info, &source))
+                });
+                match saved {
+                    Some((map_id, Ok(path))) => {
+                        chunks.edits.retain(|(id, _, _), _| *id != map_id); // the map file has them now
+                        format!("Saved {}", path.display())
+                    },
+                    Some((_, Err(e))) => format!("Save failed: {}", e),
+                    None => "No map to save".to_string(),
+                }
+            };
+            data.world.write_resource::<Editor>().status = status;
+        }
+
+        Trans::None
+    }
+}
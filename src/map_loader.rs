@@ -0,0 +1,565 @@
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    ecs::{World, WorldExt},
+    renderer::{Sprite, SpriteSheet},
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tiled::{parse_tileset, parse_with_path, Object, PropertyValue, Tileset};
+use log::error;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::bountiful::{load_texture_handle, MAP_LAYERS_Z, START_ENTRY, TILESET_PATH};
+use crate::generation::{floor_regions, generate_from_id, is_generated, FLOOR, SOLID, WALL};
+use crate::components::{TileAnimation, TileFrame};
+use crate::resources::{AutoTiler, Farm, Gids, Map, MapInfo, MapManager, MapSource, Mineable, Mining, Orientation, Point, Tile, Warp, WorldChunks};
+
+pub const MAP_DIR: &str = "assets/texture";
+pub const GROUND_GIDS: (u32, u32) = (1, 4); // first and last plain ground tile
+pub const WALL_GID: u32 = 5; // lone fence post
+
+/// Load the map `map_id` and make it the current map of the `MapManager`.  Generated map
+/// ids (see `generation::generate_from_id`) are generated and anything else is read from a
+/// tmx file in MAP_DIR.  Whatever map was loaded before is unloaded first.
+pub fn load_map(world: &mut World, map_id: &str) {
+    unload_map(world);
+
+    if let Some(nav) = generate_from_id(map_id) {
+        load_generated_map(world, map_id, nav);
+        return;
+    }
+
+    let map = load_tiled_map(map_id);
+    let map_tileset = map.get_tileset_by_gid(1).expect("Missing first tileset in tiled map");
+    let (info, source, nav) = read_tiled_map(map_id, &map, &world.read_resource::<AutoTiler>());
+
+    install_map(world, map_tileset, info, source, nav);
+}
+
+/// Read the current map (and the tileset) from its tmx again and show it in place.  Anybody
+/// on the map stays where they are.  What was stored of the map goes so farm plots, mined
+/// tiles and editor changes which were not saved are put back by `install_map` like on a
+/// first visit.
+pub fn reload_map(world: &mut World) {
+    let map_id = match world.read_resource::<MapManager>().current.clone() {
+        Some(map_id) => map_id,
+        None => return,
+    };
+
+    unload_map(world);
+    world.write_resource::<WorldChunks>().forget(&map_id);
+    world.remove::<TilesetExtras>(); // the tileset may have changed too
+    load_map(world, &map_id);
+}
+
+/// The map file of map_id as it is on disk (or how a generated map comes out).
+pub fn map_file_source(tiler: &AutoTiler, map_id: &str) -> MapSource {
+    match generate_from_id(map_id) {
+        Some(nav) => generated_source(tiler, map_id, &nav),
+        None => read_tiled_map(map_id, &load_tiled_map(map_id), tiler).1,
+    }
+}
+
+/// Put editor changes by (loc, layer) onto a freshly read map.  Changes off a map which
+/// got smaller in tiled are dropped.  Returns the solids which changed so fences next to
+/// them can be auto tiled again.
+pub fn apply_edits(tiler: &AutoTiler, source: &mut MapSource, nav: &mut Map, edits: &HashMap<(Point, usize), u32>) -> Vec<Point> {
+    let mut changed = vec![];
+
+    for ((loc, layer), gid) in edits.iter().filter(|((loc, layer), _)| *layer < source.layers.len() && source.gid_at(*layer, loc).is_some()) {
+        source.set_gid(*layer, loc, *gid);
+        if *layer == 1 {
+            nav.set_at(loc, nav_tile(tiler, *gid)).unwrap();
+            changed.push(*loc);
+        }
+    }
+
+    changed
+}
+
+/// Pull what we need out of a tiled map: its tile layers, where its entries and warps
+/// are and which tiles are solid.  Which solids are terrain the tiler decides by gid.
+/// Warps without an entry or to a map which does not exist are left out.
+pub fn read_tiled_map(map_id: &str, map: &tiled::Map, tiler: &AutoTiler) -> (MapInfo, MapSource, Map) {
+    let map_tileset = map.get_tileset_by_gid(1).expect("Missing first tileset in tiled map");
+    let (width, height) = (map.width as usize, map.height as usize);
+
+    // Only the first three layers are used: ground, solids and objects.
+    let mut layers: Vec<Vec<u32>> = map.layers.iter().take(MAP_LAYERS_Z.len())
+        .map(|layer| layer.tiles.iter().flat_map(|row| row.iter().map(|tile| tile.gid)).collect())
+        .collect();
+    layers.resize(MAP_LAYERS_Z.len(), vec![0; width * height]);
+    let mut source = MapSource { width, height, layers };
+    let nav = nav_map(tiler, &source);
+
+    let orientation = match map.orientation {
+        tiled::Orientation::Orthogonal => Orientation::Orthogonal,
+        tiled::Orientation::Isometric => Orientation::Isometric,
+        other => panic!("{:?} maps are not supported: {}", other, map_id),
+    };
+    let mut info = map_info(map_id, width, height, map_tileset, (map.tile_width, map.tile_height), orientation);
+
+    for object in map.object_groups.iter().flat_map(|group| group.objects.iter()) {
+        match object.obj_type.as_str() {
+            "entry" => {
+                if let Some(loc) = object_points(object, &info).first() {
+                    info.entries.insert(object.name.clone(), *loc);
+                }
+            },
+            "warp" => {
+                let target = string_property(object, "map").unwrap_or_else(|| map_id.to_string());
+                let entry = match string_property(object, "entry") {
+                    Some(entry) => entry,
+                    None => {
+                        error!("Warp {} in map {} has no entry", object.name, map_id);
+                        continue;
+                    },
+                };
+                if target != map_id && !is_generated(&target) && !tmx_path(&target).is_file() {
+                    error!("Warp {} in map {} goes to {} which does not exist", object.name, map_id, target);
+                    continue;
+                }
+
+                let warp = Warp { map_id: target, entry };
+                for loc in object_points(object, &info) {
+                    info.warps.insert(loc, warp.clone());
+                }
+            },
+            _ => {
+                if let Some(feet) = object_feet(object, &info) {
+                    source.set_gid(2, &feet, object.gid);
+                }
+            },
+        }
+    }
+
+    (info, source, nav)
+}
+
+/// Render a generated map with our tileset.  Floors get a random ground tile and walls
+/// become auto tiled fences on the solids layer.  The player arrives at the "start" entry
+/// which is the first floor tile.
+pub fn load_generated_map(world: &mut World, map_id: &str, nav: Map) {
+    let tileset = load_tileset();
+    let source = generated_source(&world.read_resource::<AutoTiler>(), map_id, &nav);
+
+    let tile_size = (tileset.tile_width, tileset.tile_height);
+    let mut info = map_info(map_id, nav.width(), nav.height(), &tileset, tile_size, Orientation::Orthogonal);
+    if let Some(start) = floor_regions(&nav).first().and_then(|region| region.first()) {
+        info.entries.insert(START_ENTRY.to_string(), *start);
+    }
+
+    install_map(world, &tileset, info, source, nav);
+}
+
+// Tile layers of a generated map.
+fn generated_source(tiler: &AutoTiler, map_id: &str, nav: &Map) -> MapSource {
+    let (width, height) = (nav.width(), nav.height());
+    let seed = map_id.splitn(2, ':').nth(1).and_then(|seed| seed.parse().ok()).unwrap_or(0);
+    let mut rng = StdRng::seed_from_u64(seed); // same as the map so it always looks the same
+    let mut ground = Vec::with_capacity(width * height);
+    let mut solids = Vec::with_capacity(width * height);
+
+    for (loc, tile) in nav.iter() {
+        ground.push(rng.gen_range(GROUND_GIDS.0, GROUND_GIDS.1 + 1));
+        solids.push(if tile.id == WALL { tiler.gid_at(nav, &loc, 1).unwrap_or(WALL_GID) } else { 0 });
+    }
+
+    MapSource { width, height, layers: vec![ground, solids] }
+}
+
+// Tile size is the size of a tile on the map which can be smaller than the tileset sprites.
+fn map_info(map_id: &str, width: usize, height: usize, tileset: &Tileset, (tile_width, tile_height): (u32, u32),
+            orientation: Orientation) -> MapInfo {
+    MapInfo {
+        id: map_id.to_string(),
+        width,
+        height,
+        tile_width: tile_width as f32,
+        tile_height: tile_height as f32,
+        orientation,
+        sprite_offset: (tileset.tile_height as f32 - tile_height as f32) / 2.,
+        entries: HashMap::new(),
+        warps: HashMap::new(),
+    }
+}
+
+// Tiles are spawned by the ChunkStreamingSystem as the camera gets near them.  A map which
+// is not in the chunk store yet goes in with farm plots, mined tiles and editor changes on
+// top of its map file.
+fn install_map(world: &mut World, tileset: &Tileset, info: MapInfo, mut source: MapSource, mut nav: Map) {
+    let texture_handle = load_texture_handle(world, "texture/pathetic");
+    let tile_sprites = load_sprites(tileset, tileset.tile_width, tileset.tile_height, info.sprite_offset);
+    let extras = tileset_extras(world);
+    let map_id = info.id.clone();
+
+    let sprite_sheet = SpriteSheet {
+        texture: texture_handle,
+        sprites: tile_sprites
+    };
+
+    let sprite_sheet_handle = {
+        let sprite_sheet_storage = world.read_resource::<AssetStorage<SpriteSheet>>();
+        world.read_resource::<Loader>().load_from_data(sprite_sheet, (), &sprite_sheet_storage)
+    };
+
+    {
+        let mut chunks = world.write_resource::<WorldChunks>();
+        if !chunks.is_stored(&map_id) {
+            let tiler = world.read_resource::<AutoTiler>();
+            let mut changed = apply_edits(&tiler, &mut source, &mut nav, &chunks.edits_of(&map_id));
+            // Anything off a map which got smaller in tiled is dropped.
+            let on_map = |loc: &Point| source.gid_at(0, loc).is_some();
+            let plots: Vec<(Point, u32)> = world.read_resource::<Farm>().gids(&map_id).into_iter().filter(|(loc, _)| on_map(loc)).collect();
+            let mined: Vec<Point> = world.read_resource::<Mining>().mined_on(&map_id).into_iter().filter(|loc| on_map(loc)).collect();
+            for (loc, gid) in plots {
+                source.set_gid(0, &loc, gid); // plots are not in the map files
+            }
+            for loc in mined {
+                source.set_gid(1, &loc, 0);
+                nav.set_at(&loc, Tile::new(FLOOR, 1)).unwrap();
+                changed.push(loc);
+            }
+
+            // Only what differs from the map file gets auto tiled so hand placed sprites
+            // look the same on every visit.
+            for update in tiler.refresh(&nav, &source, &changed) {
+                source.set_gid(update.layer, &update.loc, update.gid);
+            }
+            chunks.store_map(&map_id, source, nav);
+        }
+        chunks.set_map(&map_id, sprite_sheet_handle, extras.animations);
+    }
+    world.write_resource::<Mining>().mineables = extras.mineables;
+    world.write_resource::<MapManager>().add(info);
+}
+
+/// What a gid on the solids layer is in the navigation map.  Fences are the wall terrain
+/// and get auto tiled.  Every other solid only blocks.
+pub fn nav_tile(tiler: &AutoTiler, gid: u32) -> Tile {
+    match gid {
+        0 => Tile::new(FLOOR, 1),
+        _ => Tile::new(tiler.terrain_of(1, gid).unwrap_or(SOLID), 1),
+    }
+}
+
+/// Navigation map of the solids layer of source.
+pub fn nav_map(tiler: &AutoTiler, source: &MapSource) -> Map {
+    let mut nav = Map::new(source.width, source.height, FLOOR, 1);
+
+    for y in 0..source.height {
+        for x in 0..source.width {
+            let loc = Point::new(x, y);
+            nav.set_at(&loc, nav_tile(tiler, source.gid(1, &loc))).unwrap();
+        }
+    }
+
+    nav
+}
+
+/// Evict all chunks of the current map.  Their entities go away on the next frame.
+pub fn unload_map(world: &mut World) {
+    let mut manager = world.write_resource::<MapManager>();
+    world.write_resource::<WorldChunks>().unload(manager.map_mut());
+}
+
+// All tiles an object covers.  Tile objects are anchored at their bottom left and not top left.
+// Isometric maps measure objects along both tile axes using the tile height.
+fn object_points(object: &Object, info: &MapInfo) -> Vec<Point> {
+    let tile_width = match info.orientation {
+        Orientation::Orthogonal => info.tile_width,
+        Orientation::Isometric => info.tile_height,
+    };
+    let top = if object.gid != 0 { object.y - object.height } else { object.y };
+    let first_i = (object.x / tile_width) as usize;
+    let first_j = (top / info.tile_height) as usize;
+    let last_i = (((object.x + object.width) / tile_width).ceil() as usize).max(first_i + 1) - 1;
+    let last_j = (((top + object.height) / info.tile_height).ceil() as usize).max(first_j + 1) - 1;
+    let mut points = vec![];
+
+    for j in first_j..=last_j.min(info.height - 1) {
+        for i in first_i..=last_i.min(info.width - 1) {
+            points.push(Point::new(i, j));
+        }
+    }
+
+    points
+}
+
+/// Where a tile object which is neither an entry nor a warp stands.  These go on the
+/// objects layer in the tile their feet are in.
+pub fn object_feet(object: &Object, info: &MapInfo) -> Option<Point> {
+    if object.gid == 0 || object.obj_type == "entry" || object.obj_type == "warp" {
+        return None;
+    }
+
+    let points = object_points(object, info);
+    let left = points.first()?.x;
+    points.into_iter().filter(|loc| loc.x == left).last()
+}
+
+fn string_property(object: &Object, name: &str) -> Option<String> {
+    match object.properties.get(name) {
+        Some(PropertyValue::StringValue(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+// Sprites are raised by offset so taller ones still sit on the bottom of their tile.
+fn load_sprites(map_tileset: &Tileset, sprite_w: u32, sprite_h: u32, offset: f32) -> Vec<Sprite> {
+    let mut tile_sprites = Vec::new();
+    let image = &map_tileset.images[0];
+    let (tileset_width, tileset_height) = (image.width, image.height);
+    let columns = (tileset_width / sprite_w as i32) as u32;
+    let rows = (tileset_height / sprite_h as i32) as u32;
+
+    for x in 0..rows {
+        for y in 0..columns {
+            // For some reason rows are columns???
+            let (pixel_top, pixel_left) = ((x * sprite_w), (y * sprite_h));
+            let offsets = [0.0, -offset];
+
+            tile_sprites.push(Sprite::from_pixel_values(
+                tileset_width as u32,
+                tileset_height as u32,
+                sprite_w,
+                sprite_h,
+                pixel_left,
+                pixel_top,
+                offsets,
+                false,
+                false
+            ));
+        }
+    }
+
+    tile_sprites
+}
+
+/// What tiled does not tell us about the tiles of our tileset.  Read from the tsx once and
+/// kept until a map gets reloaded.
+#[derive(Clone, Default)]
+struct TilesetExtras {
+    animations: HashMap<u32, TileAnimation>, // by tile id (gid - 1)
+    mineables: HashMap<u32, Mineable>,
+}
+
+fn tileset_extras(world: &mut World) -> TilesetExtras {
+    if !world.has_value::<TilesetExtras>() {
+        let file = File::open(Path::new(TILESET_PATH)).expect("Missing tileset");
+        world.insert(read_tileset_extras(BufReader::new(file)));
+    }
+
+    world.read_resource::<TilesetExtras>().clone()
+}
+
+// tiled does not expose the frames of a tile <animation> or the properties of a tile so we
+// pull them out of the tsx ourselves.  Tiles with a "hits" property can be mined.
+fn read_tileset_extras<R: Read>(reader: R) -> TilesetExtras {
+    let mut frames: HashMap<u32, Vec<TileFrame>> = HashMap::new();
+    let mut mineables = HashMap::new();
+    let mut current_tile: Option<u32> = None;
+    let (mut hits, mut drop) = (None, None);
+
+    for event in EventReader::new(reader) {
+        match event.expect("Malformed tileset") {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "tile" => current_tile = attribute_of(&attributes, "id"),
+                "frame" => {
+                    if let (Some(id), Some(tile_id), Some(duration)) = (current_tile,
+                            attribute_of(&attributes, "tileid"), attribute_of(&attributes, "duration")) {
+                        frames.entry(id).or_insert_with(Vec::new).push(TileFrame {
+                            sprite_number: tile_id as usize, // tile ids are 0-based like sprites.
+                            duration,
+                        });
+                    }
+                },
+                "property" => {
+                    let value = attributes.iter().find(|a| a.name.local_name == "value").map(|a| a.value.clone());
+                    match attributes.iter().find(|a| a.name.local_name == "name").map(|a| a.value.as_str()) {
+                        Some("hits") => hits = attribute_of(&attributes, "value"),
+                        Some("drop") => drop = value,
+                        _ => (),
+                    }
+                },
+                _ => (),
+            },
+            XmlEvent::EndElement { name } if name.local_name == "tile" => {
+                if let (Some(id), Some(hits)) = (current_tile, hits) {
+                    mineables.insert(id, Mineable { hits, drop: drop.clone() });
+                }
+                current_tile = None;
+                hits = None;
+                drop = None;
+            },
+            _ => (),
+        }
+    }
+
+    TilesetExtras {
+        animations: frames.into_iter().map(|(id, frames)| (id, TileAnimation::new(frames))).collect(),
+        mineables,
+    }
+}
+
+fn attribute_of(attributes: &[OwnedAttribute], name: &str) -> Option<u32> {
+    attributes.iter()
+        .find(|a| a.name.local_name == name)
+        .and_then(|a| a.value.parse().ok())
+}
+
+fn load_tileset() -> Tileset {
+    let file = File::open(Path::new(TILESET_PATH)).expect("Missing tileset");
+
+    parse_tileset(BufReader::new(file), 1).expect("Malformed tileset")
+}
+
+fn load_tiled_map(map_id: &str) -> tiled::Map {
+    let file = File::open(tmx_path(map_id)).unwrap_or_else(|_| panic!("Missing map {}", map_id));
+
+    parse_tiled_map(BufReader::new(file))
+}
+
+/// Where the tmx of a (not generated) map is.  Maps from outside MAP_DIR are known by
+/// their path.
+pub fn tmx_path(map_id: &str) -> PathBuf {
+    if map_id.ends_with(".tmx") {
+        PathBuf::from(map_id)
+    } else {
+        Path::new(MAP_DIR).join(format!("{}.tmx", map_id))
+    }
+}
+
+/// Parse a tmx.  Tilesets it refers to are looked for next to our tileset.
+pub fn parse_tiled_map<R: Read>(reader: R) -> tiled::Map {
+    parse_with_path(reader, Path::new(TILESET_PATH))
+        .expect("Assets missing while loading tmx")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map_loader::*;
+    use crate::resources::{Terrain, TileUpdate, FENCE_SPRITES};
+
+    #[test]
+    fn test_read_tileset_extras() {
+        let tsx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.2" name="test" tilewidth="64" tileheight="64" tilecount="8" columns="4">
+ <image source="test.png" width="256" height="128"/>
+ <tile id="2">
+  <animation>
+   <frame tileid="2" duration="100"/>
+   <frame tileid="3" duration="300"/>
+  </animation>
+ </tile>
+ <tile id="4">
+  <properties>
+   <property name="hits" type="int" value="3"/>
+   <property name="drop" value="wood"/>
+  </properties>
+ </tile>
+ <tile id="5">
+  <properties>
+   <property name="drop" value="stone"/>
+  </properties>
+ </tile>
+</tileset>"#;
+        let extras = read_tileset_extras(tsx.as_bytes());
+
+        assert_eq!(extras.animations.keys().collect::<Vec<&u32>>(), vec![&2]);
+        let animation = &extras.animations[&2];
+        assert_eq!((animation.sprite_at(0), animation.sprite_at(150), animation.sprite_at(450)), (2, 3, 2));
+
+        // Without hits nothing can be mined.
+        assert_eq!(extras.mineables.len(), 1);
+        assert_eq!(extras.mineables[&4], Mineable { hits: 3, drop: Some("wood".to_string()) });
+    }
+
+    #[test]
+    fn test_shipped_tileset() {
+        let file = File::open(Path::new(TILESET_PATH)).unwrap();
+        let extras = read_tileset_extras(BufReader::new(file));
+
+        // Water on bountiful ripples between two frames.
+        let water = &extras.animations[&22];
+        assert_eq!((water.sprite_at(0), water.sprite_at(600), water.sprite_at(1200)), (22, 23, 22));
+        assert_eq!(extras.mineables.len(), 16); // every fence
+    }
+
+    #[test]
+    fn test_shipped_objects() {
+        let (_, source, _) = read_tiled_map("bountiful", &load_tiled_map("bountiful"), &AutoTiler::default());
+
+        // A post by the pond for the player to walk in front of and behind.
+        assert_eq!(source.gid(2, &Point::new(3, 46)), 5);
+        assert_eq!(source.gid(1, &Point::new(3, 46)), 0);
+    }
+
+    #[test]
+    fn test_bad_warps() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" orientation="orthogonal" renderorder="right-down" width="3" height="1" tilewidth="64" tileheight="64" infinite="0" nextlayerid="3" nextobjectid="4">
+ <tileset firstgid="1" source="pathetic.tsx"/>
+ <layer id="1" name="ground" width="3" height="1">
+  <data encoding="csv">1,1,1</data>
+ </layer>
+ <objectgroup id="2" name="warps">
+  <object id="1" name="good" type="warp" x="0" y="0" width="64" height="64">
+   <properties>
+    <property name="entry" value="west"/>
+    <property name="map" value="bountiful2"/>
+   </properties>
+  </object>
+  <object id="2" name="nowhere" type="warp" x="64" y="0" width="64" height="64">
+   <properties>
+    <property name="entry" value="west"/>
+    <property name="map" value="nowhere"/>
+   </properties>
+  </object>
+  <object id="3" name="no_entry" type="warp" x="128" y="0" width="64" height="64">
+   <properties>
+    <property name="map" value="bountiful2"/>
+   </properties>
+  </object>
+ </objectgroup>
+</map>"#;
+        let (info, _, _) = read_tiled_map("test", &parse_tiled_map(tmx.as_bytes()), &AutoTiler::default());
+
+        // Only the warp which can go somewhere is kept.
+        assert_eq!(info.warps.len(), 1);
+        assert_eq!(info.warps[&Point::new(0, 0)], Warp { map_id: "bountiful2".to_string(), entry: "west".to_string() });
+    }
+
+    #[test]
+    fn test_solids_by_gid() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" orientation="orthogonal" renderorder="right-down" width="3" height="1" tilewidth="64" tileheight="64" infinite="0" nextlayerid="3" nextobjectid="1">
+ <tileset firstgid="1" source="pathetic.tsx"/>
+ <layer id="1" name="ground" width="3" height="1">
+  <data encoding="csv">1,1,1</data>
+ </layer>
+ <layer id="2" name="solids" width="3" height="1">
+  <data encoding="csv">15,14,21</data>
+ </layer>
+</map>"#;
+        let tiler = AutoTiler::default().with_terrain(Terrain::four_bit(WALL, 1, FENCE_SPRITES));
+        let (_, source, mut nav) = read_tiled_map("test", &parse_tiled_map(tmx.as_bytes()), &tiler);
+
+        // Two fences and a rock which is solid without being a fence.
+        let ids: String = nav.iter().map(|(_, tile)| tile.id).collect();
+        assert_eq!(ids, format!("{}{}{}", WALL, WALL, SOLID));
+
+        // Mining the fence next to the rock leaves the rock alone.
+        nav.take_changes();
+        nav.set_at(&Point::new(1, 0), Tile::new(FLOOR, 1)).unwrap();
+        let changes = nav.take_changes();
+        let updates = tiler.refresh(&nav, &source, &changes);
+        assert!(updates.iter().all(|update| update.loc != Point::new(2, 0)));
+        assert!(updates.contains(&TileUpdate { loc: Point::new(0, 0), layer: 1, gid: 5 }));
+    }
+}
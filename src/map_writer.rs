@@ -0,0 +1,426 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tiled::parse_with_path;
+use xml::reader::{EventReader, ParserConfig, XmlEvent as ReadEvent};
+use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+
+use crate::bountiful::{LAYER_NAMES, TILESET_PATH};
+use crate::map_loader::{object_feet, tmx_path, MAP_DIR};
+use crate::resources::{Map, MapInfo, MapSource, Orientation, Point};
+
+/// Something placed on a map's object layer.  Tile objects (gid other than 0) show that
+/// tile standing in loc.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TmxObject {
+    pub name: String,
+    pub obj_type: String,
+    pub loc: Point,
+    pub gid: u32,
+    pub properties: Vec<(String, String)>,
+}
+
+impl TmxObject {
+    pub fn new(name: &str, obj_type: &str, loc: Point) -> Self {
+        Self { name: name.to_string(), obj_type: obj_type.to_string(), loc, gid: 0, properties: vec![] }
+    }
+
+    pub fn with_gid(mut self, gid: u32) -> Self {
+        self.gid = gid;
+        self
+    }
+
+    pub fn with_property(mut self, name: &str, value: &str) -> Self {
+        self.properties.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Where the map `map_id` gets saved.  Generated ids have a ':' which file names do not like
+/// and maps from outside MAP_DIR go back where they came from.
+pub fn map_path(map_id: &str) -> PathBuf {
+    if map_id.ends_with(".tmx") {
+        return tmx_path(map_id);
+    }
+
+    Path::new(MAP_DIR).join(format!("{}.tmx", map_id.replace(':', "_")))
+}
+
+const OBJECTS_LAYER: usize = 2; // written as tile objects and not as a tile layer
+
+/// Save a map over its tmx.  Object groups of the tmx being replaced are kept as they were
+/// apart from tile objects which follow whatever the objects layer has now.
+pub fn save_map(info: &MapInfo, source: &MapSource) -> io::Result<PathBuf> {
+    let path = map_path(&info.id);
+    let layers: Vec<Map<u32>> = (0..source.layers.len()).map(|layer| source.layer_map(layer)).collect();
+    let original = fs::read(&path).ok(); // before we clobber it
+    let mut out = vec![];
+
+    write_tmx(&mut out, info, &layers, original.as_deref())?;
+    fs::write(&path, out)?;
+
+    Ok(path)
+}
+
+/// Entries and warps of a map as objects (sorted so saving twice gives the same file).
+/// Every warp tile gets its own object.  Only needed for maps without a tmx of their own.
+pub fn placements(info: &MapInfo) -> Vec<TmxObject> {
+    let mut entries: Vec<_> = info.entries.iter().collect();
+    let mut warps: Vec<_> = info.warps.iter().collect();
+
+    entries.sort_by_key(|(name, _)| name.to_string());
+    warps.sort_by_key(|(loc, _)| (loc.y, loc.x));
+
+    entries.into_iter()
+        .map(|(name, loc)| TmxObject::new(name, "entry", *loc))
+        .chain(warps.into_iter().map(|(loc, warp)| {
+            TmxObject::new(&format!("to_{}", warp.map_id), "warp", *loc)
+                .with_property("entry", &warp.entry)
+                .with_property("map", &warp.map_id)
+        }))
+        .collect()
+}
+
+/// Tile objects for everything on the objects layer which is not in one of `skip`.
+pub fn tile_objects(layer: &Map<u32>, skip: &HashSet<Point>) -> Vec<TmxObject> {
+    layer.iter()
+        .filter(|(loc, gid)| **gid != 0 && !skip.contains(loc))
+        .map(|(loc, gid)| TmxObject::new("", "", loc).with_gid(*gid))
+        .collect()
+}
+
+/// Write gid layers (ground first) as a tmx which uses our tileset.  The objects layer
+/// becomes tile objects.  Object groups are copied from `original` (a tmx) if there is one
+/// and otherwise the entries and warps of info are written.  Layers must all be the size
+/// of the map.
+pub fn write_tmx<W: Write>(out: W, info: &MapInfo, layers: &[Map<u32>], original: Option<&[u8]>) -> io::Result<()> {
+    let (groups, kept, first_object_id) = match original {
+        Some(original) => original_objects(info, layers.get(OBJECTS_LAYER), original)?,
+        None => (vec![], HashSet::new(), 1),
+    };
+    let mut objects = if original.is_none() { placements(info) } else { vec![] };
+    if let Some(layer) = layers.get(OBJECTS_LAYER) {
+        objects.extend(tile_objects(layer, &kept));
+    }
+
+    let tile_layers = layers.len().min(OBJECTS_LAYER);
+    let group_count = groups.iter().filter(|event| is_group_start(event)).count() + if objects.is_empty() { 0 } else { 1 };
+    let mut writer = EmitterConfig::new().perform_indent(true).create_writer(out);
+    let (width, height) = (info.width.to_string(), info.height.to_string());
+    let (tile_width, tile_height) = ((info.tile_width as u32).to_string(), (info.tile_height as u32).to_string());
+    let orientation = match info.orientation {
+        Orientation::Orthogonal => "orthogonal",
+        Orientation::Isometric => "isometric",
+    };
+    let tileset = Path::new(TILESET_PATH).file_name().unwrap().to_string_lossy();
+    let next_layer_id = (tile_layers + group_count + 1).to_string();
+    let next_object_id = (first_object_id + objects.len()).to_string();
+
+    writer.write(XmlEvent::start_element("map")
+        .attr("version", "1.2")
+        .attr("orientation", orientation)
+        .attr("renderorder", "right-down")
+        .attr("width", &width)
+        .attr("height", &height)
+        .attr("tilewidth", &tile_width)
+        .attr("tileheight", &tile_height)
+        .attr("infinite", "0")
+        .attr("nextlayerid", &next_layer_id)
+        .attr("nextobjectid", &next_object_id)).map_err(xml_error)?;
+
+    writer.write(XmlEvent::start_element("tileset").attr("firstgid", "1").attr("source", &tileset)).map_err(xml_error)?;
+    end(&mut writer)?;
+
+    for (index, layer) in layers.iter().take(tile_layers).enumerate() {
+        let id = (index + 1).to_string();
+        let name = LAYER_NAMES.get(index).map_or_else(|| format!("layer{}", id), |name| name.to_string());
+
+        writer.write(XmlEvent::start_element("layer")
+            .attr("id", &id)
+            .attr("name", &name)
+            .attr("width", &width)
+            .attr("height", &height)).map_err(xml_error)?;
+        writer.write(XmlEvent::start_element("data").attr("encoding", "csv")).map_err(xml_error)?;
+        writer.write(XmlEvent::characters(&csv(layer))).map_err(xml_error)?;
+        end(&mut writer)?;
+        end(&mut writer)?;
+    }
+
+    copy_groups(&mut writer, &groups, tile_layers + 1)?;
+
+    if !objects.is_empty() {
+        let id = (tile_layers + group_count).to_string();
+        writer.write(XmlEvent::start_element("objectgroup").attr("id", &id).attr("name", "objects")).map_err(xml_error)?;
+        for (index, object) in objects.iter().enumerate() {
+            write_object(&mut writer, info, (first_object_id + index).to_string(), object)?;
+        }
+        end(&mut writer)?;
+    }
+
+    end(&mut writer)
+}
+
+// Object groups of the original tmx as events along with the tiles of the tile objects
+// kept and the first object id not used by them.  A tile object whose tile on the objects
+// layer has changed since is left out.
+fn original_objects(info: &MapInfo, layer: Option<&Map<u32>>, original: &[u8]) -> io::Result<(Vec<ReadEvent>, HashSet<Point>, usize)> {
+    let map = parse_with_path(original, Path::new(TILESET_PATH)).map_err(|e| xml_error(format!("{:?}", e)))?;
+    let all = || map.object_groups.iter().flat_map(|group| group.objects.iter());
+    let mut kept = HashSet::new();
+    let mut dropped = HashSet::new();
+
+    for object in all() {
+        if let Some(feet) = object_feet(object, info) {
+            let gid = layer.and_then(|layer| layer.at(&feet)).copied().unwrap_or(0);
+            if gid != object.gid || !kept.insert(feet) {
+                dropped.insert(object.id.to_string());
+            }
+        }
+    }
+    let first_free = all().map(|object| object.id as usize + 1).max().unwrap_or(1);
+
+    Ok((without_objects(object_groups(original)?, &dropped), kept, first_free))
+}
+
+// Every event from each <objectgroup> to its end.
+fn object_groups(original: &[u8]) -> io::Result<Vec<ReadEvent>> {
+    let config = ParserConfig::new().trim_whitespace(true);
+    let mut events = vec![];
+    let mut depth = 0;
+
+    for event in EventReader::new_with_config(original, config) {
+        let event = event.map_err(xml_error)?;
+
+        match &event {
+            ReadEvent::StartElement { .. } if depth > 0 || is_group_start(&event) => depth += 1,
+            // The end of the group itself still belongs to it.
+            ReadEvent::EndElement { .. } if depth > 0 => depth -= 1,
+            ReadEvent::Whitespace(_) => continue,
+            _ if depth > 0 => (),
+            _ => continue,
+        }
+
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+// Events minus the <object>s (and what is inside them) with one of the ids.
+fn without_objects(events: Vec<ReadEvent>, ids: &HashSet<String>) -> Vec<ReadEvent> {
+    let mut skipping = 0;
+
+    events.into_iter().filter(|event| match event {
+        ReadEvent::StartElement { name, attributes, .. } if skipping == 0 && name.local_name == "object" &&
+            attributes.iter().any(|a| a.name.local_name == "id" && ids.contains(&a.value)) => {
+            skipping = 1;
+            false
+        },
+        ReadEvent::StartElement { .. } if skipping > 0 => {
+            skipping += 1;
+            false
+        },
+        ReadEvent::EndElement { .. } if skipping > 0 => {
+            skipping -= 1;
+            false
+        },
+        _ => skipping == 0,
+    }).collect()
+}
+
+fn is_group_start(event: &ReadEvent) -> bool {
+    match event {
+        ReadEvent::StartElement { name, .. } => name.local_name == "objectgroup",
+        _ => false,
+    }
+}
+
+// Groups are renumbered to follow the tile layers so layer ids stay unique.
+fn copy_groups<W: Write>(writer: &mut EventWriter<W>, groups: &[ReadEvent], first_id: usize) -> io::Result<()> {
+    let mut next_id = first_id;
+
+    for event in groups {
+        if let ReadEvent::StartElement { name, attributes, .. } = event {
+            if name.local_name == "objectgroup" {
+                let id = next_id.to_string();
+                let mut element = XmlEvent::start_element("objectgroup").attr("id", &id);
+                for attribute in attributes.iter().filter(|a| a.name.local_name != "id") {
+                    element = element.attr(attribute.name.borrow(), &attribute.value);
+                }
+                writer.write(element).map_err(xml_error)?;
+                next_id += 1;
+                continue;
+            }
+        }
+
+        if let Some(event) = event.as_writer_event() {
+            writer.write(event).map_err(xml_error)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Opposite of how the loader finds the tiles of an object.  Tile objects are anchored at
+// their bottom left and isometric maps measure both axes in tile heights.
+fn write_object<W: Write>(writer: &mut EventWriter<W>, info: &MapInfo, id: String, object: &TmxObject) -> io::Result<()> {
+    let tile_width = match info.orientation {
+        Orientation::Orthogonal => info.tile_width,
+        Orientation::Isometric => info.tile_height,
+    };
+    let bottom = if object.gid != 0 { 1 } else { 0 };
+    let x = (object.loc.x as f32 * tile_width).to_string();
+    let y = ((object.loc.y + bottom) as f32 * info.tile_height).to_string();
+    let (width, height) = (tile_width.to_string(), info.tile_height.to_string());
+    let gid = object.gid.to_string();
+
+    let mut element = XmlEvent::start_element("object")
+        .attr("id", &id)
+        .attr("name", &object.name)
+        .attr("type", &object.obj_type);
+    if object.gid != 0 {
+        element = element.attr("gid", &gid);
+    }
+    writer.write(element
+        .attr("x", &x)
+        .attr("y", &y)
+        .attr("width", &width)
+        .attr("height", &height)).map_err(xml_error)?;
+
+    if !object.properties.is_empty() {
+        writer.write(XmlEvent::start_element("properties")).map_err(xml_error)?;
+        for (name, value) in &object.properties {
+            writer.write(XmlEvent::start_element("property").attr("name", name).attr("value", value)).map_err(xml_error)?;
+            end(writer)?;
+        }
+        end(writer)?;
+    }
+
+    end(writer)
+}
+
+// Same layout tiled uses: one row per line with a trailing comma on all but the last.
+fn csv(layer: &Map<u32>) -> String {
+    let rows: Vec<String> = (0..layer.height())
+        .filter_map(|y| layer.row(y))
+        .map(|row| row.map(|gid| gid.to_string()).collect::<Vec<_>>().join(","))
+        .collect();
+
+    format!("\n{}\n", rows.join(",\n"))
+}
+
+fn end<W: Write>(writer: &mut EventWriter<W>) -> io::Result<()> {
+    writer.write(XmlEvent::end_element()).map_err(xml_error)
+}
+
+fn xml_error<E: Display>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::Path;
+
+    use crate::map_loader::{parse_tiled_map, read_tiled_map, MAP_DIR};
+    use crate::map_writer::*;
+    use crate::resources::AutoTiler;
+
+    // Load a map, write it out, read that back in and it should be the same map.
+    fn round_trip(map_id: &str, original: bool) {
+        let tmx = fs::read(Path::new(MAP_DIR).join(format!("{}.tmx", map_id))).unwrap();
+        let (info, source, nav) = read_tiled_map(map_id, &parse_tiled_map(tmx.as_slice()), &AutoTiler::default());
+        let layers: Vec<Map<u32>> = (0..source.layers.len()).map(|layer| source.layer_map(layer)).collect();
+        let mut out = vec![];
+
+        write_tmx(&mut out, &info, &layers, if original { Some(tmx.as_slice()) } else { None }).unwrap();
+        let (again, again_source, again_nav) = read_tiled_map(map_id, &parse_tiled_map(out.as_slice()), &AutoTiler::default());
+
+        assert_eq!(again.orientation, info.orientation);
+        assert_eq!((again.width, again.height), (info.width, info.height));
+        assert_eq!((again.tile_width, again.tile_height), (info.tile_width, info.tile_height));
+        assert_eq!(again.entries, info.entries);
+        assert_eq!(again.warps, info.warps);
+        assert_eq!(again_source.layers, source.layers);
+        assert_eq!(again_nav.to_string(), nav.to_string());
+    }
+
+    fn info(width: usize, height: usize) -> MapInfo {
+        MapInfo {
+            id: "test".to_string(),
+            width,
+            height,
+            tile_width: 64.,
+            tile_height: 64.,
+            orientation: Orientation::Orthogonal,
+            sprite_offset: 0.,
+            entries: Default::default(),
+            warps: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for original in &[true, false] {
+            round_trip("bountiful", *original);
+            round_trip("bountiful2", *original);
+            round_trip("iso", *original);
+        }
+    }
+
+    #[test]
+    fn test_tile_objects() {
+        let mut objects = Map::filled(2, 2, 0);
+        objects.set_at(&Point::new(1, 0), 5).unwrap();
+        let mut out = vec![];
+
+        assert_eq!(tile_objects(&objects, &HashSet::new()), vec![TmxObject::new("", "", Point::new(1, 0)).with_gid(5)]);
+        assert!(tile_objects(&objects, &[Point::new(1, 0)].iter().cloned().collect()).is_empty());
+
+        write_tmx(&mut out, &info(2, 2), &[Map::filled(2, 2, 1), Map::filled(2, 2, 0), objects], None).unwrap();
+        let (_, source, _) = read_tiled_map("test", &parse_tiled_map(out.as_slice()), &AutoTiler::default());
+
+        // Tile objects end up on the objects layer where their feet are.
+        assert!(String::from_utf8(out).unwrap().contains("gid=\"5\""));
+        assert_eq!(source.gid(2, &Point::new(1, 0)), 5);
+        assert_eq!(source.gid(0, &Point::new(0, 1)), 1);
+    }
+
+    #[test]
+    fn test_original_objects() {
+        let original = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="64" tileheight="64" infinite="0" nextlayerid="3" nextobjectid="8">
+ <tileset firstgid="1" source="pathetic.tsx"/>
+ <layer id="1" name="ground" width="2" height="2">
+  <data encoding="csv">1,1,1,1</data>
+ </layer>
+ <objectgroup id="2" name="things">
+  <object id="5" name="rock" gid="5" x="64" y="64" width="64" height="64"/>
+  <object id="6" name="gone" gid="6" x="0" y="64" width="64" height="64"/>
+  <object id="7" name="sign" type="note" x="0" y="64" width="64" height="64">
+   <properties>
+    <property name="text" value="hi"/>
+   </properties>
+  </object>
+ </objectgroup>
+</map>"#;
+        // The rock stays, the other tile object was erased and a new one painted.
+        let mut objects = Map::filled(2, 2, 0);
+        objects.set_at(&Point::new(1, 0), 5).unwrap();
+        objects.set_at(&Point::new(1, 1), 7).unwrap();
+        let mut out = vec![];
+
+        write_tmx(&mut out, &info(2, 2), &[Map::filled(2, 2, 1), Map::filled(2, 2, 0), objects], Some(original.as_bytes())).unwrap();
+        let (_, source, _) = read_tiled_map("test", &parse_tiled_map(out.as_slice()), &AutoTiler::default());
+        let tmx = String::from_utf8(out).unwrap();
+
+        assert!(tmx.contains("name=\"rock\""));
+        assert!(tmx.contains("name=\"sign\"") && tmx.contains("value=\"hi\""));
+        assert!(!tmx.contains("name=\"gone\""));
+        assert!(tmx.contains("id=\"8\"") && tmx.contains("nextobjectid=\"9\""));
+        assert_eq!((source.gid(2, &Point::new(1, 0)), source.gid(2, &Point::new(0, 0)), source.gid(2, &Point::new(1, 1))), (5, 0, 7));
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::Path;
+
+use crate::generation::generator;
+use crate::map_loader::{tmx_path, MAP_DIR};
+use crate::save_game::SAVE_SLOTS;
+
+pub const USAGE: &str = "Usage: bountiful [options]
+    --map <id>        start on this map (a tmx in assets/texture, a path to a tmx, or cave, bsp or terrain)
+    --skip-welcome    go straight into the game
+    --window <w>x<h>  window size
+    --slot <n>        save slot (1 to 3) to load and autosave into
+    --seed <n>        seed for a --map generator like cave (the same seed gives the same map)
+    --clock <n>       game minutes per real second
+    --frames <n>      quit after this many frames without a window (no GPU needed)
+    --help            show this";
+
+/// What the game was started with.  Inserted as a resource so states can look at it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    pub map: Option<String>,
+    pub skip_welcome: bool,
+    pub window: Option<(u32, u32)>,
+    pub save_slot: Option<usize>, // None plays without saving
+    pub seed: Option<u64>,
+    pub clock_rate: Option<f32>,
+    pub frames: Option<u64>,
+}
+
+impl Options {
+    /// Options of this process.  Bad options print usage and exit.
+    pub fn from_args() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(message) => {
+                eprintln!("{}\n{}", message, USAGE);
+                std::process::exit(if message.is_empty() { 0 } else { 2 });
+            },
+        }
+    }
+
+    /// Parse arguments (without the program name).  Errors are a message about what was
+    /// wrong or empty when help was asked for.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg));
+
+            match arg.as_str() {
+                "--map" => options.map = Some(map_id(&value()?)?),
+                "--skip-welcome" => options.skip_welcome = true,
+                "--window" => options.window = Some(window_size(&value()?)?),
+                "--slot" => options.save_slot = Some(save_slot(&value()?)?),
+                "--seed" => options.seed = Some(number(&arg, &value()?)?),
+                "--clock" => options.clock_rate = Some(clock_rate(&value()?)?),
+                "--frames" => options.frames = Some(number(&arg, &value()?)?),
+                "--help" | "-h" => return Err(String::new()),
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+
+        // A tmx (or a generated id which has its own seed) would quietly ignore the seed.
+        match (&options.seed, &options.map) {
+            (Some(_), Some(map)) if generator(map).is_some() => (),
+            (Some(_), _) => return Err("--seed only goes with a --map generator like cave".to_string()),
+            _ => (),
+        }
+
+        Ok(options)
+    }
+
+    /// Map to start on.  A bare generator name gets the seed (or 0) as its seed.
+    pub fn start_map(&self, default: &str) -> String {
+        match &self.map {
+            Some(map) if generator(map).is_some() =>
+                format!("{}:{}", map, self.seed.unwrap_or(0)),
+            Some(map) => map.clone(),
+            None => default.to_string(),
+        }
+    }
+
+    /// Smoke tests run by themselves so nobody gets asked anything.  They run headless:
+    /// no window, no rendering and no GPU.
+    pub fn unattended(&self) -> bool {
+        self.frames.is_some()
+    }
+}
+
+// A generator name (with a seed like cave:7 or without), the id of a tmx in MAP_DIR or a
+// path to a tmx anywhere.  Ours are known by their id so warps and saves name them the same.
+fn map_id(map: &str) -> Result<String, String> {
+    let path = Path::new(map);
+
+    if path.extension().map_or(false, |extension| extension == "tmx") {
+        if !path.is_file() {
+            return Err(format!("No map file {}", map));
+        }
+        let ours = path.parent()
+            .and_then(|dir| dir.canonicalize().ok())
+            .map_or(false, |dir| Path::new(MAP_DIR).canonicalize().ok() == Some(dir));
+        return Ok(match path.file_stem() {
+            Some(stem) if ours => stem.to_string_lossy().to_string(),
+            _ => map.to_string(),
+        });
+    }
+
+    let mut parts = map.splitn(2, ':');
+    match (parts.next().and_then(generator), parts.next()) {
+        (Some(_), None) => Ok(map.to_string()),
+        (Some(_), Some(seed)) => number::<u64>("--map", seed).map(|_| map.to_string()),
+        (None, _) if tmx_path(map).is_file() => Ok(map.to_string()),
+        (None, _) => Err(format!("No map {} in {} and no generator called that either", map, MAP_DIR)),
+    }
+}
+
+fn window_size(size: &str) -> Result<(u32, u32), String> {
+    let mut parts = size.splitn(2, 'x');
+
+    match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+        (Some(Ok(width)), Some(Ok(height))) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!("Window size should look like 800x600 and not {}", size)),
+    }
+}
+
+fn clock_rate(rate: &str) -> Result<f32, String> {
+    match number("--clock", rate)? {
+        rate if rate > 0. => Ok(rate),
+        _ => Err(format!("--clock needs to be more than 0 and not {}", rate)),
+    }
+}
+
+// Only slots the welcome screen lists so a save never goes somewhere nobody can load it.
+fn save_slot(slot: &str) -> Result<usize, String> {
+    match number("--slot", slot)? {
+        slot if (1..=SAVE_SLOTS).contains(&slot) => Ok(slot),
+        _ => Err(format!("--slot needs to be from 1 to {} and not {}", SAVE_SLOTS, slot)),
+    }
+}
+
+fn number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} needs a number and not {}", arg, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(&["--map", "assets/texture/bountiful2.tmx", "--skip-welcome", "--window", "640x480",
+                              "--clock", "2.5", "--frames", "10"]).unwrap();
+
+        assert_eq!(options.map, Some("bountiful2".to_string()));
+        assert!(options.skip_welcome);
+        assert_eq!(options.window, Some((640, 480)));
+        assert_eq!(options.clock_rate, Some(2.5));
+        assert!(options.unattended());
+        assert_eq!(parse(&["--map", "terrain", "--seed", "42"]).unwrap().seed, Some(42));
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn test_bad_options() {
+        assert!(parse(&["--window", "640"]).is_err());
+        assert!(parse(&["--seed", "lots"]).is_err());
+        assert!(parse(&["--frames"]).is_err());
+        assert!(parse(&["--clock", "0"]).is_err());
+        assert!(parse(&["--slot", "0"]).is_err());
+        assert!(parse(&["--slot", &(SAVE_SLOTS + 1).to_string()]).is_err());
+        assert_eq!(parse(&["--slot", &SAVE_SLOTS.to_string()]).unwrap().save_slot, Some(SAVE_SLOTS));
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["--map", "nowhere"]).is_err());
+        assert!(parse(&["--map", "nowhere.tmx"]).is_err());
+        assert!(parse(&["--map", "cave:lots"]).is_err());
+        // Only a generator without a seed of its own takes one.
+        assert!(parse(&["--seed", "7"]).is_err());
+        assert!(parse(&["--map", "iso", "--seed", "7"]).is_err());
+        assert!(parse(&["--map", "cave:3", "--seed", "7"]).is_err());
+        assert_eq!(parse(&["--help"]), Err(String::new()));
+    }
+
+    #[test]
+    fn test_start_map() {
+        assert_eq!(parse(&[]).unwrap().start_map("bountiful"), "bountiful");
+        assert_eq!(parse(&["--map", "cave", "--seed", "7"]).unwrap().start_map("bountiful"), "cave:7");
+        assert_eq!(parse(&["--map", "cave:3"]).unwrap().start_map("bountiful"), "cave:3");
+        assert_eq!(parse(&["--map", "iso"]).unwrap().start_map("bountiful"), "iso");
+    }
+
+    #[test]
+    fn test_map_path() {
+        // A tmx which is not one of ours is known by its path.
+        let elsewhere = std::env::temp_dir().join("bountiful_options_test.tmx");
+        std::fs::copy(tmx_path("bountiful2"), &elsewhere).unwrap();
+        let map = elsewhere.to_string_lossy().to_string();
+
+        assert_eq!(parse(&["--map", &map]).unwrap().map, Some(map.clone()));
+        assert_eq!(tmx_path(&map), elsewhere);
+        assert_eq!(parse(&["--map", "assets/texture/../texture/iso.tmx"]).unwrap().map, Some("iso".to_string()));
+        std::fs::remove_file(elsewhere).unwrap();
+    }
+}
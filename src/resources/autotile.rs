@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::resources::topology::SQUARE_8;
+use crate::resources::{Gids, NavTiles, Point};
+
+// Bits of `Map::neighbour_mask` (same order as SQUARE_8).
+pub const NW: u8 = 1;
+pub const N: u8 = 1 << 1;
+pub const NE: u8 = 1 << 2;
+pub const W: u8 = 1 << 3;
+pub const E: u8 = 1 << 4;
+pub const SW: u8 = 1 << 5;
+pub const S: u8 = 1 << 6;
+pub const SE: u8 = 1 << 7;
+
+/// Fence sprites in pathetic.png indexed by 4-bit mask (N = 1, E = 2, S = 4, W = 8).
+pub const FENCE_SPRITES: [usize; 16] = [4, 12, 14, 9, 15, 7, 5, 18, 13, 10, 11, 16, 6, 17, 19, 8];
+
+pub enum TileRules {
+    /// Only the four sides matter.  Sprites are indexed by N = 1, E = 2, S = 4, W = 8.
+    FourBit([usize; 16]),
+    /// All eight neighbours matter (47 tile blob sets).  Keys are neighbour masks where a
+    /// corner only counts if both sides next to it do too.  Masks missing from the rules
+    /// use the fallback sprite.
+    Blob(HashMap<u8, usize>, usize),
+}
+
+/// A kind of tile (by id) which picks its sprite from its neighbours of the same kind.
+pub struct Terrain {
+    pub id: char,
+    pub layer: usize,
+    pub rules: TileRules,
+}
+
+impl Terrain {
+    pub fn four_bit(id: char, layer: usize, sprites: [usize; 16]) -> Self {
+        Self { id, layer, rules: TileRules::FourBit(sprites) }
+    }
+
+    pub fn blob(id: char, layer: usize, sprites: HashMap<u8, usize>, fallback: usize) -> Self {
+        Self { id, layer, rules: TileRules::Blob(sprites, fallback) }
+    }
+
+    pub fn sprite_for(&self, mask: u8) -> usize {
+        match &self.rules {
+            TileRules::FourBit(sprites) => sprites[Self::four_bit_index(mask)],
+            TileRules::Blob(sprites, fallback) => *sprites.get(&Self::blob_mask(mask)).unwrap_or(fallback),
+        }
+    }
+
+    /// Is gid one of the sprites of this terrain.  Anything else on its layer was put
+    /// there by hand and auto tiling leaves it alone.
+    pub fn contains(&self, gid: u32) -> bool {
+        if gid == 0 {
+            return false;
+        }
+
+        let sprite = (gid - 1) as usize;
+        match &self.rules {
+            TileRules::FourBit(sprites) => sprites.contains(&sprite),
+            TileRules::Blob(sprites, fallback) => *fallback == sprite || sprites.values().any(|other| *other == sprite),
+        }
+    }
+
+    pub fn four_bit_index(mask: u8) -> usize {
+        [N, E, S, W].iter()
+            .enumerate()
+            .filter(|(_, side)| mask & **side != 0)
+            .fold(0, |index, (bit, _)| index | 1 << bit)
+    }
+
+    /// Drop corners which are not backed by both of their sides.
+    pub fn blob_mask(mask: u8) -> u8 {
+        let mut result = mask & (N | E | S | W);
+
+        for (corner, a, b) in &[(NW, N, W), (NE, N, E), (SW, S, W), (SE, S, E)] {
+            if mask & corner != 0 && mask & a != 0 && mask & b != 0 {
+                result |= corner;
+            }
+        }
+
+        result
+    }
+}
+
+/// A new gid for a layer of the map at some location.  0 means no tile.
+#[derive(Debug, PartialEq)]
+pub struct TileUpdate {
+    pub loc: Point,
+    pub layer: usize,
+    pub gid: u32,
+}
+
+/// Picks sprites for terrains based on what surrounds them so edges and corners line up
+/// even when the map changes while playing.
+#[derive(Default)]
+pub struct AutoTiler {
+    terrains: Vec<Terrain>,
+}
+
+impl AutoTiler {
+    pub fn with_terrain(mut self, terrain: Terrain) -> Self {
+        self.terrains.push(terrain);
+        self
+    }
+
+    /// gid of `layer` at loc or None if no terrain on that layer cares about it.  A terrain
+    /// tile which is no longer that terrain is cleared.
+    pub fn gid_at<M: NavTiles>(&self, map: &M, loc: &Point, layer: usize) -> Option<u32> {
+        let tile = map.tile_at(loc)?;
+        let mut on_layer = self.terrains.iter().filter(|terrain| terrain.layer == layer).peekable();
+
+        on_layer.peek()?;
+
+        match on_layer.find(|terrain| terrain.id == tile.id) {
+            Some(terrain) => {
+                let mask = neighbours_of(loc)
+                    .filter(|(_, other)| map.tile_at(other).map_or(false, |other| other.id == terrain.id))
+                    .fold(0, |mask, (bit, _)| mask | 1 << bit);
+                Some(terrain.sprite_for(mask) as u32 + 1) // gids are 1-based.
+            },
+            None => Some(0),
+        }
+    }
+
+    /// Id of the terrain on layer which gid is a sprite of.
+    pub fn terrain_of(&self, layer: usize, gid: u32) -> Option<char> {
+        self.terrains.iter()
+            .find(|terrain| terrain.layer == layer && terrain.contains(gid))
+            .map(|terrain| terrain.id)
+    }
+
+    /// Work out new gids for changed locations and all of their neighbours.  Only terrain
+    /// sprites in source get cleared.
+    pub fn refresh<M: NavTiles, G: Gids>(&self, map: &M, source: &G, changes: &[Point]) -> Vec<TileUpdate> {
+        let mut seen = HashSet::new();
+        let mut layers: Vec<usize> = self.terrains.iter().map(|terrain| terrain.layer).collect();
+        let mut updates = vec![];
+
+        layers.sort();
+        layers.dedup();
+
+        for loc in changes {
+            let around = std::iter::once(*loc).chain(neighbours_of(loc).map(|(_, point)| point));
+            for point in around {
+                if !seen.insert(point) {
+                    continue;
+                }
+
+                for layer in &layers {
+                    // Neighbours which were never terrain are left alone and so is anything
+                    // which is not a terrain sprite (a painted rock stays a rock).
+                    if let Some(gid) = self.gid_at(map, &point, *layer) {
+                        let current = source.gid_at(*layer, &point).unwrap_or(0);
+                        if gid != 0 || (point == *loc && self.terrain_of(*layer, current).is_some()) {
+                            updates.push(TileUpdate { loc: point, layer: *layer, gid });
+                        }
+                    }
+                }
+            }
+        }
+
+        updates
+    }
+}
+
+// The squares around loc by their bit in a neighbour mask.  Those off the top or left of
+// the map are skipped and the rest are up to whoever looks them up.
+fn neighbours_of(loc: &Point) -> impl Iterator<Item=(usize, Point)> {
+    let loc = *loc;
+
+    SQUARE_8.iter().enumerate().filter_map(move |(bit, (dx, dy))| {
+        let (x, y) = (loc.x as isize + dx, loc.y as isize + dy);
+        if x < 0 || y < 0 { None } else { Some((bit, Point::new(x as usize, y as usize))) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::resources::autotile::*;
+    use crate::resources::{Map, MapSource, Point, Tile};
+
+    #[test]
+    fn test_four_bit_index() {
+        assert_eq!(Terrain::four_bit_index(0), 0);
+        assert_eq!(Terrain::four_bit_index(N | S), 5);
+        assert_eq!(Terrain::four_bit_index(N | E | S | W | NE | SW), 15);
+    }
+
+    #[test]
+    fn test_blob_mask() {
+        assert_eq!(Terrain::blob_mask(NW | NE | SW | SE), 0);
+        assert_eq!(Terrain::blob_mask(N | W | NW | NE), N | W | NW);
+        assert_eq!(Terrain::blob_mask(0xff), 0xff);
+    }
+
+    #[test]
+    fn test_refresh_updates_neighbours() {
+        let tiler = AutoTiler::default().with_terrain(Terrain::four_bit('#', 1, FENCE_SPRITES));
+        let mut map = Map::new(3, 3, '.', 1);
+
+        let mut source = MapSource { width: 3, height: 3, layers: vec![vec![1; 9], vec![0; 9]] };
+
+        map.set_at(&Point::new(0, 1), Tile::new('#', 1)).unwrap();
+        map.set_at(&Point::new(1, 1), Tile::new('#', 1)).unwrap();
+        let changes = map.take_changes();
+        let updates = tiler.refresh(&map, &source, &changes);
+
+        // west end and east end of a two post fence
+        assert!(updates.contains(&TileUpdate { loc: Point::new(0, 1), layer: 1, gid: 15 }));
+        assert!(updates.contains(&TileUpdate { loc: Point::new(1, 1), layer: 1, gid: 14 }));
+        assert_eq!(updates.len(), 2);
+
+        source.set_gid(1, &Point::new(0, 1), 15);
+        source.set_gid(1, &Point::new(1, 1), 14);
+        map.set_at(&Point::new(1, 1), Tile::new('.', 1)).unwrap();
+        let changes = map.take_changes();
+        let updates = tiler.refresh(&map, &source, &changes);
+
+        assert!(updates.contains(&TileUpdate { loc: Point::new(1, 1), layer: 1, gid: 0 }));
+        assert!(updates.contains(&TileUpdate { loc: Point::new(0, 1), layer: 1, gid: 5 }));
+    }
+
+    #[test]
+    fn test_refresh_keeps_painted_solids() {
+        let tiler = AutoTiler::default().with_terrain(Terrain::four_bit('#', 1, FENCE_SPRITES));
+        let mut map = Map::new(3, 3, '.', 1);
+        let mut source = MapSource { width: 3, height: 3, layers: vec![vec![1; 9], vec![0; 9]] };
+        let (fence, rock) = (Point::new(0, 1), Point::new(1, 1));
+
+        // A rock painted next to a fence is solid but no fence sprite.
+        source.set_gid(1, &fence, 5);
+        source.set_gid(1, &rock, 22);
+        map.set_at(&fence, Tile::new('#', 1)).unwrap();
+        map.set_at(&rock, Tile::new('%', 1)).unwrap();
+        let changes = map.take_changes();
+        let updates = tiler.refresh(&map, &source, &changes);
+
+        assert!(updates.iter().all(|update| update.loc != rock));
+        assert!(updates.contains(&TileUpdate { loc: fence, layer: 1, gid: 5 }));
+        assert_eq!(tiler.terrain_of(1, 5), Some('#'));
+        assert_eq!(tiler.terrain_of(1, 22), None);
+        assert_eq!(tiler.terrain_of(0, 5), None);
+    }
+
+    #[test]
+    fn test_blob_rules() {
+        let mut sprites = HashMap::new();
+        sprites.insert(N | W | NW, 3);
+        let tiler = AutoTiler::default().with_terrain(Terrain::blob('~', 0, sprites, 7));
+        let mut map = Map::new(3, 2, '~', 1);
+        map.set_at(&Point::new(2, 0), Tile::new('.', 1)).unwrap();
+        map.set_at(&Point::new(2, 1), Tile::new('.', 1)).unwrap();
+
+        assert_eq!(tiler.gid_at(&map, &Point::new(1, 1), 0), Some(4));
+        assert_eq!(tiler.gid_at(&map, &Point::new(0, 0), 0), Some(8)); // fallback
+        assert_eq!(tiler.gid_at(&map, &Point::new(2, 1), 0), Some(0));
+        assert_eq!(tiler.gid_at(&map, &Point::new(2, 1), 1), None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use pathfinding::prelude::astar;
+
+use crate::generation::FLOOR;
+use crate::resources::map::MyError;
+use crate::resources::topology::SQUARE_8;
+use crate::resources::{chunk_dimensions, Gids, MapChunk, MapSource, NavTiles, Point, Square8, Tile, Topology};
+
+/// The current map as far as its chunks are loaded.  Navigation and the gids of what is
+/// shown are read (and changed) through here.  Anything in a chunk which is not loaded
+/// reads as None and cannot be changed.  `WorldChunks` puts chunks in and takes them out
+/// again as the camera moves.
+///
+/// Chunked maps are Square8 like every map read from tiled or generated.
+pub struct ChunkedMap {
+    width: usize,
+    height: usize,
+    chunks: HashMap<Point, MapChunk>,
+    changes: Vec<Point>,
+}
+
+impl ChunkedMap {
+    /// A map of width by height tiles with none of its chunks loaded yet.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, chunks: HashMap::new(), changes: vec![] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of chunks across and down.
+    pub fn chunk_dimensions(&self) -> (usize, usize) {
+        chunk_dimensions(self.width, self.height)
+    }
+
+    pub fn chunk(&self, coord: &Point) -> Option<&MapChunk> {
+        self.chunks.get(coord)
+    }
+
+    /// Chunks which are loaded.
+    pub fn coords(&self) -> HashSet<Point> {
+        self.chunks.keys().cloned().collect()
+    }
+
+    pub fn insert(&mut self, chunk: MapChunk) {
+        self.chunks.insert(chunk.coord, chunk);
+    }
+
+    pub fn remove(&mut self, coord: &Point) -> Option<MapChunk> {
+        self.chunks.remove(coord)
+    }
+
+    /// Take out every chunk.
+    pub fn drain(&mut self) -> Vec<MapChunk> {
+        self.chunks.drain().map(|(_, chunk)| chunk).collect()
+    }
+
+    pub fn at(&self, loc: &Point) -> Option<&Tile> {
+        self.chunks.get(&MapSource::chunk_of(loc))?.tile_at(loc)
+    }
+
+    /// Changes show up in `take_changes` like they do for a `Map`.
+    pub fn set_at(&mut self, loc: &Point, tile: Tile) -> Result<(), MyError> {
+        self.chunks.get_mut(&MapSource::chunk_of(loc)).ok_or(MyError {})?.set_tile(loc, tile)?;
+        self.changes.push(*loc);
+        Ok(())
+    }
+
+    /// gid on layer at loc.  0 for no tile or a chunk which is not loaded.
+    pub fn gid(&self, layer: usize, loc: &Point) -> u32 {
+        self.gid_at(layer, loc).unwrap_or(0)
+    }
+
+    /// Returns false if nothing changed (or the chunk is not loaded).
+    pub fn set_gid(&mut self, layer: usize, loc: &Point, gid: u32) -> bool {
+        self.chunks.get_mut(&MapSource::chunk_of(loc)).map_or(false, |chunk| chunk.set_gid(layer, loc, gid))
+    }
+
+    /// Locations changed by `set_at` since the last time we asked.
+    pub fn take_changes(&mut self) -> Vec<Point> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Hand locs out with the next `take_changes` as if they had been set.
+    pub fn mark_changed<I: IntoIterator<Item = Point>>(&mut self, locs: I) {
+        self.changes.extend(locs);
+    }
+
+    pub fn distance(&self, p1: &Point, p2: &Point) -> usize {
+        Square8.distance(p1, p2)
+    }
+
+    /// Loaded tiles next to loc.
+    pub fn adjacent_ats<'a>(&'a self, loc: Point) -> impl Iterator<Item=(Point, &'a Tile)> + 'a {
+        SQUARE_8.iter().filter_map(move |(dx, dy)| {
+            let (x, y) = (loc.x as isize + dx, loc.y as isize + dy);
+            if x < 0 || y < 0 {
+                return None;
+            }
+
+            let next = Point::new(x as usize, y as usize);
+            self.at(&next).map(|tile| (next, tile))
+        })
+    }
+
+    /// Same as `Map::nearest_reachable` but never leaves the loaded chunks.
+    pub fn nearest_reachable<P, F>(&self, origin: &Point, max_steps: usize, passable: P, matches: F) -> Option<(Point, &Tile)>
+        where P: Fn(&Point, &Tile) -> bool, F: Fn(&Point, &Tile) -> bool {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        self.at(origin)?;
+        seen.insert(*origin);
+        queue.push_back((*origin, 0));
+
+        while let Some((loc, steps)) = queue.pop_front() {
+            let tile = self.at(&loc).unwrap();
+            if matches(&loc, tile) {
+                return Some((loc, tile));
+            }
+            if steps == max_steps {
+                continue;
+            }
+
+            for (next, next_tile) in self.adjacent_ats(loc) {
+                if passable(&next, next_tile) && seen.insert(next) {
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Same as `Map::shortest_path` but never leaves the loaded chunks.
+    pub fn shortest_path(&self, start: &Point, end: &Point) -> Option<(Vec<Point>, usize)> {
+        astar(start,
+              |i| self.adjacent_ats(*i).filter(|(_, tile)| tile.id == FLOOR).map(|(i, tile)| (i, tile.weight())),
+              |i| self.distance(i, end),
+              |i| i == end)
+    }
+}
+
+impl NavTiles for ChunkedMap {
+    fn tile_at(&self, loc: &Point) -> Option<&Tile> {
+        self.at(loc)
+    }
+}
+
+impl Gids for ChunkedMap {
+    fn gid_at(&self, layer: usize, loc: &Point) -> Option<u32> {
+        self.chunks.get(&MapSource::chunk_of(loc))?.gid_at(layer, loc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::chunked_map::*;
+    use crate::resources::{Map, CHUNK_SIZE};
+
+    // 20x18 so the chunks on the right and bottom are partial.  A wall runs down x = 15 and
+    // has a gap at the bottom.
+    fn whole() -> (MapSource, Map) {
+        let (width, height) = (20, 18);
+        let mut source = MapSource { width, height, layers: vec![vec![1; width * height], vec![0; width * height]] };
+        let mut nav = Map::new(width, height, FLOOR, 1);
+
+        for y in (0..height).filter(|y| *y != 17) {
+            source.set_gid(1, &Point::new(15, y), 5);
+            nav.set_at(&Point::new(15, y), Tile::new('#', 1)).unwrap();
+        }
+
+        (source, nav)
+    }
+
+    fn loaded(coords: &[(usize, usize)]) -> ChunkedMap {
+        let (source, nav) = whole();
+        let mut map = ChunkedMap::new(source.width, source.height);
+
+        for (x, y) in coords {
+            map.insert(MapChunk::cut(&source, &nav, Point::new(*x, *y)));
+        }
+
+        map
+    }
+
+    #[test]
+    fn test_reads_through_chunks() {
+        let mut map = loaded(&[(0, 0), (1, 0)]);
+
+        assert_eq!(map.chunk_dimensions(), (2, 2));
+        assert_eq!(map.at(&Point::new(15, 3)).map(|tile| tile.id), Some('#'));
+        assert_eq!(map.at(&Point::new(16, 3)).map(|tile| tile.id), Some(FLOOR));
+        assert_eq!(map.gid(1, &Point::new(15, 3)), 5);
+        assert_eq!(map.gid_at(1, &Point::new(19, 15)), Some(0));
+
+        // Not loaded and off the map both read as nothing.
+        assert_eq!(map.at(&Point::new(3, CHUNK_SIZE)), None);
+        assert_eq!(map.gid_at(0, &Point::new(3, CHUNK_SIZE)), None);
+        assert_eq!(map.at(&Point::new(20, 3)), None);
+        assert!(map.set_at(&Point::new(3, CHUNK_SIZE), Tile::new('#', 1)).is_err());
+        assert!(!map.set_gid(0, &Point::new(3, CHUNK_SIZE), 2));
+
+        map.set_at(&Point::new(15, 3), Tile::new(FLOOR, 1)).unwrap();
+        assert!(map.set_gid(1, &Point::new(15, 3), 0));
+        assert!(!map.set_gid(1, &Point::new(15, 3), 0));
+        assert_eq!(map.take_changes(), vec![Point::new(15, 3)]);
+        let walls = map.chunk(&Point::new(0, 0)).unwrap().tiles().filter(|(_, tile)| tile.id != FLOOR).count();
+        assert_eq!(walls, CHUNK_SIZE - 1);
+    }
+
+    #[test]
+    fn test_paths_stay_in_loaded_chunks() {
+        let (start, end) = (Point::new(3, 3), Point::new(18, 3));
+
+        // Only the gap in the wall gets through and it is in a chunk which is not loaded.
+        assert_eq!(loaded(&[(0, 0), (1, 0)]).shortest_path(&start, &end), None);
+
+        let (path, cost) = loaded(&[(0, 0), (1, 0), (0, 1), (1, 1)]).shortest_path(&start, &end).unwrap();
+        assert!(path.contains(&Point::new(15, 17)));
+        assert_eq!(cost, path.len() - 1);
+
+        let map = loaded(&[(0, 0), (1, 0)]);
+        let floor = |_: &Point, tile: &Tile| tile.id == FLOOR;
+        assert_eq!(map.nearest_reachable(&start, 20, floor, |loc, _| loc.x > 15), None);
+        assert_eq!(map.nearest_reachable(&Point::new(16, 0), 2, floor, |loc, _| loc.x == 18).map(|(loc, _)| loc), Some(Point::new(18, 0)));
+    }
+}
@@ -0,0 +1,762 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use amethyst::assets::Handle;
+use amethyst::ecs::Entity;
+use amethyst::renderer::{SpriteRender, SpriteSheet};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Bound, Crop, DepthSort, ItemStack, Position, TileAnimation};
+use crate::generation::FLOOR;
+use crate::resources::map::MyError;
+use crate::resources::{ChunkedMap, Map, NavTiles, Point, Tile};
+
+/// Width and height of a chunk in tiles.
+pub const CHUNK_SIZE: usize = 16;
+/// Chunks this many chunks away from the camera's chunk are loaded.
+pub const LOAD_RADIUS: usize = 1;
+/// Chunks further than this are evicted.  Larger than LOAD_RADIUS so walking back and
+/// forth over a chunk border does not keep loading and evicting the same chunks.
+pub const EVICT_RADIUS: usize = 2;
+
+/// Number of chunks across and down a map of width by height tiles.
+pub fn chunk_dimensions(width: usize, height: usize) -> (usize, usize) {
+    ((width + CHUNK_SIZE - 1) / CHUNK_SIZE, (height + CHUNK_SIZE - 1) / CHUNK_SIZE)
+}
+
+/// Gids of a map by layer.  Either a whole `MapSource` or the loaded chunks of the current
+/// map (`ChunkedMap`) so the editor and auto tiling work on both.
+pub trait Gids {
+    /// gid on layer at loc (0 for no tile) or None if loc is off the map (or in a chunk
+    /// which is not loaded).
+    fn gid_at(&self, layer: usize, loc: &Point) -> Option<u32>;
+}
+
+/// Tile layers of a map as read from tiled.  Gids are stored row by row with 0,0 being
+/// the upper left just like `Map`.
+#[derive(Clone)]
+pub struct MapSource {
+    pub width: usize,
+    pub height: usize,
+    pub layers: Vec<Vec<u32>>,
+}
+
+impl MapSource {
+    pub fn gid(&self, layer: usize, loc: &Point) -> u32 {
+        self.layers[layer][loc.y * self.width + loc.x]
+    }
+
+    pub fn set_gid(&mut self, layer: usize, loc: &Point, gid: u32) {
+        self.layers[layer][loc.y * self.width + loc.x] = gid;
+    }
+
+    /// One layer as a `Map` of gids.
+    pub fn layer_map(&self, layer: usize) -> Map<u32> {
+        let mut map = Map::filled(self.width, self.height, 0);
+        map.update_all(|loc, _| self.gid(layer, &loc));
+        map.clear_history(); // a fresh copy with nothing to undo
+        map.take_changes();
+        map
+    }
+
+    pub fn chunk_of(loc: &Point) -> Point {
+        Point::new(loc.x / CHUNK_SIZE, loc.y / CHUNK_SIZE)
+    }
+
+    /// Number of chunks across and down.
+    pub fn chunk_dimensions(&self) -> (usize, usize) {
+        chunk_dimensions(self.width, self.height)
+    }
+}
+
+impl Gids for MapSource {
+    fn gid_at(&self, layer: usize, loc: &Point) -> Option<u32> {
+        if loc.x >= self.width || loc.y >= self.height {
+            None
+        } else if layer >= self.layers.len() {
+            Some(0)
+        } else {
+            Some(self.gid(layer, loc))
+        }
+    }
+}
+
+/// Gids and navigation tiles of one chunk of a map.  Chunks on the right and bottom edge
+/// of a map are cut short.  Locations going in and out are the map's and not the chunk's.
+pub struct MapChunk {
+    pub coord: Point,
+    source: MapSource,
+    nav: Map,
+    changed: bool, // since it was read from the chunk store
+}
+
+// How a chunk is written to the chunk store.  Navigation tiles are only their ids since
+// they all weigh 1.
+#[derive(Deserialize, Serialize)]
+struct StoredChunk {
+    width: usize,
+    height: usize,
+    layers: Vec<Vec<u32>>,
+    nav: String,
+}
+
+impl MapChunk {
+    /// Cut the chunk at coord out of a whole map.
+    pub fn cut(source: &MapSource, nav: &Map, coord: Point) -> Self {
+        let (left, top) = (coord.x * CHUNK_SIZE, coord.y * CHUNK_SIZE);
+        let (width, height) = (CHUNK_SIZE.min(source.width - left), CHUNK_SIZE.min(source.height - top));
+        let layers = vec![vec![0; width * height]; source.layers.len()];
+        let mut chunk = Self {
+            coord,
+            source: MapSource { width, height, layers },
+            nav: Map::new(width, height, FLOOR, 1),
+            changed: true,
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let (local, loc) = (Point::new(x, y), Point::new(left + x, top + y));
+                for layer in 0..source.layers.len() {
+                    chunk.source.set_gid(layer, &local, source.gid(layer, &loc));
+                }
+                if let Some(tile) = nav.at(&loc) {
+                    chunk.nav.set_at(&local, tile.clone()).unwrap();
+                }
+            }
+        }
+        chunk.nav.take_changes();
+        chunk.nav.clear_history();
+
+        chunk
+    }
+
+    fn origin(&self) -> Point {
+        Point::new(self.coord.x * CHUNK_SIZE, self.coord.y * CHUNK_SIZE)
+    }
+
+    // Where loc is within the chunk or None if it is not in this chunk.
+    fn local(&self, loc: &Point) -> Option<Point> {
+        let origin = self.origin();
+
+        if loc.x < origin.x || loc.y < origin.y || loc.x - origin.x >= self.source.width || loc.y - origin.y >= self.source.height {
+            None
+        } else {
+            Some(Point::new(loc.x - origin.x, loc.y - origin.y))
+        }
+    }
+
+    /// Navigation tiles of the chunk with where they are on the map.
+    pub fn tiles<'a>(&'a self) -> impl Iterator<Item=(Point, &'a Tile)> + 'a {
+        let origin = self.origin();
+
+        self.nav.iter().map(move |(loc, tile)| (Point::new(origin.x + loc.x, origin.y + loc.y), tile))
+    }
+
+    pub fn set_tile(&mut self, loc: &Point, tile: Tile) -> Result<(), MyError> {
+        let local = self.local(loc).ok_or(MyError {})?;
+
+        self.nav.set_at(&local, tile)?;
+        self.nav.take_changes(); // `ChunkedMap` hands out changes in map coordinates
+        self.nav.clear_history(); // undo is the editor's business
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Returns false if nothing changed.
+    pub fn set_gid(&mut self, layer: usize, loc: &Point, gid: u32) -> bool {
+        match self.local(loc) {
+            Some(local) if layer < self.source.layers.len() && self.source.gid(layer, &local) != gid => {
+                self.source.set_gid(layer, &local, gid);
+                self.changed = true;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn to_ron(&self) -> String {
+        let stored = StoredChunk {
+            width: self.source.width,
+            height: self.source.height,
+            layers: self.source.layers.clone(),
+            nav: self.nav.iter().map(|(_, tile)| tile.id).collect(),
+        };
+
+        ron::ser::to_string(&stored).expect("Failed to write chunk")
+    }
+
+    fn from_ron(coord: Point, text: &str) -> Result<Self, String> {
+        let StoredChunk { width, height, layers, nav: ids } = ron::de::from_str(text).map_err(|e| e.to_string())?;
+        if ids.chars().count() != width * height || layers.iter().any(|layer| layer.len() != width * height) {
+            return Err(format!("chunk is not {}x{}", width, height));
+        }
+
+        let mut nav = Map::new(width, height, FLOOR, 1);
+        for (index, id) in ids.chars().enumerate() {
+            nav.set_at(&Point::new(index % width, index / width), Tile::new(id, 1)).unwrap();
+        }
+        nav.take_changes();
+        nav.clear_history();
+
+        Ok(Self { coord, source: MapSource { width, height, layers }, nav, changed: false })
+    }
+}
+
+impl NavTiles for MapChunk {
+    fn tile_at(&self, loc: &Point) -> Option<&Tile> {
+        self.nav.at(&self.local(loc)?)
+    }
+}
+
+impl Gids for MapChunk {
+    fn gid_at(&self, layer: usize, loc: &Point) -> Option<u32> {
+        self.source.gid_at(layer, &self.local(loc)?)
+    }
+}
+
+pub struct ChunkTile {
+    pub layer: usize,
+    pub loc: Point,
+    pub gid: u32,
+}
+
+/// Everything needed to spawn the tiles of a chunk.  Built on the chunk worker thread.
+pub struct ChunkData {
+    pub map_id: String,
+    pub coord: Point,
+    pub tiles: Vec<ChunkTile>,
+}
+
+impl ChunkData {
+    fn build(map_id: String, chunk: &MapChunk) -> Self {
+        let (origin, width) = (chunk.origin(), chunk.source.width);
+        let mut tiles = vec![];
+
+        for (layer, gids) in chunk.source.layers.iter().enumerate() {
+            // gids 1-based. 0 means nothing.
+            for (index, gid) in gids.iter().enumerate().filter(|(_, gid)| **gid != 0) {
+                let loc = Point::new(origin.x + index % width, origin.y + index / width);
+                tiles.push(ChunkTile { layer, loc, gid: *gid });
+            }
+        }
+
+        Self { map_id, coord: chunk.coord, tiles }
+    }
+}
+
+/// A non-tile entity which was living in a chunk when it got evicted.
+///
+/// Only the components with a field here survive eviction; anything else on the entity is
+/// lost.  A new component which has to outlive its chunk needs a field here and a line
+/// in both the eviction and the respawn of `ChunkStreamingSystem`.
+pub struct PersistedEntity {
+    pub position: Position,
+    pub translation: Option<Vector3<f32>>, // None for the middle of its tile
+    pub sprite: Option<SpriteRender>,
+    pub animation: Option<TileAnimation>,
+    pub solid: bool,
+    pub bound: Option<Bound>,
+    pub depth_sort: Option<DepthSort>, // None for crops from a save.  They get their map's.
+    pub loose: bool,
+    pub stack: Option<ItemStack>,
+    pub crop: Option<Crop>,
+}
+
+// What the chunk worker is asked to do.  Jobs are done in the order they are sent so a
+// chunk is always stored before it can be loaded again.
+enum ChunkJob {
+    Split(String, MapSource, Map), // a freshly read map
+    Load(String, usize, Point), // generation of `WorldChunks` it is loaded for
+    Store(String, MapChunk),
+    Forget(String),
+}
+
+type ChunkResult = (usize, ChunkData, MapChunk);
+
+// Every `WorldChunks` (tests make lots of them) gets a chunk store of its own.
+static STORES: AtomicUsize = AtomicUsize::new(0);
+
+// Chunks of every map visited so far with one ron file per chunk.  Only the chunk worker
+// uses it and the files go away with it.
+struct ChunkStore {
+    dir: PathBuf,
+    maps: HashMap<String, PathBuf>,
+    made: usize, // map directories ever made
+}
+
+impl ChunkStore {
+    fn new() -> Self {
+        let store = STORES.fetch_add(1, Ordering::Relaxed);
+
+        Self {
+            dir: env::temp_dir().join(format!("bountiful-chunks-{}-{}", process::id(), store)),
+            maps: HashMap::new(),
+            made: 0,
+        }
+    }
+
+    fn path(&mut self, map_id: &str, coord: &Point) -> PathBuf {
+        let (dir, made) = (&self.dir, &mut self.made);
+        let map_dir = self.maps.entry(map_id.to_string()).or_insert_with(|| {
+            // Numbered since map ids can be paths.
+            let map_dir = dir.join(made.to_string());
+            *made += 1;
+            fs::create_dir_all(&map_dir).expect("Failed to make chunk store");
+            map_dir
+        });
+
+        map_dir.join(format!("{}_{}.ron", coord.x, coord.y))
+    }
+
+    fn split(&mut self, map_id: &str, source: &MapSource, nav: &Map) {
+        let (across, down) = source.chunk_dimensions();
+
+        for y in 0..down {
+            for x in 0..across {
+                self.write(map_id, &MapChunk::cut(source, nav, Point::new(x, y)));
+            }
+        }
+    }
+
+    fn write(&mut self, map_id: &str, chunk: &MapChunk) {
+        let path = self.path(map_id, &chunk.coord);
+
+        fs::write(&path, chunk.to_ron()).unwrap_or_else(|e| panic!("Failed to store chunk {}: {}", path.display(), e));
+    }
+
+    fn read(&mut self, map_id: &str, coord: &Point) -> MapChunk {
+        let path = self.path(map_id, coord);
+        let text = fs::read_to_string(&path).unwrap_or_else(|e| panic!("Missing chunk {}: {}", path.display(), e));
+
+        MapChunk::from_ron(*coord, &text).unwrap_or_else(|e| panic!("Bad chunk {}: {}", path.display(), e))
+    }
+
+    fn forget(&mut self, map_id: &str) {
+        if let Some(map_dir) = self.maps.remove(map_id) {
+            let _ = fs::remove_dir_all(map_dir);
+        }
+    }
+}
+
+impl Drop for ChunkStore {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Streams the chunks of the current map in and out around the camera.  A map is split into
+/// chunks by a worker thread as soon as it is read and only the chunks near the camera are
+/// in memory.  They are in the `ChunkedMap` of `MapManager` (gids and navigation tiles) and
+/// here (their entities).  The rest wait in a chunk store on disk with whatever changed in
+/// them, so walking across a large world (or through lots of maps) keeps memory bounded.
+/// The worker loads and stores chunks and prepares their tiles.  The
+/// `ChunkStreamingSystem` decides which chunks are wanted and spawns them.
+///
+/// The chunk store keeps every map visited until the game ends, so farm plots, mined tiles
+/// and editor changes are all still there when the player comes back.  Only the first
+/// visit (or reading a map file again) puts plots and mined tiles in from `Farm` and
+/// `Mining`.  Later changes to chunks which are not loaded wait in `patch` until they are.
+///
+/// `edits` are the changes the editor made to each map as (gid before the first change,
+/// gid now).  Saving writes the map file with them on top and reading the map file again
+/// puts them back.  Farm plots and mined tiles are not map file changes so they never get
+/// saved.
+///
+/// `persisted` only ever holds loose items and crops since everything else with a
+/// `Position` is a tile or the player.  Those are game state (a save has them too) and stay
+/// until their chunk is loaded again however long ago their map was visited.
+pub struct WorldChunks {
+    pub map_id: Option<String>,
+    pub edits: HashMap<(String, Point, usize), (u32, u32)>, // (map, loc, layer) not saved yet
+    pub sprite_sheet: Option<Handle<SpriteSheet>>,
+    pub animations: HashMap<u32, TileAnimation>,
+    pub loaded: HashMap<Point, Vec<Entity>>,
+    pub tiles: HashMap<(Point, usize), Entity>, // (loc, layer) of every loaded tile
+    pub evicting: Vec<(String, Point, Vec<Entity>)>,
+    pub persisted: HashMap<(String, Point), Vec<PersistedEntity>>,
+    stored: HashSet<String>, // maps in the chunk store
+    generation: usize, // goes up with every set_map so chunks for an earlier one are dropped
+    pending: HashSet<Point>,
+    rebuilt: Vec<ChunkData>,
+    patches: HashMap<(String, Point), Vec<(usize, Point, u32)>>, // (layer, loc, gid) by chunk
+    jobs: Mutex<Sender<ChunkJob>>,
+    results: Mutex<Receiver<ChunkResult>>,
+}
+
+impl Default for WorldChunks {
+    fn default() -> Self {
+        let (job_sender, job_receiver) = channel::<ChunkJob>();
+        let (result_sender, result_receiver) = channel::<ChunkResult>();
+
+        thread::spawn(move || {
+            let mut store = ChunkStore::new();
+
+            for job in job_receiver {
+                match job {
+                    ChunkJob::Split(map_id, source, nav) => store.split(&map_id, &source, &nav),
+                    ChunkJob::Store(map_id, chunk) => store.write(&map_id, &chunk),
+                    ChunkJob::Forget(map_id) => store.forget(&map_id),
+                    ChunkJob::Load(map_id, generation, coord) => {
+                        let chunk = store.read(&map_id, &coord);
+                        let data = ChunkData::build(map_id, &chunk);
+                        if result_sender.send((generation, data, chunk)).is_err() {
+                            break;
+                        }
+                    },
+                }
+            }
+        });
+
+        Self {
+            map_id: None,
+            edits: HashMap::new(),
+            sprite_sheet: None,
+            animations: HashMap::new(),
+            loaded: HashMap::new(),
+            tiles: HashMap::new(),
+            evicting: vec![],
+            persisted: HashMap::new(),
+            stored: HashSet::new(),
+            generation: 0,
+            pending: HashSet::new(),
+            rebuilt: vec![],
+            patches: HashMap::new(),
+            jobs: Mutex::new(job_sender),
+            results: Mutex::new(result_receiver),
+        }
+    }
+}
+
+impl WorldChunks {
+    /// Has the chunk store got map_id from an earlier visit.
+    pub fn is_stored(&self, map_id: &str) -> bool {
+        self.stored.contains(map_id)
+    }
+
+    /// Hand a freshly read map over to the chunk store.  It gets split into chunks on the
+    /// worker and is not kept whole anywhere.
+    pub fn store_map(&mut self, map_id: &str, source: MapSource, nav: Map) {
+        self.stored.insert(map_id.to_string());
+        self.send(ChunkJob::Split(map_id.to_string(), source, nav));
+    }
+
+    /// Throw away the stored chunks of a map (and anything which changed in them) so the
+    /// next visit reads its map file again.
+    pub fn forget(&mut self, map_id: &str) {
+        self.patches.retain(|(id, _), _| id != map_id);
+        if self.stored.remove(map_id) {
+            self.send(ChunkJob::Forget(map_id.to_string()));
+        }
+    }
+
+    /// Change a gid in a chunk of a stored map which is not loaded.  The change is made when
+    /// the chunk next gets loaded.  A map which is not stored yet gets it from `Farm` or
+    /// `Mining` on its first visit anyway.
+    pub fn patch(&mut self, map_id: &str, layer: usize, loc: &Point, gid: u32) {
+        if self.stored.contains(map_id) {
+            let key = (map_id.to_string(), MapSource::chunk_of(loc));
+            self.patches.entry(key).or_insert_with(Vec::new).push((layer, *loc, gid));
+        }
+    }
+
+    /// Start streaming a map which is in the chunk store.  Whatever was streamed before has
+    /// to be unloaded first.
+    pub fn set_map(&mut self, map_id: &str, sprite_sheet: Handle<SpriteSheet>, animations: HashMap<u32, TileAnimation>) {
+        self.map_id = Some(map_id.to_string());
+        self.generation += 1;
+        self.sprite_sheet = Some(sprite_sheet);
+        self.animations = animations;
+    }
+
+    /// Evict every chunk of the current map.  map is the `ChunkedMap` it was streamed into.
+    pub fn unload(&mut self, map: Option<&mut ChunkedMap>) {
+        let coords: Vec<Point> = self.loaded.keys().cloned().collect();
+        for coord in coords {
+            self.evict_entities(&coord);
+        }
+        for chunk in map.map_or_else(Vec::new, |map| map.drain()) {
+            self.store(chunk);
+        }
+
+        self.pending.clear();
+        self.rebuilt.clear();
+        self.map_id = None;
+    }
+
+    /// Take a chunk out of map and back to the chunk store.  Its entities go too.
+    pub fn evict(&mut self, coord: &Point, map: &mut ChunkedMap) {
+        self.evict_entities(coord);
+        if let Some(chunk) = map.remove(coord) {
+            self.store(chunk);
+        }
+    }
+
+    fn evict_entities(&mut self, coord: &Point) {
+        if let (Some(map_id), Some(entities)) = (&self.map_id, self.loaded.remove(coord)) {
+            self.tiles.retain(|(loc, _), _| MapSource::chunk_of(loc) != *coord);
+            self.evicting.push((map_id.clone(), *coord, entities));
+        }
+    }
+
+    // Only chunks which changed since they were read need writing.
+    fn store(&mut self, chunk: MapChunk) {
+        if let Some(map_id) = self.map_id.clone().filter(|_| chunk.changed) {
+            self.send(ChunkJob::Store(map_id, chunk));
+        }
+    }
+
+    /// Throw away the entities of a chunk and build them again from what is in map.
+    pub fn reload(&mut self, coord: &Point, map: &ChunkedMap) {
+        if let (Some(map_id), Some(chunk)) = (self.map_id.clone(), map.chunk(coord)) {
+            self.evict_entities(coord);
+            self.rebuilt.push(ChunkData::build(map_id, chunk));
+        }
+    }
+
+    /// Forget the entity of a loaded tile so it can be deleted without its chunk noticing.
+    pub fn take_tile(&mut self, loc: &Point, layer: usize) -> Option<Entity> {
+        let entity = self.tiles.remove(&(*loc, layer))?;
+        if let Some(entities) = self.loaded.get_mut(&MapSource::chunk_of(loc)) {
+            entities.retain(|loaded| *loaded != entity);
+        }
+
+        Some(entity)
+    }
+
+    /// Remember the editor changing a tile of the current map from before to gid.  Going
+    /// back to what it was before the first change leaves nothing to save.
+    pub fn edit(&mut self, layer: usize, loc: &Point, before: u32, gid: u32) {
+        let map_id = match &self.map_id {
+            Some(map_id) => map_id.clone(),
+            None => return,
+        };
+        let key = (map_id, *loc, layer);
+        let first = self.edits.get(&key).map_or(before, |(first, _)| *first);
+
+        if first == gid {
+            self.edits.remove(&key);
+        } else {
+            self.edits.insert(key, (first, gid));
+        }
+    }
+
+    /// Editor changes to map_id as gids by (loc, layer).
+    pub fn edits_of(&self, map_id: &str) -> HashMap<(Point, usize), u32> {
+        self.edits.iter()
+            .filter(|((id, _, _), _)| id == map_id)
+            .map(|((_, loc, layer), (_, gid))| ((*loc, *layer), *gid))
+            .collect()
+    }
+
+    /// Ask the worker for a chunk unless it is in map or already on its way.
+    pub fn request(&mut self, coord: Point, map: &ChunkedMap) {
+        if self.loaded.contains_key(&coord) || self.pending.contains(&coord) || map.chunk(&coord).is_some() {
+            return;
+        }
+
+        if let Some(map_id) = self.map_id.clone() {
+            self.pending.insert(coord);
+            self.send(ChunkJob::Load(map_id, self.generation, coord));
+        }
+    }
+
+    /// Chunks which are ready to be spawned.  Chunks the worker loaded go into map and
+    /// leftovers for a map we already left are dropped.
+    pub fn finished(&mut self, map: &mut ChunkedMap) -> Vec<ChunkData> {
+        let results: Vec<ChunkResult> = self.results.lock().unwrap().try_iter().collect();
+        let mut finished = std::mem::take(&mut self.rebuilt);
+        finished.retain(|data| map.chunk(&data.coord).is_some()); // evicted since
+
+        for (generation, data, mut chunk) in results {
+            if generation == self.generation && self.pending.remove(&data.coord) {
+                let patches = self.patches.remove(&(data.map_id.clone(), data.coord)).unwrap_or_default();
+                for (layer, loc, gid) in &patches {
+                    chunk.set_gid(*layer, loc, *gid);
+                }
+
+                finished.push(if patches.is_empty() { data } else { ChunkData::build(data.map_id, &chunk) });
+                map.insert(chunk);
+            }
+        }
+
+        finished
+    }
+
+    fn send(&self, job: ChunkJob) {
+        self.jobs.lock().unwrap().send(job).expect("Chunk worker died");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use amethyst::ecs::{Builder, World, WorldExt};
+
+    use crate::resources::chunks::*;
+
+    // 20x18 so the chunks on the right and bottom are partial.
+    fn source() -> MapSource {
+        let (width, height) = (20, 18);
+        let ground = vec![1; width * height];
+        let mut solids = vec![0; width * height];
+        solids[17] = 5; // 17,0
+        solids[width + 3] = 6; // 3,1
+
+        MapSource { width, height, layers: vec![ground, solids] }
+    }
+
+    fn nav() -> Map {
+        let mut nav = Map::new(20, 18, FLOOR, 1);
+        nav.set_at(&Point::new(17, 0), Tile::new('#', 1)).unwrap();
+        nav.set_at(&Point::new(3, 1), Tile::new('%', 1)).unwrap();
+        nav
+    }
+
+    // Whatever the worker sends back within a few seconds.
+    fn wait(chunks: &mut WorldChunks, map: &mut ChunkedMap) -> Vec<ChunkData> {
+        let started = Instant::now();
+        let mut finished = vec![];
+        while finished.is_empty() && started.elapsed() < Duration::from_secs(5) {
+            finished.extend(chunks.finished(map));
+        }
+
+        finished
+    }
+
+    #[test]
+    fn test_chunk_of() {
+        assert_eq!(MapSource::chunk_of(&Point::new(0, 0)), Point::new(0, 0));
+        assert_eq!(MapSource::chunk_of(&Point::new(CHUNK_SIZE - 1, CHUNK_SIZE)), Point::new(0, 1));
+        assert_eq!(MapSource::chunk_of(&Point::new(19, 17)), Point::new(1, 1));
+        assert_eq!(source().chunk_dimensions(), (2, 2));
+    }
+
+    #[test]
+    fn test_build() {
+        let (source, nav) = (source(), nav());
+        let chunk = ChunkData::build("test".to_string(), &MapChunk::cut(&source, &nav, Point::new(1, 0)));
+
+        // 4 columns by 16 rows of ground and the one solid in this chunk.
+        assert_eq!(chunk.tiles.iter().filter(|tile| tile.layer == 0).count(), 4 * CHUNK_SIZE);
+        let solids: Vec<(Point, u32)> = chunk.tiles.iter()
+            .filter(|tile| tile.layer == 1)
+            .map(|tile| (tile.loc, tile.gid))
+            .collect();
+        assert_eq!(solids, vec![(Point::new(17, 0), 5)]);
+
+        let corner = ChunkData::build("test".to_string(), &MapChunk::cut(&source, &nav, Point::new(1, 1)));
+        assert_eq!(corner.tiles.len(), 4 * 2);
+    }
+
+    #[test]
+    fn test_stored_chunk() {
+        let chunk = MapChunk::cut(&source(), &nav(), Point::new(1, 0));
+        let read = MapChunk::from_ron(Point::new(1, 0), &chunk.to_ron()).unwrap();
+
+        assert_eq!(read.gid_at(1, &Point::new(17, 0)), Some(5));
+        assert_eq!(read.tile_at(&Point::new(17, 0)).map(|tile| tile.id), Some('#'));
+        assert_eq!(read.tile_at(&Point::new(3, 1)), None); // in the chunk to the left
+        assert_eq!(read.tiles().count(), 4 * CHUNK_SIZE);
+        assert!(!read.changed);
+
+        assert!(MapChunk::from_ron(Point::new(0, 0), "(width: 2, height: 2, layers: [], nav: \"...\")").is_err());
+    }
+
+    #[test]
+    fn test_evict() {
+        let mut world = World::new();
+        let (tile, other, kept) = (world.create_entity().build(), world.create_entity().build(), world.create_entity().build());
+        let mut chunks = WorldChunks::default();
+        let mut map = ChunkedMap::new(20, 18);
+        map.insert(MapChunk::cut(&source(), &nav(), Point::new(0, 0)));
+        map.insert(MapChunk::cut(&source(), &nav(), Point::new(1, 0)));
+        chunks.map_id = Some("test".to_string());
+        chunks.loaded.insert(Point::new(0, 0), vec![tile, other]);
+        chunks.loaded.insert(Point::new(1, 0), vec![kept]);
+        chunks.tiles.insert((Point::new(3, 1), 1), tile);
+        chunks.tiles.insert((Point::new(17, 0), 1), kept);
+
+        assert_eq!(chunks.take_tile(&Point::new(3, 1), 1), Some(tile));
+        assert_eq!(chunks.loaded[&Point::new(0, 0)], vec![other]);
+
+        chunks.evict(&Point::new(0, 0), &mut map);
+        chunks.evict(&Point::new(0, 1), &mut map); // not loaded
+        assert_eq!(chunks.evicting, vec![("test".to_string(), Point::new(0, 0), vec![other])]);
+        assert_eq!(map.coords(), vec![Point::new(1, 0)].into_iter().collect());
+
+        chunks.unload(Some(&mut map));
+        assert_eq!(chunks.evicting.len(), 2);
+        assert!(chunks.loaded.is_empty());
+        assert!(chunks.tiles.is_empty());
+        assert!(map.coords().is_empty());
+        assert_eq!(chunks.map_id, None);
+    }
+
+    #[test]
+    fn test_edits() {
+        let mut chunks = WorldChunks::default();
+        chunks.edit(0, &Point::new(2, 2), 1, 7); // no map to edit
+        assert!(chunks.edits.is_empty());
+
+        chunks.map_id = Some("test".to_string());
+        chunks.edit(0, &Point::new(2, 2), 1, 7);
+        chunks.edit(0, &Point::new(2, 2), 7, 8);
+        assert_eq!(chunks.edits[&("test".to_string(), Point::new(2, 2), 0)], (1, 8));
+
+        // Putting back what was there before leaves nothing to save.
+        chunks.edit(1, &Point::new(3, 1), 6, 0);
+        chunks.edit(1, &Point::new(3, 1), 0, 6);
+        chunks.map_id = Some("other".to_string());
+        chunks.edit(0, &Point::new(0, 0), 1, 2);
+
+        assert_eq!(chunks.edits.len(), 2);
+        assert_eq!(chunks.edits_of("test"), vec![((Point::new(2, 2), 0), 8)].into_iter().collect());
+    }
+
+    #[test]
+    fn test_request() {
+        let mut chunks = WorldChunks::default();
+        let mut map = ChunkedMap::new(20, 18);
+        chunks.store_map("test", source(), nav());
+        chunks.store_map("elsewhere", source(), Map::new(20, 18, FLOOR, 1));
+        assert!(chunks.is_stored("test"));
+
+        chunks.map_id = Some("test".to_string());
+        chunks.request(Point::new(0, 0), &map);
+        chunks.request(Point::new(0, 0), &map); // already on its way
+        chunks.request(Point::new(1, 1), &map);
+
+        // Leftovers for a map we left get dropped.
+        chunks.unload(Some(&mut map));
+        chunks.map_id = Some("elsewhere".to_string());
+        chunks.generation += 1;
+        chunks.patch("elsewhere", 1, &Point::new(17, 0), 9);
+        chunks.patch("nowhere", 1, &Point::new(17, 0), 9); // not stored so it has nothing to patch
+        chunks.request(Point::new(1, 0), &map);
+
+        let finished = wait(&mut chunks, &mut map);
+        assert_eq!(finished.iter().map(|chunk| chunk.coord).collect::<Vec<Point>>(), vec![Point::new(1, 0)]);
+        assert!(finished.iter().all(|chunk| chunk.map_id == "elsewhere"));
+        assert!(finished[0].tiles.iter().any(|tile| tile.layer == 1 && tile.gid == 9));
+        assert_eq!(map.coords(), vec![Point::new(1, 0)].into_iter().collect());
+        assert_eq!(map.gid(1, &Point::new(17, 0)), 9);
+        assert_eq!(map.at(&Point::new(17, 0)).map(|tile| tile.id), Some(FLOOR));
+        assert_eq!(chunks.patches.len(), 0);
+
+        // What changed is stored with the chunk when it goes and is there when it comes back.
+        chunks.evict(&Point::new(1, 0), &mut map);
+        chunks.request(Point::new(1, 0), &map);
+        assert_eq!(wait(&mut chunks, &mut map).len(), 1);
+        assert_eq!(map.gid(1, &Point::new(17, 0)), 9);
+
+        chunks.forget("elsewhere");
+        assert!(!chunks.is_stored("elsewhere"));
+    }
+}
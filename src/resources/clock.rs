@@ -0,0 +1,186 @@
+use amethyst::ecs::Entity;
+
+/// Game minutes in a day.
+pub const MINUTES_PER_DAY: f32 = 24. * 60.;
+/// Minute of the day the player wakes up at.
+pub const MORNING: f32 = 6. * 60.;
+/// Game minutes passing each real second unless the options say otherwise.
+pub const MINUTES_PER_SECOND: f32 = 1.;
+pub const DAYS_PER_SEASON: u32 = 28;
+/// How dark the middle of the night gets (alpha of the tint).
+pub const NIGHT_DARKNESS: f32 = 0.6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+pub const WEEKDAYS: [Weekday; 7] = [Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday, Weekday::Thursday,
+    Weekday::Friday, Weekday::Saturday, Weekday::Sunday];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Fall,
+    Winter,
+}
+
+pub const SEASONS: [Season; 4] = [Season::Spring, Season::Summer, Season::Fall, Season::Winter];
+
+/// Time in the game world.  Day 0 is the first Monday of spring in year 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameClock {
+    pub day: u32,
+    pub minutes: f32, // into the current day
+    pub rate: f32, // game minutes per real second
+    pub paused: bool, // menus are open
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new(MINUTES_PER_SECOND)
+    }
+}
+
+impl GameClock {
+    pub fn new(rate: f32) -> Self {
+        Self { day: 0, minutes: MORNING, rate, paused: false }
+    }
+
+    /// Move time along by real seconds.  Returns how many days started.
+    pub fn advance(&mut self, seconds: f32) -> u32 {
+        if self.paused {
+            return 0;
+        }
+
+        self.minutes += seconds * self.rate;
+        let days = (self.minutes / MINUTES_PER_DAY) as u32;
+        self.day += days;
+        self.minutes -= days as f32 * MINUTES_PER_DAY;
+
+        days
+    }
+
+    /// Skip to the next morning.  After midnight the day has already started so this only
+    /// wakes up later the same day.  Returns how many days started.
+    pub fn sleep(&mut self) -> u32 {
+        let days = if self.minutes < MORNING { 0 } else { 1 };
+        self.day += days;
+        self.minutes = MORNING;
+
+        days
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        WEEKDAYS[self.day as usize % WEEKDAYS.len()]
+    }
+
+    pub fn season(&self) -> Season {
+        SEASONS[(self.day / DAYS_PER_SEASON) as usize % SEASONS.len()]
+    }
+
+    /// 1 for the first day of a season.
+    pub fn day_of_season(&self) -> u32 {
+        self.day % DAYS_PER_SEASON + 1
+    }
+
+    pub fn year(&self) -> u32 {
+        self.day / (DAYS_PER_SEASON * SEASONS.len() as u32) + 1
+    }
+
+    /// 24 hour time like 06:30.
+    pub fn time_of_day(&self) -> String {
+        let minutes = self.minutes as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+
+    /// 0 during the day up to 1 in the middle of the night.  Evenings get dark from 6 to
+    /// 9 and it gets light again from 5 to 8 in the morning.
+    pub fn darkness(&self) -> f32 {
+        let hour = self.minutes / 60.;
+
+        match hour {
+            hour if hour < 5. => 1.,
+            hour if hour < 8. => (8. - hour) / 3.,
+            hour if hour < 18. => 0.,
+            hour if hour < 21. => (hour - 18.) / 3.,
+            _ => 1.,
+        }
+    }
+
+    /// Color laid over the scene.  Dusk and dawn are warm, night is blue.
+    pub fn tint(&self) -> [f32; 4] {
+        let (warm, night) = ([0.9, 0.45, 0.15], [0.02, 0.04, 0.2]);
+        let darkness = self.darkness();
+        let mix = |i: usize| warm[i] + (night[i] - warm[i]) * darkness;
+
+        [mix(0), mix(1), mix(2), darkness * NIGHT_DARKNESS]
+    }
+}
+
+/// The full screen overlay which tints the scene for the time of day.
+#[derive(Default)]
+pub struct Lighting {
+    pub overlay: Option<Entity>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::clock::*;
+
+    #[test]
+    fn test_advance() {
+        let mut clock = GameClock::new(2.);
+
+        assert_eq!(clock.advance(60.), 0);
+        assert_eq!(clock.time_of_day(), "08:00");
+        clock.paused = true;
+        assert_eq!(clock.advance(60.), 0);
+        clock.paused = false;
+        assert_eq!(clock.advance(MINUTES_PER_DAY / 2.), 1);
+        assert_eq!((clock.day, clock.time_of_day()), (1, "08:00".to_string()));
+        assert_eq!(clock.advance(MINUTES_PER_DAY), 2);
+    }
+
+    #[test]
+    fn test_sleep() {
+        let mut clock = GameClock::default();
+
+        assert_eq!(clock.sleep(), 1);
+        assert_eq!((clock.day, clock.minutes), (1, MORNING));
+        clock.minutes = 60.; // slept past midnight
+        assert_eq!(clock.sleep(), 0);
+        assert_eq!((clock.day, clock.minutes), (1, MORNING));
+    }
+
+    #[test]
+    fn test_calendar() {
+        let mut clock = GameClock::default();
+
+        assert_eq!((clock.weekday(), clock.season(), clock.day_of_season(), clock.year()), (Weekday::Monday, Season::Spring, 1, 1));
+        clock.day = DAYS_PER_SEASON + 8;
+        assert_eq!((clock.weekday(), clock.season(), clock.day_of_season()), (Weekday::Tuesday, Season::Summer, 9));
+        clock.day = DAYS_PER_SEASON * 4;
+        assert_eq!((clock.season(), clock.year()), (Season::Spring, 2));
+    }
+
+    #[test]
+    fn test_darkness() {
+        let mut clock = GameClock::default();
+
+        assert_eq!(clock.darkness(), 2. / 3.);
+        clock.minutes = 12. * 60.;
+        assert_eq!(clock.tint()[3], 0.);
+        clock.minutes = 19.5 * 60.;
+        assert_eq!(clock.darkness(), 0.5);
+        clock.minutes = 23. * 60.;
+        assert_eq!(clock.tint()[3], NIGHT_DARKNESS);
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::resources::{Gids, History, Point, TileEdit, TileUpdate};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tool {
+    Paint,
+    Erase,
+    Fill,
+    Rect,
+}
+
+/// Tools and their names in the editor window.
+pub const TOOLS: [(Tool, &str); 4] = [(Tool::Paint, "Paint"), (Tool::Erase, "Erase"), (Tool::Fill, "Fill"), (Tool::Rect, "Rect")];
+
+/// What the map editor is doing.  Only does anything while `active` (the `MapEditor`
+/// state is on top).
+pub struct Editor {
+    pub active: bool,
+    pub tool: Tool,
+    pub layer: usize,
+    pub gid: u32,
+    pub anchor: Option<Point>, // corner the rect tool started at
+    pub save_requested: bool,
+    pub undo_requested: bool,
+    pub redo_requested: bool,
+    pub status: String,
+    pub history: History<(usize, TileEdit<u32>)>, // edits of every layer
+    pub palette: Option<usize>, // imgui texture of the tileset
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            active: false,
+            tool: Tool::Paint,
+            layer: 0,
+            gid: 1,
+            anchor: None,
+            save_requested: false,
+            undo_requested: false,
+            redo_requested: false,
+            status: String::new(),
+            history: History::default(),
+            palette: None,
+        }
+    }
+}
+
+impl Editor {
+    /// Gid a click puts down.  Erase always puts down nothing.
+    pub fn brush(&self) -> u32 {
+        if self.tool == Tool::Erase { 0 } else { self.gid }
+    }
+
+    /// Remember putting gid down on layer at points so it can be undone as one.  Returns
+    /// the updates for the points which actually change.  Points source does not have (off
+    /// the map or not loaded) are left alone.
+    pub fn record<G: Gids>(&mut self, source: &G, layer: usize, gid: u32, points: &[Point]) -> Vec<TileUpdate> {
+        let updates: Vec<TileUpdate> = points.iter()
+            .filter(|loc| source.gid_at(layer, loc).map_or(false, |before| before != gid))
+            .map(|loc| TileUpdate { loc: *loc, layer, gid })
+            .collect();
+
+        self.history.begin_group();
+        for update in &updates {
+            self.history.record((layer, TileEdit { loc: update.loc, before: source.gid_at(layer, &update.loc).unwrap(), after: gid }));
+        }
+        self.history.end_group();
+
+        updates
+    }
+
+    /// Updates which take back the last group of edits.
+    pub fn undo(&mut self) -> Vec<TileUpdate> {
+        match self.history.pop_undo() {
+            Some(edits) => {
+                let updates = edits.iter().rev().map(|(layer, edit)| TileUpdate { loc: edit.loc, layer: *layer, gid: edit.before }).collect();
+                self.history.undone(edits);
+                updates
+            },
+            None => vec![],
+        }
+    }
+
+    /// Updates which put back the last group of edits undone.
+    pub fn redo(&mut self) -> Vec<TileUpdate> {
+        match self.history.pop_redo() {
+            Some(edits) => {
+                let updates = edits.iter().map(|(layer, edit)| TileUpdate { loc: edit.loc, layer: *layer, gid: edit.after }).collect();
+                self.history.redone(edits);
+                updates
+            },
+            None => vec![],
+        }
+    }
+}
+
+/// Every tile in the rectangle with corners a and b (in any order).
+pub fn rect_points(a: &Point, b: &Point) -> Vec<Point> {
+    let (min_x, max_x) = (a.x.min(b.x), a.x.max(b.x));
+    let (min_y, max_y) = (a.y.min(b.y), a.y.max(b.y));
+
+    (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| Point::new(x, y))).collect()
+}
+
+/// Tiles reachable from start through their sides which have the same gid on layer as
+/// start does.  The fill stops wherever source has nothing (off the map or not loaded).
+pub fn fill_points<G: Gids>(source: &G, layer: usize, start: &Point) -> Vec<Point> {
+    let target = match source.gid_at(layer, start) {
+        Some(target) => target,
+        None => return vec![],
+    };
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    let mut points = vec![];
+
+    seen.insert(*start);
+    queue.push_back(*start);
+
+    while let Some(loc) = queue.pop_front() {
+        points.push(loc);
+
+        for (dx, dy) in &[(0, -1), (-1, 0), (1, 0), (0, 1)] {
+            let (x, y) = (loc.x as isize + dx, loc.y as isize + dy);
+            if x < 0 || y < 0 {
+                continue;
+            }
+
+            let next = Point::new(x as usize, y as usize);
+            if source.gid_at(layer, &next) == Some(target) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::editor::*;
+    use crate::resources::MapSource;
+
+    #[test]
+    fn test_rect_points() {
+        let points = rect_points(&Point::new(2, 3), &Point::new(1, 1));
+
+        assert_eq!(points.len(), 6);
+        assert_eq!(points[0], Point::new(1, 1));
+        assert_eq!(points[5], Point::new(2, 3));
+    }
+
+    #[test]
+    fn test_fill_points() {
+        // Fill from the left side stops at the wall of 2s and does not leak through the corner.
+        let source = MapSource {
+            width: 4,
+            height: 3,
+            layers: vec![vec![
+                1, 1, 2, 1,
+                1, 2, 1, 1,
+                1, 2, 1, 1,
+            ]],
+        };
+
+        assert_eq!(fill_points(&source, 0, &Point::new(0, 0)).len(), 4);
+        assert_eq!(fill_points(&source, 0, &Point::new(3, 0)).len(), 5);
+        assert_eq!(fill_points(&source, 0, &Point::new(2, 0)), vec![Point::new(2, 0)]);
+    }
+
+    #[test]
+    fn test_undo_any_layer() {
+        let mut source = MapSource { width: 2, height: 1, layers: vec![vec![1, 1], vec![0, 0], vec![0, 0]] };
+        let mut editor = Editor::default();
+        let apply = |source: &mut MapSource, updates: Vec<TileUpdate>| for update in updates {
+            source.set_gid(update.layer, &update.loc, update.gid);
+        };
+
+        let updates = editor.record(&source, 0, 2, &[Point::new(0, 0), Point::new(1, 0)]);
+        apply(&mut source, updates);
+        let updates = editor.record(&source, 2, 9, &[Point::new(1, 0)]);
+        apply(&mut source, updates);
+        assert!(editor.record(&source, 2, 9, &[Point::new(1, 0)]).is_empty()); // already there
+        assert_eq!(source.layers, vec![vec![2, 2], vec![0, 0], vec![0, 9]]);
+
+        let updates = editor.undo();
+        assert_eq!(updates, vec![TileUpdate { loc: Point::new(1, 0), layer: 2, gid: 0 }]);
+        apply(&mut source, updates);
+        let updates = editor.undo();
+        apply(&mut source, updates);
+        assert_eq!(source.layers, vec![vec![1, 1], vec![0, 0], vec![0, 0]]);
+        assert!(editor.undo().is_empty());
+
+        let updates = editor.redo();
+        apply(&mut source, updates);
+        assert_eq!(source.layers[0], vec![2, 2]);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::resources::Point;
+
+/// Ground tile a hoe turns plain ground into.
+pub const SOIL_GID: u32 = 21;
+/// Soil which has been watered today.
+pub const WET_SOIL_GID: u32 = 22;
+
+/// The tick farming runs on: crops grow and soil dries out once per `NewDay` on an
+/// `EventChannel<NewDay>`.  Whoever keeps time (the `ClockSystem`) publishes one for every
+/// day which starts, either from running past midnight or from sleeping.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewDay {
+    pub day: u32,
+    pub slept: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Plot {
+    pub watered: bool,
+}
+
+/// Every tilled tile of every map.  Map files know nothing about them so they get put
+/// on the ground layer when their map is first loaded.
+#[derive(Default)]
+pub struct Farm {
+    plots: HashMap<(String, Point), Plot>,
+}
+
+impl Farm {
+    /// Turn a tile into a plot.  Returns false if it already was one.
+    pub fn till(&mut self, map_id: &str, loc: &Point) -> bool {
+        let key = (map_id.to_string(), *loc);
+        if self.plots.contains_key(&key) {
+            return false;
+        }
+
+        self.plots.insert(key, Plot::default());
+        true
+    }
+
+    /// Water a plot for the rest of the day.  Returns false if there is no dry plot there.
+    pub fn water(&mut self, map_id: &str, loc: &Point) -> bool {
+        match self.plots.get_mut(&(map_id.to_string(), *loc)) {
+            Some(plot) if !plot.watered => {
+                plot.watered = true;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub fn plot(&self, map_id: &str, loc: &Point) -> Option<&Plot> {
+        self.plots.get(&(map_id.to_string(), *loc))
+    }
+
+    pub fn is_watered(&self, map_id: &str, loc: &Point) -> bool {
+        self.plot(map_id, loc).map_or(false, |plot| plot.watered)
+    }
+
+    /// Every plot with the map it is on.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Point, &Plot)> {
+        self.plots.iter().map(|((map_id, loc), plot)| (map_id.as_str(), loc, plot))
+    }
+
+    /// A new day dries out every plot.  Returns the ones which were wet.
+    pub fn dry_out(&mut self) -> Vec<(String, Point)> {
+        let mut dried = vec![];
+        for (key, plot) in self.plots.iter_mut().filter(|(_, plot)| plot.watered) {
+            plot.watered = false;
+            dried.push(key.clone());
+        }
+
+        dried
+    }
+
+    /// Ground gids of the plots on a map.
+    pub fn gids(&self, map_id: &str) -> Vec<(Point, u32)> {
+        self.plots.iter()
+            .filter(|((plot_map, _), _)| plot_map == map_id)
+            .map(|((_, loc), plot)| (*loc, if plot.watered { WET_SOIL_GID } else { SOIL_GID }))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::farm::*;
+
+    #[test]
+    fn test_farm() {
+        let mut farm = Farm::default();
+        let (loc, other) = (Point::new(1, 1), Point::new(2, 1));
+
+        assert!(!farm.water("farm", &loc)); // not tilled yet
+        assert!(farm.till("farm", &loc));
+        assert!(!farm.till("farm", &loc));
+        assert!(farm.till("farm", &other));
+        assert!(farm.water("farm", &loc));
+        assert!(!farm.water("farm", &loc));
+        assert!(!farm.is_watered("cave", &loc));
+
+        let mut gids = farm.gids("farm");
+        gids.sort_by_key(|(loc, _)| loc.x);
+        assert_eq!(gids, vec![(loc, WET_SOIL_GID), (other, SOIL_GID)]);
+        assert_eq!(farm.dry_out(), vec![("farm".to_string(), loc)]);
+        assert!(!farm.is_watered("farm", &loc));
+        assert!(farm.dry_out().is_empty());
+    }
+}
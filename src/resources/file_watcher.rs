@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Seconds between looking at the watched files.
+pub const WATCH_SECONDS: f32 = 1.0;
+
+/// Notices files changing on disk by checking when they were last modified.  Missing files
+/// can be watched too and count as changed once they show up.
+#[derive(Default)]
+pub struct FileWatcher {
+    files: HashMap<PathBuf, Option<SystemTime>>,
+    elapsed: f32,
+}
+
+impl FileWatcher {
+    /// Watch exactly these files.  Files already being watched keep their last seen time
+    /// so nothing is missed by calling this often.
+    pub fn watch_only(&mut self, paths: &[&Path]) {
+        self.files.retain(|path, _| paths.contains(&path.as_path()));
+
+        for path in paths {
+            if !self.files.contains_key(*path) {
+                self.files.insert(path.to_path_buf(), Self::modified(path));
+            }
+        }
+    }
+
+    /// Files which changed since they were last checked.
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+
+        for (path, seen) in self.files.iter_mut() {
+            let modified = Self::modified(path);
+            if modified != *seen {
+                *seen = modified;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+
+    /// Same as `changed` but only looks every WATCH_SECONDS.
+    pub fn poll(&mut self, delta_seconds: f32) -> Vec<PathBuf> {
+        self.elapsed += delta_seconds;
+        if self.elapsed < WATCH_SECONDS {
+            return vec![];
+        }
+
+        self.elapsed = 0.;
+        self.changed()
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::resources::file_watcher::*;
+
+    #[test]
+    fn test_changed() {
+        let path = std::env::temp_dir().join(format!("bountiful_watch_{}.tmx", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut watcher = FileWatcher::default();
+
+        watcher.watch_only(&[&path]);
+        assert!(watcher.changed().is_empty());
+        assert!(watcher.poll(WATCH_SECONDS / 2.).is_empty());
+
+        fs::write(&path, "<map/>").unwrap();
+        assert!(watcher.poll(WATCH_SECONDS / 4.).is_empty()); // not time to look yet
+        assert_eq!(watcher.poll(WATCH_SECONDS / 4.), vec![path.clone()]);
+        assert!(watcher.changed().is_empty());
+
+        watcher.watch_only(&[]);
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed().is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+/// Most undoable groups of edits kept.  Older ones are forgotten.
+pub const HISTORY_LIMIT: usize = 500;
+
+/// Undo and redo stacks of groups of edits.  Anything recorded while a group is open
+/// is undone together.  Groups can be nested and only the outermost one counts.
+pub struct History<E> {
+    undo: VecDeque<Vec<E>>,
+    redo: Vec<Vec<E>>,
+    open: Vec<E>,
+    depth: usize,
+}
+
+impl<E> Default for History<E> {
+    fn default() -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            open: vec![],
+            depth: 0,
+        }
+    }
+}
+
+impl<E> History<E> {
+    /// A new edit means anything undone can no longer be redone.
+    pub fn record(&mut self, edit: E) {
+        self.redo.clear();
+        self.open.push(edit);
+
+        if self.depth == 0 {
+            self.close();
+        }
+    }
+
+    pub fn begin_group(&mut self) {
+        self.depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+
+        if self.depth == 0 {
+            self.close();
+        }
+    }
+
+    fn close(&mut self) {
+        if self.open.is_empty() {
+            return;
+        }
+
+        let edits = std::mem::take(&mut self.open);
+        self.push_undo(edits);
+    }
+
+    fn push_undo(&mut self, edits: Vec<E>) {
+        self.undo.push_back(edits);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Edits to undo (in the order they were made).  Ends any open group first.
+    pub fn pop_undo(&mut self) -> Option<Vec<E>> {
+        self.depth = 0;
+        self.close();
+        self.undo.pop_back()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Vec<E>> {
+        self.redo.pop()
+    }
+
+    /// Put back a group which was just undone so it can be redone.
+    pub fn undone(&mut self, edits: Vec<E>) {
+        self.redo.push(edits);
+    }
+
+    /// Put back a group which was just redone so it can be undone again.
+    pub fn redone(&mut self, edits: Vec<E>) {
+        self.push_undo(edits);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.open.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
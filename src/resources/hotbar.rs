@@ -3,6 +3,9 @@ use amethyst::ecs::Entity;
 use amethyst::prelude::{World, WorldExt};
 use nalgebra::Vector3;
 
+use crate::bountiful::{TILE_HEIGHT, TILE_WIDTH};
+use crate::components::Inventory;
+
 #[derive(Default)]
 pub struct Hotbar {
     pub selected: Option<usize>,
@@ -15,6 +18,13 @@ pub struct HotbarSlot {
 }
 
 impl Hotbar {
+    /// Item in the selected slot which is what the player is holding.
+    pub fn in_hand(&self) -> Option<Entity> {
+        self.selected
+            .and_then(|selected| self.contents.get(selected))
+            .and_then(|slot| slot.contents)
+    }
+
     pub fn translation_of(&self, world: &World, slot: usize) -> Option<Vector3<f32>>{
         if let Some(hotbar_slot) = self.contents.get(slot) {
             let reader = world.read_component::<Transform>();
@@ -23,4 +33,156 @@ impl Hotbar {
             None
         }
     }
-}
\ No newline at end of file
+}
+
+/// Slots of the inventory past the hotbar.  They are only shown while the inventory is open.
+#[derive(Default)]
+pub struct InventoryScreen {
+    pub open: bool,
+    pub slots: Vec<HotbarSlot>,
+    pub dragged: Option<Entity>, // follows the pointer instead of its slot until dropped
+}
+
+/// Gui slot showing inventory slot `index`.  The hotbar shows the first row.
+pub fn slot_gui<'a>(hotbar: &'a mut Hotbar, screen: &'a mut InventoryScreen, index: usize) -> Option<&'a mut HotbarSlot> {
+    if index < hotbar.contents.len() {
+        hotbar.contents.get_mut(index)
+    } else {
+        screen.slots.get_mut(index - hotbar.contents.len())
+    }
+}
+
+/// Swap what two inventory slots show.
+pub fn swap_slots(hotbar: &mut Hotbar, screen: &mut InventoryScreen, a: usize, b: usize) {
+    let a_contents = slot_gui(hotbar, screen, a).map(|slot| slot.contents.take());
+    let b_contents = slot_gui(hotbar, screen, b).map(|slot| slot.contents.take());
+
+    if let (Some(a_contents), Some(b_contents)) = (a_contents, b_contents) {
+        slot_gui(hotbar, screen, a).unwrap().contents = b_contents;
+        slot_gui(hotbar, screen, b).unwrap().contents = a_contents;
+    }
+}
+
+/// Inventory slot whose gui holds item.
+pub fn slot_of(hotbar: &Hotbar, screen: &InventoryScreen, item: Entity) -> Option<usize> {
+    hotbar.contents.iter()
+        .chain(screen.slots.iter())
+        .position(|slot| slot.contents == Some(item))
+}
+
+/// Inventory slots which can be seen (and dropped onto) right now with their gui.
+pub fn visible_slots(hotbar: &Hotbar, screen: &InventoryScreen) -> Vec<(usize, Entity)> {
+    let shown = if screen.open { screen.slots.len() } else { 0 };
+
+    hotbar.contents.iter()
+        .chain(screen.slots.iter().take(shown))
+        .map(|slot| slot.hotbar_gui)
+        .enumerate()
+        .collect()
+}
+
+/// Where an item let go of at some translation (relative to the player like the slots) ends up.
+#[derive(Debug, PartialEq)]
+pub enum DropTarget {
+    Slot(usize),
+    Slots, // between or around slots which puts it back where it came from
+    Map,
+}
+
+pub fn drop_target(at: &Vector3<f32>, slots: &[(usize, Vector3<f32>)]) -> DropTarget {
+    let near = |slot: &Vector3<f32>, dx: f32, dy: f32| (at.x - slot.x).abs() <= dx && (at.y - slot.y).abs() <= dy;
+
+    if let Some((index, _)) = slots.iter().find(|(_, slot)| near(slot, TILE_WIDTH / 2., TILE_HEIGHT / 2.)) {
+        return DropTarget::Slot(*index);
+    }
+
+    let (mut low, mut high) = ((std::f32::MAX, std::f32::MAX), (std::f32::MIN, std::f32::MIN));
+    for (_, slot) in slots {
+        low = (low.0.min(slot.x), low.1.min(slot.y));
+        high = (high.0.max(slot.x), high.1.max(slot.y));
+    }
+    if at.x >= low.0 - TILE_WIDTH / 2. && at.x <= high.0 + TILE_WIDTH / 2. &&
+        at.y >= low.1 - TILE_HEIGHT / 2. && at.y <= high.1 + TILE_HEIGHT / 2. {
+        DropTarget::Slots
+    } else {
+        DropTarget::Map
+    }
+}
+
+/// Drop `item` dragged out of slot `from` onto slot `to`.  Different items swap places and
+/// the same item merges as much as fits.  A split drags `quantity` of the stack as a new
+/// entity which shows `to` when it was empty.  Returns whether item got a slot of its own
+/// and the item of a slot emptied by merging which the caller should delete.
+pub fn drop_on_slot(inventory: &mut Inventory, hotbar: &mut Hotbar, screen: &mut InventoryScreen, item: Entity,
+                    (from, to): (usize, usize), split: Option<u32>, max_stack: u32) -> (bool, Option<Entity>) {
+    let (from_id, to_id) = (inventory.get(from).map(|stack| stack.id.clone()), inventory.get(to).map(|stack| stack.id.clone()));
+
+    if from == to {
+        return (false, None);
+    }
+    if split.is_none() && from_id != to_id {
+        if inventory.swap(from, to) {
+            swap_slots(hotbar, screen, from, to);
+        }
+        return (false, None);
+    }
+
+    let (mut placed, mut emptied) = (false, None);
+    if inventory.transfer(from, to, split.unwrap_or(std::u32::MAX), max_stack) > 0 {
+        if to_id.is_none() {
+            slot_gui(hotbar, screen, to).unwrap().contents = Some(item);
+            placed = true;
+        }
+        if inventory.get(from).is_none() { // all of it merged
+            emptied = slot_gui(hotbar, screen, from).unwrap().contents.take();
+        }
+    }
+
+    (placed, emptied)
+}
+
+#[cfg(test)]
+mod tests {
+    use amethyst::prelude::{Builder, World, WorldExt};
+    use nalgebra::Vector3;
+
+    use crate::components::{Inventory, ItemStack};
+    use crate::resources::hotbar::*;
+
+    #[test]
+    fn test_whole_stack_drop() {
+        let mut world = World::new();
+        let mut entity = || world.create_entity().build();
+        let slots: Vec<HotbarSlot> = (0..3).map(|_| HotbarSlot { hotbar_gui: entity(), contents: None }).collect();
+        let (hoe, seeds) = (entity(), entity());
+        let mut hotbar = Hotbar { selected: None, contents: slots };
+        let mut screen = InventoryScreen::default();
+        let mut inventory = Inventory::new(3);
+        inventory.slots[0] = Some(ItemStack::new("hoe", 1));
+        inventory.slots[2] = Some(ItemStack::new("turnip_seeds", 4));
+        hotbar.contents[0].contents = Some(hoe);
+        hotbar.contents[2].contents = Some(seeds);
+        let at = |x: f32| Vector3::new(x, -100., 0.);
+        let guis: Vec<(usize, Vector3<f32>)> = (0..3).map(|index| (index, at(index as f32 * 1.5 * TILE_WIDTH))).collect();
+
+        // Let go of over the last slot while it follows the pointer instead of its own slot.
+        screen.dragged = Some(hoe);
+        assert_eq!(drop_target(&at(3. * TILE_WIDTH + 5.), &guis), DropTarget::Slot(2));
+        assert_eq!(drop_target(&at(0.75 * TILE_WIDTH), &guis), DropTarget::Slots);
+        assert_eq!(drop_target(&Vector3::new(0., 4. * TILE_HEIGHT, 0.), &guis), DropTarget::Map);
+        assert_eq!(drop_on_slot(&mut inventory, &mut hotbar, &mut screen, hoe, (0, 2), None, 1), (false, None));
+        assert_eq!(inventory.get(2), Some(&ItemStack::new("hoe", 1)));
+        assert_eq!((slot_of(&hotbar, &screen, hoe), slot_of(&hotbar, &screen, seeds)), (Some(2), Some(0)));
+
+        // Into an empty slot the whole stack moves along with its item.
+        assert_eq!(drop_on_slot(&mut inventory, &mut hotbar, &mut screen, seeds, (0, 1), None, 99), (false, None));
+        assert_eq!((inventory.get(0), slot_of(&hotbar, &screen, seeds)), (None, Some(1)));
+
+        // Onto the same item it merges and the emptied slot's item goes away.
+        inventory.slots[0] = Some(ItemStack::new("turnip_seeds", 2));
+        let more = entity();
+        hotbar.contents[0].contents = Some(more);
+        assert_eq!(drop_on_slot(&mut inventory, &mut hotbar, &mut screen, more, (0, 1), None, 99), (false, Some(more)));
+        assert_eq!((inventory.get(0), inventory.get(1)), (None, Some(&ItemStack::new("turnip_seeds", 6))));
+    }
+}
@@ -1,36 +1,222 @@
+use std::collections::{HashMap, HashSet};
 use amethyst::assets::Handle;
 use amethyst::renderer::SpriteSheet;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
 
-pub struct Items {
-    pub textures: Handle<SpriteSheet>,
-    pub items: Vec<Item>,
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum Category {
+    Tool,
+    Seed,
+    Crop,
+    Resource,
+    Misc,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum ToolKind {
+    PickAxe,
+    Hoe,
+    WateringCan,
+}
+
+/// How a tool gets used on the tile under the pointer.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ToolUse {
+    pub reach: usize, // tiles from the player
+    pub cooldown: f32, // seconds between uses
+    #[serde(default = "one")]
+    pub power: u32,
+    #[serde(default)]
+    pub swing: Vec<f32>, // degrees the tool is turned through while it is used
+}
+
+/// How a seed grows once it is planted.  Crops only grow on days their soil was watered.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Growth {
+    pub stages: Vec<usize>, // sprites from just planted to ready for harvest
+    pub days: u32, // watered days until it is ready
+    pub harvest: String, // item id
+    #[serde(default = "one")]
+    pub amount: u32,
 }
 
+impl Growth {
+    /// Stage a crop is at after growing for some days.
+    pub fn stage_after(&self, days: u32) -> usize {
+        let last = self.stages.len().saturating_sub(1);
+        if days >= self.days {
+            return last;
+        }
+
+        (days as usize * last / self.days as usize).min(last)
+    }
+}
+
+/// One kind of item from the catalogue (assets/items.ron).  Code refers to items by id
+/// since names are for people and may change.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct Item {
-    pub(crate) name: String,
-    pub(crate) texture_id: usize,
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub sprite: usize, // in texture/items.ron.  FIXME: Support for animated sprites
+    pub category: Category,
+    #[serde(default = "one")]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub tool: Option<ToolKind>,
+    #[serde(default)]
+    pub tool_use: Option<ToolUse>,
+    #[serde(default)]
+    pub growth: Option<Growth>, // seeds only
+    #[serde(default)]
+    pub value: u32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+pub struct Items {
+    pub textures: Handle<SpriteSheet>,
+    items: Vec<Item>,
+    by_id: HashMap<String, usize>,
 }
 
 impl Items {
-    pub fn new(textures: Handle<SpriteSheet>) -> Self {
+    pub fn new(textures: Handle<SpriteSheet>, catalogue: Vec<Item>) -> Self {
+        let by_id = catalogue.iter().enumerate().map(|(index, item)| (item.id.clone(), index)).collect();
+
         Self {
             textures,
-            items: vec![],
+            items: catalogue,
+            by_id,
         }
     }
 
-    // FIXME: Support for animated sprites
-    pub fn add(&mut self, name: String, texture_id: usize) {
-        // FIXME: Add sanity to make sure texture_id is not outside of range of textures.
-        self.items.push(Item::new(name, texture_id));
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.by_id.get(id).map(|index| &self.items[*index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter()
     }
 }
 
-impl Item {
-    pub fn new(name: String, texture_id: usize) -> Self {
-        Self {
-            name,
-            texture_id,
+/// Read a ron catalogue of items and make sure it makes sense.  sprite_count is how many
+/// sprites the items sprite sheet has.
+pub fn read_catalogue(catalogue: &str, sprite_count: usize) -> Result<Vec<Item>, String> {
+    let items: Vec<Item> = ron::de::from_str(catalogue).map_err(|e| e.to_string())?;
+    let mut ids = HashSet::new();
+
+    for item in &items {
+        if item.sprite >= sprite_count {
+            return Err(format!("{} uses sprite {} but there are only {}", item.id, item.sprite, sprite_count));
+        }
+        if item.max_stack == 0 {
+            return Err(format!("{} has a max_stack of 0", item.id));
+        }
+        if let Some(tool_use) = &item.tool_use {
+            if tool_use.reach == 0 || tool_use.cooldown <= 0. {
+                return Err(format!("{} needs a reach and a cooldown", item.id));
+            }
+        }
+        if let Some(growth) = &item.growth {
+            if growth.stages.is_empty() || growth.days == 0 {
+                return Err(format!("{} needs growth stages and days", item.id));
+            }
+            if let Some(sprite) = growth.stages.iter().find(|sprite| **sprite >= sprite_count) {
+                return Err(format!("{} grows through sprite {} but there are only {}", item.id, sprite, sprite_count));
+            }
         }
+        if !ids.insert(&item.id) {
+            return Err(format!("{} is in the catalogue twice", item.id));
+        }
+    }
+
+    for item in &items {
+        match &item.growth {
+            Some(growth) if !ids.contains(&growth.harvest) =>
+                return Err(format!("{} grows into {} which is not in the catalogue", item.id, growth.harvest)),
+            _ => (),
+        }
+    }
+
+    Ok(items)
+}
+
+// Just enough of amethyst's sprite sheet ron to count the sprites in it.
+#[derive(Deserialize)]
+enum SpriteSheetRon {
+    List(SpriteListRon),
+}
+
+#[derive(Deserialize)]
+struct SpriteListRon {
+    sprites: Vec<IgnoredAny>,
+}
+
+/// Number of sprites in a sprite sheet ron (only lists of sprites are understood).
+pub fn sprite_count(sheet: &str) -> Result<usize, String> {
+    match ron::de::from_str(sheet).map_err(|e| e.to_string())? {
+        SpriteSheetRon::List(list) => Ok(list.sprites.len()),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::resources::items::*;
+
+    const CATALOGUE: &str = r#"[
+        (id: "pick_axe", name: "Pick Axe", sprite: 0, category: Tool, tool: Some(PickAxe),
+         tool_use: Some((reach: 2, cooldown: 0.5, swing: [45., -45.]))),
+        (id: "stone", name: "Stone", sprite: 1, category: Resource, max_stack: 99, value: 2, tags: ["rock"]),
+        (id: "stone_seeds", name: "Stone Seeds", sprite: 1, category: Seed, max_stack: 99,
+         growth: Some((stages: [0, 1, 1], days: 4, harvest: "stone", amount: 2))),
+    ]"#;
+
+    #[test]
+    fn test_catalogue() {
+        let items = read_catalogue(CATALOGUE, 2).unwrap();
+        let (pick_axe, stone, seeds) = (&items[0], &items[1], &items[2]);
+
+        assert_eq!((pick_axe.tool, pick_axe.max_stack), (Some(ToolKind::PickAxe), 1));
+        assert_eq!((stone.max_stack, stone.value, stone.tags.len()), (99, 2, 1));
+        assert_eq!(stone.tool, None);
+        assert_eq!(pick_axe.tool_use.as_ref().map(|tool_use| (tool_use.reach, tool_use.power, tool_use.swing.len())), Some((2, 1, 2)));
+        assert_eq!(seeds.growth.as_ref().map(|growth| growth.amount), Some(2));
+    }
+
+    #[test]
+    fn test_stage_after() {
+        let growth = Growth { stages: vec![5, 6, 7], days: 4, harvest: "turnip".to_string(), amount: 1 };
+
+        assert_eq!(growth.stage_after(0), 0);
+        assert_eq!(growth.stage_after(1), 0);
+        assert_eq!(growth.stage_after(2), 1);
+        assert_eq!(growth.stage_after(4), 2);
+        assert_eq!(growth.stage_after(9), 2);
+    }
+
+    #[test]
+    fn test_bad_catalogue() {
+        assert!(read_catalogue(CATALOGUE, 1).is_err());
+        assert!(read_catalogue(r#"[(id: "a", name: "A", sprite: 0, category: Misc, max_stack: 0)]"#, 1).is_err());
+        assert!(read_catalogue(r#"[(id: "a", name: "A", sprite: 0, category: Misc), (id: "a", name: "B", sprite: 0, category: Misc)]"#, 1).is_err());
+        assert!(read_catalogue(r#"[(id: "a", name: "A", sprite: 0, category: Tool, tool_use: Some((reach: 0, cooldown: 1.)))]"#, 1).is_err());
+        assert!(read_catalogue(r#"[(id: "a", name: "A", sprite: 0, category: Seed, growth: Some((stages: [0], days: 1, harvest: "b")))]"#, 1).is_err());
+    }
+
+    // The real catalogue has to fit the real sprite sheet.
+    #[test]
+    fn test_assets() {
+        let count = sprite_count(&fs::read_to_string("assets/texture/items.ron").unwrap()).unwrap();
+
+        assert!(read_catalogue(&fs::read_to_string("assets/items.ron").unwrap(), count).is_ok());
+    }
+}
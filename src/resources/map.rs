@@ -1,15 +1,19 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
+use std::hash::Hash;
 use core::fmt;
 use nalgebra::Point2;
 use pathfinding::prelude::astar;
-use pathfinding::utils::absdiff;
+
+use crate::resources::history::History;
+use crate::resources::topology::{Square8, Topology, SQUARE_8};
 
 pub type Point = Point2<usize>;
 
 #[derive(Debug)]
 pub struct MyError {}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Tile {
     pub id: char,
     weight: usize,
@@ -22,59 +26,120 @@ impl Tile {
             weight,
         }
     }
+
+    /// What it costs to step onto this tile.
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+}
+
+/// Navigation tiles by location.  Either a whole `Map` or the loaded chunks of the current
+/// map (`ChunkedMap`) so auto tiling works on both.
+pub trait NavTiles {
+    /// None if loc is off the map (or in a chunk which is not loaded).
+    fn tile_at(&self, loc: &Point) -> Option<&Tile>;
 }
 
-pub struct Map {
+/// What a `set_at` changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileEdit<T> {
+    pub loc: Point,
+    pub before: T,
+    pub after: T,
+}
+
+/// A grid of anything.  Navigation uses `Tile` but other layers of a map (moisture, crops,
+/// lighting...) can use the same grid and coordinate math with their own payload.
+///
+/// Maps only have a handful of different tiles so each distinct tile is stored once in a
+/// palette and cells are u16 indices into it.  Tiles nothing uses any more are dropped from
+/// the palette once it fills up and a map which really has more than u16 can index
+/// switches to u32 indices.
+///
+/// Cells share palette entries so nothing hands out a `&mut T` into the map.  Instead of
+/// `at_mut`, `row_mut` and `iter_mut` use `modify`, `modify_row` and `modify_all` which change
+/// a copy in place and intern the result.  That interning is why changing a map needs
+/// `T: Clone + Eq + Hash`; reading one does not.
+pub struct Map<T = Tile> {
     width: usize,
     height: usize,
-    map: Vec<Tile>
+    cells: Cells,
+    palette: Vec<T>,
+    lookup: HashMap<T, usize>,
+    changes: Vec<Point>,
+    history: History<TileEdit<T>>,
+    topology: Box<dyn Topology>,
 }
 
-struct MapIterator<'a> {
-    map: &'a Map,
-    index: usize,
+/// Palette indices of every cell.  Narrow until a map has too many different tiles.
+enum Cells {
+    Narrow(Vec<u16>),
+    Wide(Vec<u32>),
 }
 
-impl<'a> MapIterator<'a> {
-    fn new(map: &'a Map) -> Self {
-        Self {
-            map,
-            index: 0,
+impl Cells {
+    fn len(&self) -> usize {
+        match self {
+            Cells::Narrow(ids) => ids.len(),
+            Cells::Wide(ids) => ids.len(),
         }
     }
-}
 
-impl<'a> Iterator for MapIterator<'a> {
-    type Item = (Point, Tile);
+    fn get(&self, index: usize) -> usize {
+        match self {
+            Cells::Narrow(ids) => ids[index] as usize,
+            Cells::Wide(ids) => ids[index] as usize,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.map.map.len() {
-            return None;
+    fn set(&mut self, index: usize, id: usize) {
+        match self {
+            Cells::Narrow(ids) => ids[index] = id as u16,
+            Cells::Wide(ids) => ids[index] = id as u32,
+        }
+    }
+
+    /// How many palette entries these indices can tell apart.
+    fn capacity(&self) -> usize {
+        match self {
+            Cells::Narrow(_) => 1 << 16,
+            Cells::Wide(_) => std::u32::MAX as usize,
         }
+    }
 
-        let point = self.map.point_for(self.index);
-        let tile = self.map.at(&point).unwrap();
-        self.index += 1;
+    fn index_bytes(&self) -> usize {
+        match self {
+            Cells::Narrow(_) => std::mem::size_of::<u16>(),
+            Cells::Wide(_) => std::mem::size_of::<u32>(),
+        }
+    }
 
-        // FIXME: It bugs me that I am having to clone immutable data here.
-        Some((point, tile.clone()))
+    fn widen(&mut self) {
+        if let Cells::Narrow(ids) = self {
+            *self = Cells::Wide(ids.iter().map(|id| *id as u32).collect());
+        }
     }
 }
 
-
 // FIXME: I had wanted loc to be reference but life time woes once I hit calling astar in shortest path.
-struct CoordIterator<'a> {
-    map: &'a Map,
+struct CoordIterator<'a, T> {
+    map: &'a Map<T>,
     loc: Point,
-    // Current index in POINTS
+    offsets: &'static [(isize, isize)],
+    // Current index in offsets
     index: usize,
 }
 
-impl<'a> CoordIterator<'a> {
-    fn new(map: &'a Map, loc: Point) -> Self {
+impl<'a, T> CoordIterator<'a, T> {
+    fn new(map: &'a Map<T>, loc: Point) -> Self {
+        Self::with_offsets(map, loc, map.topology.offsets(&loc))
+    }
+
+    fn with_offsets(map: &'a Map<T>, loc: Point, offsets: &'static [(isize, isize)]) -> Self {
         Self {
             map,
             loc,
+            offsets,
             index: 0,
         }
     }
@@ -92,23 +157,12 @@ impl<'a> CoordIterator<'a> {
     }
 }
 
-const POINTS: [(isize, isize); 8] = [
-    (-1, -1),  // upper left
-    (0, -1),   // up
-    (1, -1),   // upper right
-    (-1, 0),   // left
-    (1, 0),    // right
-    (-1, 1),   // lower left
-    (0, 1),    // down
-    (1, 1)     // lower right
-];
-
-impl<'a> Iterator for CoordIterator<'a> {
-    type Item = (Point, usize);
+impl<'a, T> Iterator for CoordIterator<'a, T> {
+    type Item = (Point, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while self.index < POINTS.len() {
-            let (dx, dy) = POINTS[self.index];
+        while self.index < self.offsets.len() {
+            let (dx, dy) = self.offsets[self.index];
             self.index += 1;
 
             // checked add to guarantee no negative values and at_xy still checks upper bounds of map.
@@ -116,7 +170,7 @@ impl<'a> Iterator for CoordIterator<'a> {
                 if let Some(ny) = Self::math_is_hard(self.loc.y, dy) {
                     let new_loc = Point::new(nx, ny);
                     if let Some(tile) = self.map.at(&new_loc) {
-                        return Some((new_loc, tile.weight))
+                        return Some((new_loc, tile))
                     }
                 }
             }
@@ -126,25 +180,268 @@ impl<'a> Iterator for CoordIterator<'a> {
     }
 }
 
-impl Map {
+impl Map<Tile> {
     pub fn new(width: usize, height: usize, default_char: char, default_weight: usize) -> Self {
-        Self {
+        Self::filled(width, height, Tile::new(default_char, default_weight))
+    }
+
+    pub fn shortest_path(&self, start: &Point, end: &Point) -> Option<(Vec<Point>, usize)> {
+        astar(start,
+              |i| self.adjacent_ats(*i).filter(|(_, tile)| tile.id == '.').map(|(i, tile)| (i, tile.weight)),
+              |i| self.distance(i, end),
+              |i| i == end)
+    }
+}
+
+impl NavTiles for Map<Tile> {
+    fn tile_at(&self, loc: &Point) -> Option<&Tile> {
+        self.at(loc)
+    }
+}
+
+impl<T: Clone + Eq + Hash> Map<T> {
+    pub fn filled(width: usize, height: usize, tile: T) -> Self {
+        let mut map = Self {
             width,
             height,
-            map: vec![Tile::new(default_char, default_weight); width * height],
+            cells: Cells::Narrow(vec![0; width * height]),
+            palette: vec![],
+            lookup: HashMap::new(),
+            changes: vec![],
+            history: History::default(),
+            topology: Box::new(Square8),
+        };
+
+        map.intern(tile);
+        map
+    }
+
+    fn intern(&mut self, tile: T) -> usize {
+        if let Some(id) = self.lookup.get(&tile) {
+            return *id;
+        }
+
+        // Once there are twice as many tiles as cells (or no more room) most of them are
+        // only in the history.  If dropping those does not free up half the room the map
+        // needs wider indices.
+        let limit = self.cells.capacity().min(2 * self.cells.len().max(1));
+        if self.palette.len() >= limit {
+            self.compact();
+            if self.palette.len() >= self.cells.capacity() / 2 {
+                self.cells.widen();
+            }
+        }
+
+        let id = self.palette.len();
+        self.palette.push(tile.clone());
+        self.lookup.insert(tile, id);
+        id
+    }
+
+    /// Drop palette entries no cell uses.  Undo does not need them since history keeps
+    /// its own copies of tiles.
+    fn compact(&mut self) {
+        let mut used = vec![false; self.palette.len()];
+        for index in 0..self.cells.len() {
+            used[self.cells.get(index)] = true;
+        }
+
+        let mut new_ids = vec![0; used.len()];
+        let palette = std::mem::take(&mut self.palette);
+        self.lookup.clear();
+        for (old_id, tile) in palette.into_iter().enumerate().filter(|(old_id, _)| used[*old_id]) {
+            new_ids[old_id] = self.palette.len();
+            self.lookup.insert(tile.clone(), self.palette.len());
+            self.palette.push(tile);
+        }
+
+        for index in 0..self.cells.len() {
+            let id = new_ids[self.cells.get(index)];
+            self.cells.set(index, id);
+        }
+    }
+
+    /// Edits which change something can be undone.
+    pub fn set_at(&mut self, loc: &Point, tile: T) -> Result<(), MyError>{
+        if let Some(index) = self.is_valid_loc(loc) {
+            self.record(index, &tile);
+            self.put(index, tile);
+            Ok(())
+        } else {
+            Err(MyError{})
+        }
+    }
+
+    fn record(&mut self, index: usize, tile: &T) {
+        if self.tile(index) != tile {
+            let before = self.tile(index).clone();
+            self.history.record(TileEdit { loc: self.point_for(index), before, after: tile.clone() });
+        }
+    }
+
+    // Bulk changes only report the tiles which really changed.
+    fn replace(&mut self, index: usize, tile: T) {
+        if *self.tile(index) != tile {
+            self.record(index, &tile);
+            self.put(index, tile);
+        }
+    }
+
+    fn put(&mut self, index: usize, tile: T) {
+        let id = self.intern(tile);
+        self.cells.set(index, id);
+        self.changes.push(self.point_for(index));
+    }
+
+    /// Undo the last edit (or group of edits).  Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop_undo() {
+            Some(edits) => {
+                for edit in edits.iter().rev() {
+                    self.put(self.at_xy_raw(&edit.loc), edit.before.clone());
+                }
+                self.history.undone(edits);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Redo the last undone edit (or group of edits).  Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.history.pop_redo() {
+            Some(edits) => {
+                for edit in edits.iter() {
+                    self.put(self.at_xy_raw(&edit.loc), edit.after.clone());
+                }
+                self.history.redone(edits);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Replace the tile at loc with whatever update makes from it.
+    pub fn update<F: FnOnce(&T) -> T>(&mut self, loc: &Point, update: F) -> Result<(), MyError> {
+        let tile = update(self.at(loc).ok_or(MyError{})?);
+
+        self.set_at(loc, tile)
+    }
+
+    /// Replace every tile with whatever update makes from it.  Tiles which change are
+    /// tracked like `set_at` and undone together.
+    pub fn update_all<F: FnMut(Point, &T) -> T>(&mut self, mut update: F) {
+        self.begin_group();
+        for index in 0..self.cells.len() {
+            let tile = update(self.point_for(index), self.tile(index));
+            self.replace(index, tile);
+        }
+        self.end_group();
+    }
+
+    /// Change a tile in place.  Tracked and undoable like `set_at`.
+    pub fn modify<F: FnOnce(&mut T)>(&mut self, loc: &Point, modify: F) -> Result<(), MyError> {
+        self.update(loc, |tile| {
+            let mut tile = tile.clone();
+            modify(&mut tile);
+            tile
+        })
+    }
+
+    /// Change every tile in row y in place.  Tracked and undone together like `update_all`.
+    pub fn modify_row<F: FnMut(Point, &mut T)>(&mut self, y: usize, mut modify: F) -> Result<(), MyError> {
+        if y >= self.height {
+            return Err(MyError{});
         }
+
+        self.begin_group();
+        for x in 0..self.width {
+            let index = y * self.width + x;
+            let mut tile = self.tile(index).clone();
+            modify(Point::new(x, y), &mut tile);
+            self.replace(index, tile);
+        }
+        self.end_group();
+
+        Ok(())
+    }
+
+    /// Change every tile in place.  Tracked and undone together like `update_all`.
+    pub fn modify_all<F: FnMut(Point, &mut T)>(&mut self, mut modify: F) {
+        self.update_all(|loc, tile| {
+            let mut tile = tile.clone();
+            modify(loc, &mut tile);
+            tile
+        });
+    }
+}
+
+impl<T> Map<T> {
+    /// Maps are square grids where corners touch unless told otherwise.
+    pub fn with_topology(mut self, topology: Box<dyn Topology>) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn topology(&self) -> &dyn Topology {
+        self.topology.as_ref()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
     }
 
     /// Note: Assumes all index accesses will get an index from a method which will prepare
     /// a safe index.
-    fn at(&self, loc: &Point) -> Option<&Tile> {
+    pub fn at(&self, loc: &Point) -> Option<&T> {
         if let Some(index) = self.is_valid_loc(loc) {
-            return Some(&self.map[index]);
+            return Some(self.tile(index));
         }
 
         None
     }
 
+    fn tile(&self, index: usize) -> &T {
+        &self.palette[self.cells.get(index)]
+    }
+
+    /// Everything set until the matching `end_group` is undone and redone together.
+    pub fn begin_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_group(&mut self) {
+        self.history.end_group();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Forget all edits.  For things like generating a map which nobody should undo.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Every distinct tile in the map.  Tiles which were replaced stay in it until the
+    /// palette fills up.
+    pub fn palette(&self) -> &[T] {
+        &self.palette
+    }
+
+    /// Bytes each cell takes.  The palette is shared so it is not counted.
+    pub fn cell_bytes(&self) -> usize {
+        self.cells.index_bytes()
+    }
+
     fn is_valid_loc(&self, loc: &Point) -> Option<usize> {
         if loc.x >= self.width || loc.y >= self.height {
             None
@@ -153,13 +450,34 @@ impl Map {
         }
     }
 
-    pub fn set_at(&mut self, loc: &Point, tile: Tile) -> Result<(), MyError>{
-        if let Some(index) = self.is_valid_loc(loc) {
-            self.map[index] = tile;
-            Ok(())
-        } else {
-            Err(MyError{})
+    /// Locations changed by `set_at` or `update` since the last time we asked.
+    pub fn take_changes(&mut self) -> Vec<Point> {
+        std::mem::take(&mut self.changes)
+    }
+
+    pub fn mark_all_changed(&mut self) {
+        self.changes = (0..self.cells.len()).map(|i| self.point_for(i)).collect();
+    }
+
+    /// Hand locs out with the next `take_changes` as if they had been set.
+    pub fn mark_changed<I: IntoIterator<Item = Point>>(&mut self, locs: I) {
+        self.changes.extend(locs);
+    }
+
+    /// Bitmask of which of the 8 squares around loc match whatever the topology is.  Bit n
+    /// is the nth entry in SQUARE_8 (upper left is bit 0, lower right is bit 7).  Neighbours
+    /// off the map never match.
+    pub fn neighbour_mask<F: Fn(&T) -> bool>(&self, loc: &Point, matches: F) -> u8 {
+        let mut neighbours = CoordIterator::with_offsets(self, *loc, &SQUARE_8);
+        let mut mask = 0;
+
+        while let Some((_, tile)) = neighbours.next() {
+            if matches(tile) {
+                mask |= 1 << (neighbours.index - 1);
+            }
         }
+
+        mask
     }
 
     fn at_xy_raw(&self, loc: &Point) -> usize {
@@ -173,29 +491,205 @@ impl Map {
     }
 
     // Assumes valid point
-    fn adjacent_ats<'a>(&'a self, loc: Point) -> impl Iterator<Item=(Point, usize)> + 'a {
+    pub fn adjacent_ats<'a>(&'a self, loc: Point) -> impl Iterator<Item=(Point, &'a T)> + 'a {
         CoordIterator::new(self, loc)
     }
 
-    fn distance(p1: &Point, p2: &Point) -> usize {
-        absdiff(p1.x, p2.x) + absdiff(p1.y, p2.y)
+    pub fn neighbours<'a>(&'a self, loc: &Point) -> impl Iterator<Item=Point> + 'a {
+        self.adjacent_ats(*loc).map(|(point, _)| point)
     }
 
-    pub fn iter<'a>(&'a self) -> impl Iterator<Item=(Point, Tile)> + 'a {
-        MapIterator::new(self)
+    pub fn distance(&self, p1: &Point, p2: &Point) -> usize {
+        self.topology.distance(p1, p2)
     }
 
-    pub fn shortest_path(&self, start: &Point, end: &Point) -> Option<(Vec<Point>, usize)> {
-        astar(&start,
-              |i| self.adjacent_ats(i.clone()).filter(|(i, _)| self.at(i).unwrap().id == '.'),
-              |i| Self::distance(i, end),
-              |i| i == end)
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=(Point, &'a T)> + 'a {
+        (0..self.cells.len()).map(move |i| (self.point_for(i), self.tile(i)))
+    }
+
+    pub fn row<'a>(&'a self, y: usize) -> Option<impl Iterator<Item=&'a T> + 'a> {
+        if y < self.height {
+            Some((y * self.width..(y + 1) * self.width).map(move |i| self.tile(i)))
+        } else {
+            None
+        }
+    }
+
+    /// Everything in the rectangle with its upper left at loc.  Parts off the map are skipped.
+    pub fn rect<'a>(&'a self, loc: &Point, width: usize, height: usize) -> impl Iterator<Item=(Point, &'a T)> + 'a {
+        let (left, right) = (loc.x.min(self.width), (loc.x + width).min(self.width));
+        let (top, bottom) = (loc.y.min(self.height), (loc.y + height).min(self.height));
+
+        (top..bottom).flat_map(move |y| {
+            (left..right).map(move |x| (Point::new(x, y), self.tile(y * self.width + x)))
+        })
+    }
+
+    /// Everything no more than radius columns and rows away from center (a square).
+    pub fn within_chebyshev<'a>(&'a self, center: &Point, radius: usize) -> impl Iterator<Item=(Point, &'a T)> + 'a {
+        let corner = Point::new(center.x.saturating_sub(radius), center.y.saturating_sub(radius));
+
+        self.rect(&corner, center.x + radius + 1 - corner.x, center.y + radius + 1 - corner.y)
+    }
+
+    /// Everything whose center is no further than radius from the center of center (a circle).
+    pub fn within_euclidean<'a>(&'a self, center: &Point, radius: f32) -> impl Iterator<Item=(Point, &'a T)> + 'a {
+        let (cx, cy) = (center.x as f32, center.y as f32);
+
+        self.within_chebyshev(center, radius.max(0.) as usize)
+            .filter(move |(loc, _)| (loc.x as f32 - cx).powi(2) + (loc.y as f32 - cy).powi(2) <= radius * radius)
+    }
+
+    /// Up to length tiles starting at start and stepping by direction until we fall off
+    /// the map.
+    pub fn ray<'a>(&'a self, start: &Point, (dx, dy): (isize, isize), length: usize) -> impl Iterator<Item=(Point, &'a T)> + 'a {
+        let length = if dx == 0 && dy == 0 { length.min(1) } else { length };
+        let mut next = Some(*start);
+
+        std::iter::from_fn(move || {
+            let loc = next?;
+            let tile = self.at(&loc)?;
+            next = CoordIterator::<T>::math_is_hard(loc.x, dx)
+                .and_then(|x| CoordIterator::<T>::math_is_hard(loc.y, dy).map(|y| Point::new(x, y)));
+            Some((loc, tile))
+        }).take(length)
+    }
+
+    /// Closest matching tile to origin no more than max_distance steps away.  Distance is
+    /// in steps of the map's topology and walls do not get in the way.
+    pub fn nearest<F: Fn(&T) -> bool>(&self, origin: &Point, max_distance: usize, matches: F) -> Option<(Point, &T)> {
+        self.nearest_where(origin, max_distance, |_, tile| matches(tile))
+    }
+
+    /// Same as `nearest` but matches can look at where the tile is too.
+    pub fn nearest_where<F: Fn(&Point, &T) -> bool>(&self, origin: &Point, max_distance: usize, matches: F) -> Option<(Point, &T)> {
+        let (origin, max_distance) = (*origin, max_distance);
+        self.nearest_reachable(&origin, std::usize::MAX, |loc, _| self.distance(&origin, loc) <= max_distance, matches)
+    }
+
+    /// Closest matching tile which can be walked to from origin in at most max_steps steps
+    /// only going through passable tiles.  Origin itself is always looked at.
+    pub fn nearest_reachable<P, F>(&self, origin: &Point, max_steps: usize, passable: P, matches: F) -> Option<(Point, &T)>
+        where P: Fn(&Point, &T) -> bool, F: Fn(&Point, &T) -> bool {
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        self.is_valid_loc(origin)?;
+        seen.insert(*origin);
+        queue.push_back((*origin, 0));
+
+        while let Some((loc, steps)) = queue.pop_front() {
+            let tile = self.at(&loc).unwrap();
+            if matches(&loc, tile) {
+                return Some((loc, tile));
+            }
+            if steps == max_steps {
+                continue;
+            }
+
+            for next in self.neighbours(&loc) {
+                if passable(&next, self.at(&next).unwrap()) && seen.insert(next) {
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Cells on the way from start to end (both included).  Each step goes to whichever
+    /// neighbour is closest to the straight line between their centers.
+    pub fn line(&self, start: &Point, end: &Point) -> Vec<Point> {
+        let steps = self.distance(start, end);
+        let ((sx, sy), (ex, ey)) = (self.topology.center(start), self.topology.center(end));
+        let mut line = vec![*start];
+        let mut current = *start;
+
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let (tx, ty) = (sx + (ex - sx) * t, sy + (ey - sy) * t);
+            let off_by = |loc: &Point| {
+                let (x, y) = self.topology.center(loc);
+                (x - tx).powi(2) + (y - ty).powi(2)
+            };
+
+            current = self.neighbours(&current)
+                .min_by(|a, b| off_by(a).partial_cmp(&off_by(b)).unwrap())
+                .unwrap_or(current);
+            line.push(current);
+        }
+
+        line
+    }
+
+    /// Cells within radius steps of origin which can be seen from it.  A cell which blocks
+    /// can be seen but hides everything behind it.
+    pub fn field_of_view<F: Fn(&T) -> bool>(&self, origin: &Point, radius: usize, blocks: F) -> HashSet<Point> {
+        let mut visible = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        seen.insert(*origin);
+        queue.push_back(*origin);
+
+        while let Some(loc) = queue.pop_front() {
+            let line = self.line(origin, &loc);
+            let clear = line[1..line.len().saturating_sub(1).max(1)].iter()
+                .all(|point| !blocks(self.at(point).unwrap()));
+            if clear {
+                visible.insert(loc);
+            }
+
+            for next in self.neighbours(&loc) {
+                if self.distance(origin, &next) <= radius && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        visible
+    }
+
+    /// Groups of matching cells which connect to each other, largest first.
+    pub fn regions<F: Fn(&T) -> bool>(&self, matches: F) -> Vec<Vec<Point>> {
+        // Only needs asking once per kind of tile.
+        let matching: Vec<bool> = self.palette.iter().map(matches).collect();
+        let matches = |index: usize| matching[self.cells.get(index)];
+        let mut labelled = vec![false; self.cells.len()];
+        let mut regions: Vec<Vec<Point>> = vec![];
+
+        for index in 0..self.cells.len() {
+            if labelled[index] || !matches(index) {
+                continue;
+            }
+
+            let mut region = vec![];
+            let mut queue = VecDeque::new();
+            labelled[index] = true;
+            queue.push_back(self.point_for(index));
+
+            while let Some(loc) = queue.pop_front() {
+                for next in self.neighbours(&loc) {
+                    let next_index = self.at_xy_raw(&next);
+                    if !labelled[next_index] && matches(next_index) {
+                        labelled[next_index] = true;
+                        queue.push_back(next);
+                    }
+                }
+                region.push(loc);
+            }
+
+            regions.push(region);
+        }
+
+        regions.sort_by(|a, b| b.len().cmp(&a.len()));
+        regions
     }
 }
 
-impl Display for Map {
+impl Display for Map<Tile> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let stream: Vec<char> = self.map.iter().map(|e| e.id).collect();
+        let stream: Vec<char> = self.iter().map(|(_, e)| e.id).collect();
         let split = &stream.chunks(self.width).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>();
         for line in split {
             let _ = writeln!(f, "{}", line);
@@ -207,6 +701,7 @@ impl Display for Map {
 #[cfg(test)]
 mod tests {
     use crate::resources::map::{Map, Point, Tile};
+    use crate::resources::topology::{HexOddR, Square4};
 
     pub fn generate_ascii_map(ascii_map: &str) -> Option<Map> {
         let rows: Vec<&str> = ascii_map.split_terminator('\n').collect();
@@ -228,7 +723,6 @@ mod tests {
 
         for (y, row) in rows.iter().enumerate() {
             for (x, tile) in row.chars().enumerate() {
-                // FIXME: All tiles will be immutable so share them all.
                 let tile = Tile::new(tile, 1);
                 let point = Point::new(x, y);
 
@@ -314,6 +808,97 @@ mod tests {
         assert_eq!(ats, vec![(3, 8), (4, 8), (3, 9)]);
     }
 
+    #[test]
+    fn test_topology_neighbours() {
+        let map = Map::new(5, 5, '.', 1).with_topology(Box::new(Square4));
+        let ats: Vec<Point> = map.neighbours(&Point::new(0, 0)).collect();
+        assert_eq!(ats, vec![Point::new(1, 0), Point::new(0, 1)]);
+
+        // Odd rows are shoved right so their upper neighbours are x and x + 1.
+        let map = Map::new(5, 5, '.', 1).with_topology(Box::new(HexOddR));
+        let mut ats: Vec<(usize, usize)> = map.neighbours(&Point::new(2, 1)).map(|p| (p.x, p.y)).collect();
+        ats.sort();
+        assert_eq!(ats, vec![(1, 1), (2, 0), (2, 2), (3, 0), (3, 1), (3, 2)]);
+    }
+
+    #[test]
+    fn test_hex_shortest_path() {
+        let map = generate_ascii_map(".....\n\
+                                      .###.\n\
+                                      .....").unwrap().with_topology(Box::new(HexOddR));
+
+        let (path, cost) = map.shortest_path(&Point::new(0, 1), &Point::new(4, 1)).unwrap();
+        assert_eq!(cost, 5);
+        for pair in path.windows(2) {
+            assert_eq!(map.distance(&pair[0], &pair[1]), 1);
+        }
+    }
+
+    #[test]
+    fn test_line() {
+        let map = Map::new(10, 10, '.', 1);
+        let line = map.line(&Point::new(0, 0), &Point::new(4, 2));
+        assert_eq!(line.len(), 5);
+        assert_eq!(line.first(), Some(&Point::new(0, 0)));
+        assert_eq!(line.last(), Some(&Point::new(4, 2)));
+
+        let map = Map::new(10, 10, '.', 1).with_topology(Box::new(HexOddR));
+        let line = map.line(&Point::new(1, 1), &Point::new(4, 3));
+        assert_eq!(line.len(), 5);
+        assert_eq!(line.last(), Some(&Point::new(4, 3)));
+    }
+
+    #[test]
+    fn test_field_of_view() {
+        let map = generate_ascii_map(".....\n\
+                                      ..#..\n\
+                                      .....").unwrap();
+
+        let visible = map.field_of_view(&Point::new(2, 0), 3, |tile| tile.id == '#');
+        assert!(visible.contains(&Point::new(2, 1))); // the wall itself
+        assert!(!visible.contains(&Point::new(2, 2))); // behind the wall
+        assert!(visible.contains(&Point::new(0, 2)));
+        assert!(visible.contains(&Point::new(4, 2)));
+    }
+
+    #[test]
+    fn test_regions() {
+        let map = generate_ascii_map("..#.\n\
+                                      ##..\n\
+                                      .#..").unwrap();
+
+        let sizes: Vec<usize> = map.regions(|tile| tile.id == '.').iter().map(|r| r.len()).collect();
+        assert_eq!(sizes, vec![7, 1]); // corners touch
+
+        let map = map.with_topology(Box::new(Square4));
+        let sizes: Vec<usize> = map.regions(|tile| tile.id == '.').iter().map(|r| r.len()).collect();
+        assert_eq!(sizes, vec![5, 2, 1]);
+    }
+
+    #[test]
+    fn test_neighbour_mask() {
+        let map = generate_ascii_map("#.#\n\
+                                      .x#\n\
+                                      ##.").unwrap();
+
+        assert_eq!(map.neighbour_mask(&Point::new(1, 1), |t| t.id == '#'), 0b0111_0101);
+        assert_eq!(map.neighbour_mask(&Point::new(0, 0), |t| t.id == '#'), 0b0000_0000);
+        assert_eq!(map.neighbour_mask(&Point::new(0, 0), |t| t.id == 'x'), 0b1000_0000);
+    }
+
+    #[test]
+    fn test_take_changes() {
+        let mut map = Map::new(3, 3, '.', 1);
+
+        map.set_at(&Point::new(1, 2), Tile::new('#', 1)).unwrap();
+        assert!(map.set_at(&Point::new(3, 3), Tile::new('#', 1)).is_err());
+        assert_eq!(map.take_changes(), vec![Point::new(1, 2)]);
+        assert!(map.take_changes().is_empty());
+
+        map.mark_changed(vec![Point::new(0, 0)]);
+        assert_eq!(map.take_changes(), vec![Point::new(0, 0)]);
+    }
+
     #[test]
     fn test_generate_ascii_map() {
         let map_string = "##############\n\
@@ -359,4 +944,169 @@ mod tests {
 
         assert_eq!(string, "123#.####");
     }
+
+    #[test]
+    fn test_views() {
+        let map = generate_ascii_map("123\n\
+                                      456\n\
+                                      789").unwrap();
+
+        let row: String = map.row(1).unwrap().map(|tile| tile.id).collect();
+        assert_eq!(row, "456");
+        assert!(map.row(3).is_none());
+
+        let rect: String = map.rect(&Point::new(1, 1), 5, 5).map(|(_, tile)| tile.id).collect();
+        assert_eq!(rect, "5689");
+        assert_eq!(map.rect(&Point::new(1, 1), 2, 2).last().map(|(loc, _)| loc), Some(Point::new(2, 2)));
+        assert_eq!(map.rect(&Point::new(4, 0), 2, 2).count(), 0);
+    }
+
+    #[test]
+    fn test_spatial_queries() {
+        let map = generate_ascii_map("#....\n\
+                                      .....\n\
+                                      ..x..\n\
+                                      .....\n\
+                                      ....#").unwrap();
+        let center = Point::new(2, 2);
+
+        assert_eq!(map.within_chebyshev(&center, 1).count(), 9);
+        assert_eq!(map.within_chebyshev(&center, 5).count(), 25);
+        assert_eq!(map.within_chebyshev(&Point::new(0, 0), 1).count(), 4);
+        assert_eq!(map.within_euclidean(&center, 1.).count(), 5);
+        assert_eq!(map.within_euclidean(&center, 2.).count(), 13);
+
+        let ray: Vec<Point> = map.ray(&center, (1, -1), 10).map(|(loc, _)| loc).collect();
+        assert_eq!(ray, vec![Point::new(2, 2), Point::new(3, 1), Point::new(4, 0)]);
+        assert_eq!(map.ray(&center, (-1, 0), 2).count(), 2);
+        assert_eq!(map.ray(&center, (0, 0), 10).count(), 1);
+
+        assert_eq!(map.nearest(&Point::new(1, 1), 5, |tile| tile.id == '#').map(|(loc, _)| loc), Some(Point::new(0, 0)));
+        assert_eq!(map.nearest(&Point::new(3, 3), 5, |tile| tile.id == '#').map(|(loc, _)| loc), Some(Point::new(4, 4)));
+        assert!(map.nearest(&center, 1, |tile| tile.id == '#').is_none());
+        assert!(map.nearest(&Point::new(9, 9), 5, |tile| tile.id == '#').is_none());
+        assert_eq!(map.nearest_where(&Point::new(1, 1), 5, |loc, tile| tile.id == '#' && loc.x > 0).map(|(loc, _)| loc),
+                   Some(Point::new(4, 4)));
+
+        // Walls are in the way of reaching things but can still be what is looked for.
+        let fenced = generate_ascii_map("..#..\n\
+                                         ..#..\n\
+                                         ###..\n\
+                                         .....").unwrap();
+        let open = |_: &Point, tile: &Tile| tile.id != '#';
+        let found = |max_steps, matches: &dyn Fn(&Point) -> bool| fenced.nearest_reachable(&Point::new(0, 0), max_steps, open, |loc, _| matches(loc))
+            .map(|(loc, _)| loc);
+        assert_eq!(found(10, &|loc| loc.y == 3), None);
+        assert_eq!(found(10, &|loc| loc.x == 1 && loc.y == 1), Some(Point::new(1, 1)));
+        assert_eq!(found(0, &|loc| *loc == Point::new(1, 1)), None);
+        assert_eq!(fenced.nearest_reachable(&Point::new(4, 3), 10, open, |loc, _| loc.x < 4 && loc.y == 0).map(|(loc, _)| loc), Some(Point::new(3, 0)));
+        assert_eq!(fenced.nearest_reachable(&Point::new(3, 0), 1, |_, _| true, |_, tile| tile.id == '#').map(|(loc, _)| loc), Some(Point::new(2, 0)));
+    }
+
+    #[test]
+    fn test_generic_payload() {
+        let mut moisture: Map<u8> = Map::filled(4, 3, 0);
+
+        moisture.update_all(|loc, _| loc.x as u8);
+        moisture.update(&Point::new(0, 0), |level| level + 9).unwrap();
+
+        assert_eq!(moisture.row(2).unwrap().cloned().collect::<Vec<u8>>(), vec![0, 1, 2, 3]);
+        assert_eq!(moisture.take_changes().len(), 3 * 3 + 1); // x == 0 was already 0
+        assert_eq!(moisture.iter().filter(|(_, level)| **level > 2).count(), 4);
+        assert_eq!(moisture.regions(|level| *level == 1).len(), 1);
+
+        moisture.modify_row(1, |_, level| *level += 5).unwrap();
+        moisture.modify_all(|loc, level| if loc.y == 2 { *level = 0 });
+        moisture.modify(&Point::new(3, 0), |level| *level = 7).unwrap();
+
+        assert_eq!(moisture.row(1).unwrap().cloned().collect::<Vec<u8>>(), vec![5, 6, 7, 8]);
+        assert_eq!(moisture.row(2).unwrap().cloned().collect::<Vec<u8>>(), vec![0, 0, 0, 0]);
+        assert_eq!(moisture.at(&Point::new(3, 0)), Some(&7));
+        assert_eq!(moisture.take_changes().len(), 4 + 3 + 1);
+        assert!(moisture.modify_row(3, |_, _| ()).is_err());
+
+        // Each bulk change is undone as one.
+        assert!(moisture.undo());
+        assert!(moisture.undo());
+        assert_eq!(moisture.row(2).unwrap().cloned().collect::<Vec<u8>>(), vec![0, 1, 2, 3]);
+        assert!(moisture.undo());
+        assert_eq!(moisture.row(1).unwrap().cloned().collect::<Vec<u8>>(), vec![0, 1, 2, 3]);
+        assert_eq!(moisture.at(&Point::new(3, 0)), Some(&3));
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut map = Map::new(3, 3, '.', 1);
+        let (a, b) = (Point::new(0, 0), Point::new(1, 1));
+
+        assert!(!map.undo());
+        map.set_at(&a, Tile::new('#', 1)).unwrap();
+        map.set_at(&a, Tile::new('#', 1)).unwrap(); // no change so nothing to undo
+        map.begin_group();
+        map.set_at(&b, Tile::new('#', 1)).unwrap();
+        map.set_at(&b, Tile::new('~', 1)).unwrap();
+        map.end_group();
+        map.take_changes();
+
+        assert!(map.undo());
+        assert_eq!(map.at(&b).unwrap().id, '.');
+        assert_eq!(map.at(&a).unwrap().id, '#');
+        assert_eq!(map.take_changes(), vec![b, b]);
+
+        assert!(map.undo());
+        assert_eq!(map.at(&a).unwrap().id, '.');
+        assert!(!map.can_undo());
+
+        assert!(map.redo());
+        assert!(map.redo());
+        assert_eq!(map.at(&b).unwrap().id, '~');
+        assert!(!map.redo());
+
+        // A fresh edit throws away whatever could have been redone.
+        map.undo();
+        map.set_at(&a, Tile::new('x', 1)).unwrap();
+        assert!(!map.can_redo());
+    }
+
+    #[test]
+    fn test_palette() {
+        let mut map = Map::new(100, 100, '.', 1);
+
+        for x in 0..100 {
+            map.set_at(&Point::new(x, 50), Tile::new('#', 1)).unwrap();
+        }
+        map.set_at(&Point::new(3, 3), Tile::new('.', 1)).unwrap();
+
+        assert_eq!(map.palette().len(), 2);
+        assert_eq!(map.at(&Point::new(7, 50)), Some(&Tile::new('#', 1)));
+        assert_eq!(map.at(&Point::new(3, 3)), Some(&Tile::new('.', 1)));
+    }
+
+    #[test]
+    fn test_palette_compacts() {
+        // Far more different values over time than a u16 can index but only a few at once.
+        let mut lighting: Map<u32> = Map::filled(4, 4, 0);
+        for level in 0..100_000 {
+            lighting.set_at(&Point::new(1, 1), level).unwrap();
+        }
+
+        assert!(lighting.palette().len() <= 2 * 16);
+        assert_eq!(lighting.at(&Point::new(1, 1)), Some(&99_999));
+        assert_eq!(lighting.at(&Point::new(0, 0)), Some(&0));
+        assert!(lighting.undo());
+        assert_eq!(lighting.at(&Point::new(1, 1)), Some(&99_998));
+    }
+
+    #[test]
+    fn test_palette_widens() {
+        // Every cell different really needs more than a u16.
+        let mut owners: Map<u32> = Map::filled(300, 300, 0);
+        assert_eq!(owners.cell_bytes(), 2);
+        owners.update_all(|loc, _| (loc.y * 300 + loc.x) as u32);
+        assert_eq!(owners.cell_bytes(), 4);
+
+        assert_eq!(owners.palette().len(), 300 * 300);
+        assert_eq!(owners.at(&Point::new(299, 299)), Some(&(300 * 300 - 1)));
+        assert_eq!(owners.at(&Point::new(7, 1)), Some(&307));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use amethyst::ecs::Entity;
+use nalgebra::Vector3;
+
+use crate::resources::{ChunkedMap, Point};
+
+/// Seconds it takes to fade out (and then back in) when warping between maps.
+pub const FADE_SECONDS: f32 = 0.5;
+
+/// Moves the player to the named entry point of a map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warp {
+    pub map_id: String,
+    pub entry: String,
+}
+
+/// A tile of a map changed (or went back to what it was with an undo).  Published on an
+/// `EventChannel<MapChange>` for anything which draws or collides with tiles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapChange {
+    pub map_id: String,
+    pub loc: Point,
+}
+
+/// How tiles are laid out on screen.  Isometric is tiled's diamond layout where x runs
+/// down to the right and y runs down to the left.  Either way the tiles themselves (and
+/// `Map`) are still a plain grid so neighbours of a tile do not change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    Orthogonal,
+    Isometric,
+}
+
+/// Everything about a loaded map which is not an entity.  Points use the same layout as
+/// tiled and `Map` (0,0 is upper left) where world coordinates have 0,0 at the lower left.
+pub struct MapInfo {
+    pub id: String,
+    pub width: usize,
+    pub height: usize,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub orientation: Orientation,
+    pub sprite_offset: f32, // how far up sprites taller than a tile are drawn
+    pub entries: HashMap<String, Point>,
+    pub warps: HashMap<Point, Warp>,
+}
+
+impl MapInfo {
+    /// Height of the whole map in world coordinates.
+    pub fn pixel_height(&self) -> f32 {
+        match self.orientation {
+            Orientation::Orthogonal => self.height as f32 * self.tile_height,
+            Orientation::Isometric => (self.width + self.height) as f32 * self.tile_height / 2.,
+        }
+    }
+
+    /// World coordinates of the center of a tile.
+    pub fn world_of(&self, loc: &Point, z: f32) -> Vector3<f32> {
+        match self.orientation {
+            Orientation::Orthogonal =>
+                Vector3::new(loc.x as f32 * self.tile_width + self.tile_width / 2.,
+                             (self.height - 1 - loc.y) as f32 * self.tile_height + self.tile_height / 2.,
+                             z),
+            Orientation::Isometric => {
+                let (half_width, half_height) = (self.tile_width / 2., self.tile_height / 2.);
+                // Top corner of the 0,0 diamond is height tiles in from the left.
+                Vector3::new((self.height + loc.x - loc.y) as f32 * half_width,
+                             self.pixel_height() - (loc.x + loc.y + 1) as f32 * half_height,
+                             z)
+            },
+        }
+    }
+
+    /// Tile which contains the world coordinates x, y.
+    pub fn point_of(&self, x: f32, y: f32) -> Option<Point> {
+        let (i, j) = match self.orientation {
+            Orientation::Orthogonal =>
+                (x / self.tile_width, self.height as f32 - y / self.tile_height),
+            Orientation::Isometric => {
+                let across = x / (self.tile_width / 2.) - self.height as f32;
+                let down = (self.pixel_height() - y) / (self.tile_height / 2.);
+                ((down + across) / 2., (down - across) / 2.)
+            },
+        };
+
+        if i < 0. || j < 0. || i as usize >= self.width || j as usize >= self.height {
+            None
+        } else {
+            Some(Point::new(i as usize, j as usize))
+        }
+    }
+
+    /// Size of a collision box which fits inside a tile.
+    pub fn tile_bound(&self) -> (f32, f32) {
+        match self.orientation {
+            Orientation::Orthogonal => (self.tile_width, self.tile_height),
+            Orientation::Isometric => (self.tile_width / 2., self.tile_height / 2.),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Transition {
+    None,
+    FadeOut,
+    Swap, // Screen is black and we are waiting on the state to load the next map.
+    FadeIn,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Transition::None
+    }
+}
+
+/// Keeps track of which map is loaded.  Its tiles are in a `ChunkedMap` which only has the
+/// chunks `WorldChunks` streamed in.  Everything else about it (and every other map we have
+/// visited) waits in the chunk store.
+#[derive(Default)]
+pub struct MapManager {
+    pub current: Option<String>,
+    pub transition: Transition,
+    pub pending: Option<Warp>,
+    pub reload: bool, // the map file changed so the `Bountiful` state should read it again
+    pub fade: f32,
+    pub fade_overlay: Option<Entity>,
+    info: Option<MapInfo>,
+    map: Option<ChunkedMap>,
+    loads: usize,
+}
+
+impl MapManager {
+    /// Make a freshly read map the current one.  Its chunks get streamed in later.
+    pub fn add(&mut self, info: MapInfo) {
+        self.loads += 1;
+        self.current = Some(info.id.clone());
+        self.map = Some(ChunkedMap::new(info.width, info.height));
+        self.info = Some(info);
+    }
+
+    /// Goes up with every `add` so anything built from the current map knows when to
+    /// build it again (even when the same map is loaded again).
+    pub fn loads(&self) -> usize {
+        self.loads
+    }
+
+    pub fn info(&self) -> Option<&MapInfo> {
+        self.info.as_ref().filter(|info| self.current.as_ref() == Some(&info.id))
+    }
+
+    /// The loaded chunks of the current map.
+    pub fn map(&self) -> Option<&ChunkedMap> {
+        self.map.as_ref().filter(|_| self.info().is_some())
+    }
+
+    pub fn map_mut(&mut self) -> Option<&mut ChunkedMap> {
+        if self.info().is_some() { self.map.as_mut() } else { None }
+    }
+
+    /// Info and loaded chunks of the current map at once so chunks can change while the
+    /// info is in use.
+    pub fn current_mut(&mut self) -> Option<(&MapInfo, &mut ChunkedMap)> {
+        let (info, map) = (self.info.as_ref()?, self.map.as_mut()?);
+
+        if self.current.as_ref() == Some(&info.id) { Some((info, map)) } else { None }
+    }
+
+    pub fn warp_at(&self, loc: &Point) -> Option<&Warp> {
+        self.info().and_then(|info| info.warps.get(loc))
+    }
+
+    pub fn start_warp(&mut self, warp: Warp) {
+        self.pending = Some(warp);
+        self.transition = Transition::FadeOut;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::resources::{MapInfo, MapManager, Orientation, Point};
+
+    fn info(orientation: Orientation, tile_height: f32) -> MapInfo {
+        MapInfo {
+            id: "test".to_string(),
+            width: 5,
+            height: 10,
+            tile_width: 64.,
+            tile_height,
+            orientation,
+            sprite_offset: 0.,
+            entries: HashMap::new(),
+            warps: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_world_of_and_point_of() {
+        let info = info(Orientation::Orthogonal, 64.);
+
+        // Upper left tile is at the top of the world.
+        let world = info.world_of(&Point::new(0, 0), 0.);
+        assert_eq!((world.x, world.y), (32., 608.));
+        assert_eq!(info.point_of(world.x, world.y), Some(Point::new(0, 0)));
+        assert_eq!(info.point_of(70., 10.), Some(Point::new(1, 9)));
+        assert_eq!(info.point_of(-1., 10.), None);
+        assert_eq!(info.point_of(320., 10.), None);
+    }
+
+    #[test]
+    fn test_isometric_world_of_and_point_of() {
+        let info = info(Orientation::Isometric, 32.);
+
+        // 0,0 is the top diamond.
+        let world = info.world_of(&Point::new(0, 0), 0.);
+        assert_eq!((world.x, world.y), (320., 224.));
+        assert_eq!(info.pixel_height(), 240.);
+
+        for loc in &[Point::new(0, 0), Point::new(4, 0), Point::new(0, 9), Point::new(4, 9), Point::new(2, 3)] {
+            let world = info.world_of(loc, 0.);
+            assert_eq!(info.point_of(world.x, world.y), Some(*loc));
+            // Just inside the left and right corners too.
+            assert_eq!(info.point_of(world.x - 31., world.y), Some(*loc));
+            assert_eq!(info.point_of(world.x + 31., world.y), Some(*loc));
+        }
+
+        assert_eq!(info.point_of(10., 230.), None); // left of the top diamond
+        assert_eq!(info.point_of(320., 241.), None);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut manager = MapManager::default();
+        assert!(manager.map().is_none());
+
+        manager.add(MapInfo { id: "cave:0".to_string(), ..info(Orientation::Orthogonal, 64.) });
+        manager.map_mut().unwrap().mark_changed(vec![Point::new(1, 1)]);
+        assert_eq!(manager.info().map(|info| info.id.as_str()), Some("cave:0"));
+        assert_eq!(manager.map().map(|map| (map.width(), map.height())), Some((5, 10)));
+        assert_eq!(manager.loads(), 1);
+
+        // Nothing carries over from the last time a map was added, not even the same one.
+        manager.add(MapInfo { id: "cave:0".to_string(), ..info(Orientation::Orthogonal, 64.) });
+        assert!(manager.map_mut().unwrap().take_changes().is_empty());
+        assert!(manager.map().unwrap().coords().is_empty());
+        assert_eq!(manager.loads(), 2);
+
+        manager.current = Some("cave:1".to_string());
+        assert!(manager.info().is_none());
+        assert!(manager.map().is_none());
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::resources::Point;
+
+/// A solid which breaks after enough hits from a pick axe.  Set up with "hits" and "drop"
+/// properties on its tile in the tileset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mineable {
+    pub hits: u32,
+    pub drop: Option<String>, // item id
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Hit {
+    Unbreakable,
+    Damaged(u32), // hits left
+    Broken(Option<String>), // what it drops
+}
+
+/// Which solids can be mined, how much damage each has taken so far and where solids
+/// have been mined away.  Map files still have those so loading a map takes them out again.
+#[derive(Default)]
+pub struct Mining {
+    pub mineables: HashMap<u32, Mineable>, // by tile id (gid - 1)
+    pub mined: HashSet<(String, Point)>,
+    damage: HashMap<(String, Point), u32>,
+}
+
+impl Mining {
+    pub fn mineable(&self, gid: u32) -> Option<&Mineable> {
+        if gid == 0 {
+            return None;
+        }
+
+        self.mineables.get(&(gid - 1))
+    }
+
+    pub fn mined_on(&self, map_id: &str) -> Vec<Point> {
+        self.mined.iter().filter(|(mined_map, _)| mined_map == map_id).map(|(_, loc)| *loc).collect()
+    }
+
+    /// Hit the solid gid at loc on map_id.  Damage is by location since auto tiling changes
+    /// the gid of a fence as its neighbours change.
+    pub fn hit(&mut self, map_id: &str, loc: &Point, gid: u32, power: u32) -> Hit {
+        let mineable = match self.mineable(gid) {
+            Some(mineable) => mineable.clone(),
+            None => return Hit::Unbreakable,
+        };
+        let key = (map_id.to_string(), *loc);
+        let damage = self.damage.get(&key).cloned().unwrap_or(0) + power;
+
+        if damage >= mineable.hits {
+            self.damage.remove(&key);
+            self.mined.insert(key);
+            Hit::Broken(mineable.drop)
+        } else {
+            self.damage.insert(key, damage);
+            Hit::Damaged(mineable.hits - damage)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::mining::*;
+
+    #[test]
+    fn test_hit() {
+        let mut mining = Mining::default();
+        for id in 4..6 {
+            mining.mineables.insert(id, Mineable { hits: 3, drop: Some("wood".to_string()) });
+        }
+        let (loc, other) = (Point::new(1, 1), Point::new(2, 1));
+
+        assert_eq!(mining.hit("farm", &loc, 1, 1), Hit::Unbreakable);
+        assert_eq!(mining.hit("farm", &loc, 0, 1), Hit::Unbreakable);
+        assert_eq!(mining.hit("farm", &loc, 5, 1), Hit::Damaged(2));
+        assert_eq!(mining.hit("farm", &other, 5, 1), Hit::Damaged(2));
+        assert_eq!(mining.hit("cave", &loc, 5, 1), Hit::Damaged(2));
+        assert_eq!(mining.hit("farm", &loc, 6, 2), Hit::Broken(Some("wood".to_string())));
+        assert_eq!(mining.hit("farm", &loc, 5, 1), Hit::Damaged(2)); // a new fence starts over
+        assert_eq!(mining.mined_on("farm"), vec![loc]);
+        assert!(mining.mined_on("cave").is_empty());
+    }
+}
@@ -1,7 +1,29 @@
 pub(crate) mod map;
+pub mod autotile;
+pub mod chunked_map;
+pub mod chunks;
+pub mod clock;
+pub mod editor;
+pub mod farm;
+pub mod file_watcher;
+pub mod history;
 pub mod hotbar;
 pub mod items;
+pub mod map_manager;
+pub mod mining;
+pub mod topology;
 
-pub use self::hotbar::{HotbarSlot, Hotbar};
-pub use self::items::{Item, Items};
-pub use self::map::{Map, Point, Tile};
\ No newline at end of file
+pub use self::autotile::{AutoTiler, Terrain, TileRules, TileUpdate, FENCE_SPRITES};
+pub use self::chunked_map::ChunkedMap;
+pub use self::chunks::{chunk_dimensions, ChunkData, ChunkTile, Gids, MapChunk, MapSource, PersistedEntity, WorldChunks, CHUNK_SIZE, EVICT_RADIUS, LOAD_RADIUS};
+pub use self::clock::{GameClock, Lighting, Season, Weekday, DAYS_PER_SEASON, MINUTES_PER_DAY, MINUTES_PER_SECOND, MORNING};
+pub use self::editor::{Editor, Tool, TOOLS};
+pub use self::farm::{Farm, NewDay, Plot, SOIL_GID, WET_SOIL_GID};
+pub use self::file_watcher::{FileWatcher, WATCH_SECONDS};
+pub use self::history::{History, HISTORY_LIMIT};
+pub use self::hotbar::{drop_on_slot, drop_target, slot_gui, slot_of, swap_slots, visible_slots, DropTarget, HotbarSlot, Hotbar, InventoryScreen};
+pub use self::items::{Category, Growth, Item, Items, ToolKind, ToolUse};
+pub use self::map::{Map, NavTiles, Point, Tile, TileEdit};
+pub use self::map_manager::{MapChange, MapInfo, MapManager, Orientation, Transition, Warp, FADE_SECONDS};
+pub use self::mining::{Hit, Mineable, Mining};
+pub use self::topology::{HexEvenQ, HexOddR, Square4, Square8, Topology};
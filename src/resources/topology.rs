@@ -0,0 +1,162 @@
+use pathfinding::utils::absdiff;
+
+use crate::resources::Point;
+
+/// The eight squares around a square (upper left first, lower right last).
+pub const SQUARE_8: [(isize, isize); 8] = [
+    (-1, -1),  // upper left
+    (0, -1),   // up
+    (1, -1),   // upper right
+    (-1, 0),   // left
+    (1, 0),    // right
+    (-1, 1),   // lower left
+    (0, 1),    // down
+    (1, 1)     // lower right
+];
+
+const SQUARE_4: [(isize, isize); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+// Hex neighbours depend on whether the row (or column) was shoved over.  Both start
+// with the neighbour to the east (or south east) and go counter clockwise.
+const ODD_R_EVEN_ROWS: [(isize, isize); 6] = [(1, 0), (0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1)];
+const ODD_R_ODD_ROWS: [(isize, isize); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (0, 1), (1, 1)];
+const EVEN_Q_EVEN_COLUMNS: [(isize, isize); 6] = [(1, 1), (1, 0), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+const EVEN_Q_ODD_COLUMNS: [(isize, isize); 6] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (0, 1)];
+
+const HEX_SPACING: f32 = 0.866_025_4; // sqrt(3) / 2
+
+/// How the cells of a `Map` connect to each other.  Points are always stored as a
+/// rectangle of columns and rows.  Topologies only change who is next to who.
+pub trait Topology: Send + Sync {
+    /// Offsets from loc to each of its neighbours.  Some may be off the map.
+    fn offsets(&self, loc: &Point) -> &'static [(isize, isize)];
+
+    /// Fewest steps it takes to get from a to b ignoring anything in the way.
+    fn distance(&self, a: &Point, b: &Point) -> usize;
+
+    /// Where the center of loc is if neighbouring centers are 1 apart.  Used for drawing
+    /// lines between cells.
+    fn center(&self, loc: &Point) -> (f32, f32) {
+        (loc.x as f32, loc.y as f32)
+    }
+}
+
+// Cube coordinates make hex distances easy.  Axial (q, r) is all we need since s = -q - r.
+fn hex_distance((aq, ar): (isize, isize), (bq, br): (isize, isize)) -> usize {
+    let (dq, dr) = (aq - bq, ar - br);
+
+    ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as usize
+}
+
+/// Squares which only connect through their sides.
+pub struct Square4;
+
+impl Topology for Square4 {
+    fn offsets(&self, _loc: &Point) -> &'static [(isize, isize)] {
+        &SQUARE_4
+    }
+
+    fn distance(&self, a: &Point, b: &Point) -> usize {
+        absdiff(a.x, b.x) + absdiff(a.y, b.y)
+    }
+}
+
+/// Squares which also connect through their corners.
+pub struct Square8;
+
+impl Topology for Square8 {
+    fn offsets(&self, _loc: &Point) -> &'static [(isize, isize)] {
+        &SQUARE_8
+    }
+
+    fn distance(&self, a: &Point, b: &Point) -> usize {
+        absdiff(a.x, b.x).max(absdiff(a.y, b.y))
+    }
+}
+
+/// Pointy topped hexes where every odd row is shoved half a hex to the right.
+pub struct HexOddR;
+
+impl HexOddR {
+    fn axial(loc: &Point) -> (isize, isize) {
+        let (x, y) = (loc.x as isize, loc.y as isize);
+
+        (x - (y - (y & 1)) / 2, y)
+    }
+}
+
+impl Topology for HexOddR {
+    fn offsets(&self, loc: &Point) -> &'static [(isize, isize)] {
+        if loc.y % 2 == 0 { &ODD_R_EVEN_ROWS } else { &ODD_R_ODD_ROWS }
+    }
+
+    fn distance(&self, a: &Point, b: &Point) -> usize {
+        hex_distance(Self::axial(a), Self::axial(b))
+    }
+
+    fn center(&self, loc: &Point) -> (f32, f32) {
+        (loc.x as f32 + (loc.y % 2) as f32 * 0.5, loc.y as f32 * HEX_SPACING)
+    }
+}
+
+/// Flat topped hexes where every even column is shoved half a hex down.
+pub struct HexEvenQ;
+
+impl HexEvenQ {
+    fn axial(loc: &Point) -> (isize, isize) {
+        let (x, y) = (loc.x as isize, loc.y as isize);
+
+        (x, y - (x + (x & 1)) / 2)
+    }
+}
+
+impl Topology for HexEvenQ {
+    fn offsets(&self, loc: &Point) -> &'static [(isize, isize)] {
+        if loc.x % 2 == 0 { &EVEN_Q_EVEN_COLUMNS } else { &EVEN_Q_ODD_COLUMNS }
+    }
+
+    fn distance(&self, a: &Point, b: &Point) -> usize {
+        hex_distance(Self::axial(a), Self::axial(b))
+    }
+
+    fn center(&self, loc: &Point) -> (f32, f32) {
+        (loc.x as f32 * HEX_SPACING, loc.y as f32 + ((loc.x + 1) % 2) as f32 * 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::resources::topology::*;
+    use crate::resources::Point;
+
+    // Every neighbour should be one step away and one unit apart.
+    fn check_neighbours(topology: &dyn Topology, loc: Point) {
+        let (cx, cy) = topology.center(&loc);
+
+        for (dx, dy) in topology.offsets(&loc) {
+            let next = Point::new((loc.x as isize + dx) as usize, (loc.y as isize + dy) as usize);
+            let (nx, ny) = topology.center(&next);
+            assert_eq!(topology.distance(&loc, &next), 1, "{:?} -> {:?}", loc, next);
+            assert!((((nx - cx).powi(2) + (ny - cy).powi(2)).sqrt() - 1.).abs() < 0.001, "{:?} -> {:?}", loc, next);
+        }
+    }
+
+    #[test]
+    fn test_hex_neighbours() {
+        for loc in &[Point::new(4, 4), Point::new(4, 5), Point::new(5, 4), Point::new(5, 5)] {
+            check_neighbours(&HexOddR, *loc);
+            check_neighbours(&HexEvenQ, *loc);
+        }
+    }
+
+    #[test]
+    fn test_distance() {
+        let (a, b) = (Point::new(1, 1), Point::new(4, 3));
+
+        assert_eq!(Square4.distance(&a, &b), 5);
+        assert_eq!(Square8.distance(&a, &b), 3);
+        assert_eq!(HexOddR.distance(&a, &b), 4);
+        assert_eq!(HexEvenQ.distance(&a, &b), 4);
+        assert_eq!(HexOddR.distance(&b, &b), 0);
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use amethyst::ecs::{Entity, Join, World, WorldExt};
+use amethyst::renderer::SpriteRender;
+use amethyst_core::transform::components::Parent;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Crop, Inventory, ItemStack, Loose, Player, Position};
+use crate::generation::is_generated;
+use crate::map_loader::tmx_path;
+use crate::resources::{Farm, GameClock, Hotbar, Items, MapSource, Mining, PersistedEntity, Point, WorldChunks};
+
+/// Bumped whenever a save written by an older version can no longer be read.
+pub const SAVE_VERSION: u32 = 1;
+pub const SAVE_DIR: &str = "saves";
+/// Slots are numbered from 1.
+pub const SAVE_SLOTS: usize = 3;
+
+/// A tile of some map.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct SavedLoc {
+    pub map_id: String,
+    pub x: usize,
+    pub y: usize,
+}
+
+impl SavedLoc {
+    pub fn new(map_id: &str, loc: &Point) -> Self {
+        Self { map_id: map_id.to_string(), x: loc.x, y: loc.y }
+    }
+
+    pub fn loc(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+}
+
+/// Everything about a game in progress which is not already in the map files or the item
+/// catalogue.  There are no quests yet so there is no quest state to save either.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub player: SavedLoc,
+    pub inventory: Vec<Option<ItemStack>>,
+    pub selected: Option<usize>, // hotbar slot
+    pub mined: Vec<SavedLoc>,
+    pub plots: Vec<(SavedLoc, bool)>, // watered
+    pub items: Vec<(SavedLoc, ItemStack)>, // lying on the ground
+    pub crops: Vec<(SavedLoc, Crop)>,
+    pub day: u32,
+    pub minutes: f32,
+}
+
+// Read on its own first so an old save gets a better error than a missing field.
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl SaveGame {
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        let Version { version } = ron::de::from_str(text).map_err(|e| e.to_string())?;
+        if version != SAVE_VERSION {
+            return Err(format!("save is version {} but only version {} can be loaded", version, SAVE_VERSION));
+        }
+
+        ron::de::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, PrettyConfig::default()).map_err(|e| e.to_string())
+    }
+
+    /// One line for picking a slot.
+    pub fn summary(&self) -> String {
+        let clock = GameClock { day: self.day, minutes: self.minutes, ..GameClock::default() };
+
+        format!("{:?} {} of {:?}, year {} on {}", clock.weekday(), clock.day_of_season(), clock.season(),
+                clock.year(), self.player.map_id)
+    }
+}
+
+pub fn save_path(slot: usize) -> PathBuf {
+    Path::new(SAVE_DIR).join(format!("slot{}.ron", slot))
+}
+
+/// What is saved in a slot.  Nothing saved there yet is Ok(None).  A save which cannot be
+/// played because its map is gone is as bad as one which cannot be read.
+pub fn read_save(slot: usize) -> Result<Option<SaveGame>, String> {
+    let path = save_path(slot);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+
+    playable(SaveGame::from_ron(&text)?).map(Some)
+}
+
+/// A save is only any good if the map the player is on can still be loaded.  Generated
+/// maps have no tmx but can always be generated again.
+pub fn playable(save: SaveGame) -> Result<SaveGame, String> {
+    let map_id = &save.player.map_id;
+    if !is_generated(map_id) && !tmx_path(map_id).exists() {
+        return Err(format!("map {} is missing", map_id));
+    }
+
+    Ok(save)
+}
+
+/// Save into a slot.  Written next to the old save first so a failed write does not lose it.
+pub fn write_save(save: &SaveGame, slot: usize) -> Result<PathBuf, String> {
+    let path = save_path(slot);
+    let partial = path.with_extension("ron.partial");
+
+    fs::create_dir_all(SAVE_DIR).map_err(|e| e.to_string())?;
+    fs::write(&partial, save.to_ron()?).map_err(|e| e.to_string())?;
+    fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+
+    Ok(path)
+}
+
+/// Every slot with what is in it (or why it cannot be read).
+pub fn list_slots() -> Vec<(usize, Result<Option<SaveGame>, String>)> {
+    (1..=SAVE_SLOTS).map(|slot| (slot, read_save(slot))).collect()
+}
+
+/// Everything worth saving out of the world.  None until there is a player.
+pub fn gather(world: &World) -> Option<SaveGame> {
+    let players = world.read_component::<Player>();
+    let positions = world.read_component::<Position>();
+    let inventories = world.read_component::<Inventory>();
+    let loose = world.read_component::<Loose>();
+    let stacks = world.read_component::<ItemStack>();
+    let parents = world.read_component::<Parent>();
+    let crops = world.read_component::<Crop>();
+    let chunks = world.read_resource::<WorldChunks>();
+    let clock = world.read_resource::<GameClock>();
+
+    let (position, inventory) = (&players, &positions, &inventories).join()
+        .map(|(_, position, inventory)| (position, inventory))
+        .next()?;
+
+    let mut mined: Vec<SavedLoc> = world.read_resource::<Mining>().mined.iter()
+        .map(|(map_id, loc)| SavedLoc::new(map_id, loc))
+        .collect();
+    let mut plots: Vec<(SavedLoc, bool)> = world.read_resource::<Farm>().iter()
+        .map(|(map_id, loc, plot)| (SavedLoc::new(map_id, loc), plot.watered))
+        .collect();
+
+    // Whatever is on the map right now and whatever got persisted when its chunk was evicted.
+    let persisted = chunks.persisted.values().flatten();
+    let mut items: Vec<(SavedLoc, ItemStack)> = (&loose, &stacks, &positions, !&parents).join()
+        .map(|(_, stack, position, _)| (position, stack))
+        .chain(persisted.clone().filter_map(|entity| entity.stack.as_ref().map(|stack| (&entity.position, stack))))
+        .map(|(position, stack)| (SavedLoc::new(&position.map_id, &position.loc), stack.clone()))
+        .collect();
+    let mut planted: Vec<(SavedLoc, Crop)> = (&crops, &positions).join()
+        .map(|(crop, position)| (position, crop))
+        .chain(persisted.filter_map(|entity| entity.crop.as_ref().map(|crop| (&entity.position, crop))))
+        .map(|(position, crop)| (SavedLoc::new(&position.map_id, &position.loc), crop.clone()))
+        .collect();
+
+    // Hash maps come out in any order so sort to keep saves of the same game the same.
+    mined.sort();
+    plots.sort_by(|a, b| a.0.cmp(&b.0));
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    planted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Some(SaveGame {
+        version: SAVE_VERSION,
+        player: SavedLoc::new(&position.map_id, &position.loc),
+        inventory: inventory.slots.clone(),
+        selected: world.read_resource::<Hotbar>().selected,
+        mined,
+        plots,
+        items,
+        crops: planted,
+        day: clock.day,
+        minutes: clock.minutes,
+    })
+}
+
+pub fn save_game(world: &World, slot: usize) -> Result<PathBuf, String> {
+    let save = gather(world).ok_or_else(|| "nothing to save yet".to_string())?;
+
+    write_save(&save, slot)
+}
+
+/// Put back what a save knows about maps and time.  Has to happen before any map is
+/// loaded since a map only takes its mined tiles and plots on its first load.
+pub fn prepare(world: &mut World, save: &SaveGame) {
+    {
+        let mut mining = world.write_resource::<Mining>();
+        mining.mined = save.mined.iter().map(|at| (at.map_id.clone(), at.loc())).collect();
+    }
+
+    {
+        let mut farm = world.write_resource::<Farm>();
+        for (at, watered) in &save.plots {
+            farm.till(&at.map_id, &at.loc());
+            if *watered {
+                farm.water(&at.map_id, &at.loc());
+            }
+        }
+    }
+
+    let mut clock = world.write_resource::<GameClock>();
+    clock.day = save.day;
+    clock.minutes = save.minutes;
+}
+
+/// Fill the player's inventory and put items and crops back onto their maps.  They wait
+/// in `WorldChunks` until their chunk gets loaded.  Anything no longer in the catalogue
+/// is left out.
+pub fn restore(world: &mut World, player: Entity, save: &SaveGame) {
+    let items = world.read_resource::<Items>();
+    let mut chunks = world.write_resource::<WorldChunks>();
+
+    if let Some(inventory) = world.write_component::<Inventory>().get_mut(player) {
+        let size = inventory.slots.len();
+        inventory.slots = save.inventory.iter().take(size)
+            .map(|stack| stack.clone().filter(|stack| items.get(&stack.id).is_some()))
+            .collect();
+        inventory.slots.resize(size, None);
+    }
+    world.write_resource::<Hotbar>().selected = save.selected;
+
+    let on_ground = save.items.iter().filter_map(|(at, stack)| {
+        let item = items.get(&stack.id)?;
+        Some((at, item.sprite, Some(stack.clone()), None::<Crop>))
+    });
+    let planted = save.crops.iter().filter_map(|(at, crop)| {
+        let growth = items.get(&crop.seed)?.growth.as_ref()?;
+        Some((at, crop.sprite(growth), None::<ItemStack>, Some(crop.clone())))
+    });
+
+    for (at, sprite, stack, crop) in on_ground.chain(planted) {
+        let loc = at.loc();
+        chunks.persisted.entry((at.map_id.clone(), MapSource::chunk_of(&loc))).or_insert_with(Vec::new).push(PersistedEntity {
+            position: Position::new(at.map_id.clone(), loc),
+            translation: None,
+            sprite: Some(SpriteRender { sprite_sheet: items.textures.clone(), sprite_number: sprite }),
+            animation: None,
+            solid: false,
+            bound: None,
+            depth_sort: None,
+            loose: stack.is_some(),
+            stack,
+            crop,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use amethyst::assets::{AssetStorage, Loader};
+    use amethyst::ecs::{Builder, World, WorldExt};
+    use amethyst::renderer::{SpriteSheet, Texture};
+    use amethyst_core::transform::components::Parent;
+    use rayon::ThreadPoolBuilder;
+
+    use crate::bountiful::load_items;
+    use crate::components::{Crop, Inventory, ItemStack, Loose, Player, Position};
+    use crate::resources::{Farm, GameClock, Hotbar, Mining, MapSource, Point, WorldChunks};
+    use crate::save_game::*;
+
+    fn save() -> SaveGame {
+        let at = |x, y| SavedLoc::new("bountiful", &Point::new(x, y));
+
+        SaveGame {
+            version: SAVE_VERSION,
+            player: at(3, 4),
+            inventory: vec![Some(ItemStack::new("hoe", 1)), None, Some(ItemStack::new("turnip_seeds", 7))],
+            selected: Some(0),
+            mined: vec![at(5, 5)],
+            plots: vec![(at(6, 6), true), (at(7, 6), false)],
+            items: vec![(at(2, 2), ItemStack::new("wood", 3))],
+            crops: vec![(at(6, 6), Crop { seed: "turnip_seeds".to_string(), stage: 1, days: 2 })],
+            day: 30,
+            minutes: 600.,
+        }
+    }
+
+    // Just enough of a game for saving and loading with a player carrying three slots.
+    fn world() -> (World, Entity) {
+        let mut world = World::new();
+        world.register::<Player>();
+        world.register::<Position>();
+        world.register::<Inventory>();
+        world.register::<Loose>();
+        world.register::<ItemStack>();
+        world.register::<Parent>();
+        world.register::<Crop>();
+        world.insert(AssetStorage::<Texture>::default());
+        world.insert(AssetStorage::<SpriteSheet>::default());
+        world.insert(Loader::new("assets", Arc::new(ThreadPoolBuilder::new().num_threads(1).build().unwrap())));
+        let items = load_items(&mut world);
+        world.insert(items);
+        world.insert(WorldChunks::default());
+        world.insert(GameClock::default());
+        world.insert(Mining::default());
+        world.insert(Farm::default());
+        world.insert(Hotbar::default());
+
+        let player = world.create_entity()
+            .with(Position::new("bountiful".to_string(), Point::new(3, 4)))
+            .with(Inventory::new(3))
+            .build();
+        world.write_component::<Player>().insert(player, Player { entity: player }).unwrap();
+
+        (world, player)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let save = save();
+
+        assert_eq!(SaveGame::from_ron(&save.to_ron().unwrap()), Ok(save.clone()));
+        assert_eq!(save.summary(), "Wednesday 3 of Summer, year 1 on bountiful");
+    }
+
+    #[test]
+    fn test_playable() {
+        let on = |map_id: &str| SaveGame { player: SavedLoc::new(map_id, &Point::new(3, 4)), ..save() };
+        let cave = on("cave:42").to_ron().unwrap();
+
+        assert_eq!(playable(SaveGame::from_ron(&cave).unwrap()), Ok(on("cave:42")));
+        assert!(playable(on("bountiful")).is_ok());
+        assert!(playable(on("volcano:42")).unwrap_err().contains("missing"));
+        assert!(playable(on("nowhere")).is_err());
+    }
+
+    #[test]
+    fn test_version() {
+        let old = SaveGame { version: SAVE_VERSION + 1, ..save() }.to_ron().unwrap();
+
+        assert!(SaveGame::from_ron(&old).unwrap_err().contains("version"));
+        assert!(SaveGame::from_ron("(version: 1)").is_err());
+        assert_eq!(save_path(2), Path::new(SAVE_DIR).join("slot2.ron"));
+    }
+
+    #[test]
+    fn test_restore_then_gather() {
+        let (mut world, player) = world();
+        let save = save();
+
+        prepare(&mut world, &save);
+        restore(&mut world, player, &save);
+        assert!(world.read_resource::<Farm>().is_watered("bountiful", &Point::new(6, 6)));
+        assert_eq!(world.read_resource::<Mining>().mined_on("bountiful"), vec![Point::new(5, 5)]);
+        assert_eq!(world.read_resource::<GameClock>().day, 30);
+        {
+            // Nothing is spawned until the chunks they are in get loaded.
+            let chunks = world.read_resource::<WorldChunks>();
+            let waiting = &chunks.persisted[&("bountiful".to_string(), MapSource::chunk_of(&Point::new(2, 2)))];
+            assert_eq!(waiting.iter().filter_map(|entity| entity.stack.clone()).collect::<Vec<_>>(), vec![ItemStack::new("wood", 3)]);
+            assert!(waiting.iter().all(|entity| entity.translation.is_none() && entity.sprite.is_some()));
+        }
+
+        // Gathered from what is around: the chunk with the crop gets loaded and one more
+        // item is picked up.
+        let crop = {
+            let mut chunks = world.write_resource::<WorldChunks>();
+            let waiting = chunks.persisted.get_mut(&("bountiful".to_string(), MapSource::chunk_of(&Point::new(6, 6)))).unwrap();
+            let index = waiting.iter().position(|entity| entity.crop.is_some()).unwrap();
+            waiting.remove(index)
+        };
+        world.create_entity().with(crop.position).with(crop.crop.unwrap()).build();
+        world.create_entity()
+            .with(Position::new("bountiful".to_string(), Point::new(1, 1)))
+            .with(Loose {})
+            .with(ItemStack::new("turnip", 2))
+            .build();
+        world.write_component::<Inventory>().get_mut(player).unwrap().slots[1] = Some(ItemStack::new("wood", 1));
+
+        let mut expected = save.clone();
+        expected.inventory[1] = Some(ItemStack::new("wood", 1));
+        expected.items.insert(0, (SavedLoc::new("bountiful", &Point::new(1, 1)), ItemStack::new("turnip", 2)));
+        assert_eq!(gather(&world), Some(expected));
+    }
+}
@@ -2,7 +2,7 @@ use amethyst::core::SystemBundle;
 use amethyst::ecs::{DispatcherBuilder, World};
 use amethyst::Result;
 
-use crate::resources::Hotbar;
+use crate::resources::{AutoTiler, Editor, Farm, GameClock, Hotbar, InventoryScreen, Lighting, MapManager, Mining, WorldChunks};
 
 pub struct SetupBundle;
 
@@ -11,6 +11,15 @@ pub struct SetupBundle;
 impl<'a, 'b> SystemBundle<'a, 'b> for SetupBundle {
     fn build(self, world: &mut World, _builder: &mut DispatcherBuilder<'a, 'b>) -> Result<()> {
         world.insert(Hotbar::default()); // will be reinserted later for reals
+        world.insert(InventoryScreen::default()); // ditto
+        world.insert(MapManager::default());
+        world.insert(WorldChunks::default());
+        world.insert(AutoTiler::default()); // will be reinserted later for reals
+        world.insert(Editor::default());
+        world.insert(Mining::default());
+        world.insert(Farm::default());
+        world.insert(GameClock::default()); // ditto
+        world.insert(Lighting::default());
         Ok(())
     }
 }
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Read, System, SystemData, Write, WriteStorage};
+use amethyst::renderer::SpriteRender;
+use amethyst::shrev::{EventChannel, ReaderId};
+
+use crate::resources::{AutoTiler, ChunkedMap, MapChange, MapManager, MapSource, TileUpdate, WorldChunks};
+
+/// Re-picks terrain sprites around tiles in `MapChange` events.
+#[derive(SystemDesc)]
+#[system_desc(name(AutoTileSystemDesc))]
+pub struct AutoTileSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<MapChange>,
+}
+
+impl AutoTileSystem {
+    pub fn new(reader_id: ReaderId<MapChange>) -> Self {
+        Self { reader_id }
+    }
+}
+
+impl<'s> System<'s> for AutoTileSystem {
+    type SystemData = (
+        Read<'s, EventChannel<MapChange>>,
+        Write<'s, MapManager>,
+        Write<'s, WorldChunks>,
+        Read<'s, AutoTiler>,
+        WriteStorage<'s, SpriteRender>,
+    );
+
+    fn run(&mut self, (events, mut manager, mut chunks, tiler, mut renders): Self::SystemData) {
+        // Always read so old events don't pile up while no map is streamed in.
+        let changes: Vec<_> = events.read(&mut self.reader_id)
+            .filter(|change| Some(&change.map_id) == chunks.map_id.as_ref() && Some(&change.map_id) == manager.current.as_ref())
+            .map(|change| change.loc)
+            .collect();
+
+        let map = match manager.map_mut() {
+            Some(map) if !changes.is_empty() => map,
+            _ => return,
+        };
+        let updates = tiler.refresh(&*map, &*map, &changes);
+        apply_tile_updates(&mut chunks, map, updates, &mut renders);
+    }
+}
+
+/// Put new gids into the loaded chunks of the current map.  Loaded tiles get a new sprite
+/// and chunks where a tile appears or disappears are rebuilt.
+pub fn apply_tile_updates<I>(chunks: &mut WorldChunks, map: &mut ChunkedMap, updates: I, renders: &mut WriteStorage<'_, SpriteRender>)
+    where I: IntoIterator<Item = TileUpdate> {
+    let mut reload = HashSet::new();
+
+    for update in updates {
+        if !map.set_gid(update.layer, &update.loc, update.gid) {
+            continue;
+        }
+
+        match chunks.tiles.get(&(update.loc, update.layer)) {
+            Some(entity) if update.gid != 0 => {
+                if let Some(render) = renders.get_mut(*entity) {
+                    render.sprite_number = (update.gid - 1) as usize;
+                }
+            },
+            _ => {
+                let coord = MapSource::chunk_of(&update.loc);
+                if chunks.loaded.contains_key(&coord) {
+                    reload.insert(coord);
+                }
+            },
+        }
+    }
+
+    for coord in reload {
+        chunks.reload(&coord, map);
+    }
+}
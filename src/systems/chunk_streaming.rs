@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use amethyst::core::Transform;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Entities, Entity, Join, ReadStorage, System, SystemData, Write, WriteStorage};
+use amethyst::renderer::{Camera, SpriteRender};
+use pathfinding::utils::absdiff;
+
+use crate::bountiful::{GROUND_ITEMS_Z, MAP_LAYERS_Z};
+use crate::components::{Bound, Crop, DepthSort, ItemStack, Loose, Player, Position, Solid, TileAnimation};
+use crate::resources::{ChunkData, MapManager, MapSource, Orientation, PersistedEntity, Point, WorldChunks, EVICT_RADIUS, LOAD_RADIUS};
+
+/// Loads the chunks around the camera into the `ChunkedMap` of the `MapManager` and evicts
+/// the ones far away from it.  Entities which are not tiles but live in an evicted chunk
+/// are persisted and come back when their chunk gets loaded again.
+#[derive(SystemDesc)]
+pub struct ChunkStreamingSystem;
+
+impl<'s> System<'s> for ChunkStreamingSystem {
+    type SystemData = (
+        Write<'s, WorldChunks>,
+        Write<'s, MapManager>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Player>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Position>,
+        WriteStorage<'s, TileAnimation>,
+        WriteStorage<'s, Solid>,
+        WriteStorage<'s, Bound>,
+        WriteStorage<'s, DepthSort>,
+        WriteStorage<'s, Loose>,
+        WriteStorage<'s, ItemStack>,
+        WriteStorage<'s, Crop>,
+        Entities<'s>,
+    );
+
+    fn run(&mut self, (mut chunks, mut manager, cameras, players, mut transforms, mut renders, mut positions,
+        mut animations, mut solids, mut bounds, mut sorts, mut loose, mut stacks, mut crops,
+        entities): Self::SystemData) {
+        for (map_id, coord, tiles) in std::mem::take(&mut chunks.evicting) {
+            let tiles: HashSet<Entity> = tiles.into_iter().collect();
+            let mut persisted = vec![];
+
+            for (entity, position, transform, _) in (&entities, &positions, &transforms, !&players).join() {
+                if position.map_id == map_id && MapSource::chunk_of(&position.loc) == coord && !tiles.contains(&entity) {
+                    persisted.push(PersistedEntity {
+                        position: position.clone(),
+                        translation: Some(*transform.translation()),
+                        sprite: renders.get(entity).cloned(),
+                        animation: animations.get(entity).cloned(),
+                        solid: solids.contains(entity),
+                        bound: bounds.get(entity).cloned(),
+                        depth_sort: sorts.get(entity).cloned(),
+                        loose: loose.contains(entity),
+                        stack: stacks.get(entity).cloned(),
+                        crop: crops.get(entity).cloned(),
+                    });
+                    entities.delete(entity).expect("Failed to evict entity");
+                }
+            }
+
+            for entity in tiles {
+                entities.delete(entity).expect("Failed to evict tile");
+            }
+
+            if !persisted.is_empty() {
+                chunks.persisted.entry((map_id, coord)).or_insert_with(Vec::new).extend(persisted);
+            }
+        }
+
+        let (info, map) = match manager.current_mut() {
+            Some((info, map)) if Some(&info.id) == chunks.map_id.as_ref() => (info, map),
+            _ => return,
+        };
+
+        // The camera is parented to the player so we need its global location.
+        let camera_chunk = (&cameras, &transforms).join()
+            .map(|(_, transform)| transform.global_matrix())
+            .filter_map(|matrix| info.point_of(matrix[(0, 3)], matrix[(1, 3)]))
+            .map(|loc| MapSource::chunk_of(&loc))
+            .next();
+
+        if let Some(center) = camera_chunk {
+            let (chunks_wide, chunks_high) = map.chunk_dimensions();
+
+            for y in center.y.saturating_sub(LOAD_RADIUS)..(center.y + LOAD_RADIUS + 1).min(chunks_high) {
+                for x in center.x.saturating_sub(LOAD_RADIUS)..(center.x + LOAD_RADIUS + 1).min(chunks_wide) {
+                    chunks.request(Point::new(x, y), map);
+                }
+            }
+
+            let far: Vec<Point> = map.coords().into_iter()
+                .filter(|coord| Self::chunk_distance(coord, &center) > EVICT_RADIUS)
+                .collect();
+            for coord in far {
+                chunks.evict(&coord, map);
+            }
+        }
+
+        for chunk in chunks.finished(map) {
+            let ChunkData { map_id, coord, tiles } = chunk;
+            let sprite_sheet = chunks.sprite_sheet.clone().unwrap();
+            let mut spawned = Vec::with_capacity(tiles.len());
+
+            for tile in tiles {
+                let mut transform = Transform::default();
+                transform.set_translation(info.world_of(&tile.loc, MAP_LAYERS_Z[tile.layer]));
+
+                let sprite_render = SpriteRender {
+                    sprite_sheet: sprite_sheet.clone(),
+                    sprite_number: (tile.gid - 1) as usize, // sprites are 0-based.
+                };
+
+                // Tiles on the solids layer are not `Solid`.  The `CollisionSystem` gets them
+                // from the map.
+                let mut builder = entities.build_entity()
+                    .with(transform, &mut transforms)
+                    .with(sprite_render, &mut renders)
+                    .with(Position::new(map_id.clone(), tile.loc), &mut positions);
+
+                if let Some(animation) = chunks.animations.get(&(tile.gid - 1)) {
+                    builder = builder.with(animation.clone(), &mut animations);
+                }
+
+                // Isometric solids stand up out of the ground so they need sorting too.
+                if tile.layer == 2 || (tile.layer == 1 && info.orientation == Orientation::Isometric) {
+                    builder = builder.with(DepthSort::new(info.tile_height / 2.), &mut sorts);
+                }
+
+                let entity = builder.build();
+                chunks.tiles.insert((tile.loc, tile.layer), entity);
+                spawned.push(entity);
+            }
+
+            for persisted in chunks.persisted.remove(&(map_id, coord)).unwrap_or_default() {
+                let z = if persisted.crop.is_some() { MAP_LAYERS_Z[2] } else { GROUND_ITEMS_Z };
+                let mut transform = Transform::default();
+                transform.set_translation(persisted.translation.unwrap_or_else(|| info.world_of(&persisted.position.loc, z)));
+
+                let mut builder = entities.build_entity()
+                    .with(transform, &mut transforms)
+                    .with(persisted.position, &mut positions);
+
+                if let Some(sprite) = persisted.sprite {
+                    builder = builder.with(sprite, &mut renders);
+                }
+
+                if let Some(animation) = persisted.animation {
+                    builder = builder.with(animation, &mut animations);
+                }
+
+                if persisted.solid {
+                    builder = builder.with(Solid {}, &mut solids);
+                }
+
+                if let Some(bound) = persisted.bound {
+                    builder = builder.with(bound, &mut bounds);
+                }
+
+                match persisted.depth_sort {
+                    Some(sort) => builder = builder.with(sort, &mut sorts),
+                    None if persisted.crop.is_some() => builder = builder.with(DepthSort::new(info.tile_height / 2.), &mut sorts),
+                    None => (),
+                }
+
+                if persisted.loose {
+                    builder = builder.with(Loose {}, &mut loose);
+                }
+
+                if let Some(stack) = persisted.stack {
+                    builder = builder.with(stack, &mut stacks);
+                }
+
+                if let Some(crop) = persisted.crop {
+                    builder = builder.with(crop, &mut crops);
+                }
+
+                builder.build();
+            }
+
+            chunks.loaded.insert(coord, spawned);
+        }
+    }
+}
+
+impl ChunkStreamingSystem {
+    fn chunk_distance(a: &Point, b: &Point) -> usize {
+        absdiff(a.x, b.x).max(absdiff(a.y, b.y))
+    }
+}
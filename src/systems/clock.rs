@@ -0,0 +1,43 @@
+use amethyst::core::timing::Time;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Read, System, SystemData, Write};
+use amethyst::input::{InputHandler, StringBindings};
+use amethyst::shrev::EventChannel;
+
+use crate::resources::{Editor, GameClock, InventoryScreen, MapManager, NewDay};
+
+/// Moves the `GameClock` along with real time once there is a map to play on.  The clock
+/// stops while the inventory screen or the editor is open.  The "sleep" action skips to
+/// the next morning.  Every day which starts gets a `NewDay` event.
+#[derive(SystemDesc, Default)]
+pub struct ClockSystem {
+    key_down: bool,
+}
+
+impl<'s> System<'s> for ClockSystem {
+    type SystemData = (
+        Write<'s, GameClock>,
+        Write<'s, EventChannel<NewDay>>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, InventoryScreen>,
+        Read<'s, Editor>,
+        Read<'s, MapManager>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (mut clock, mut new_days, input, screen, editor, manager, time): Self::SystemData) {
+        clock.paused = screen.open || editor.active;
+        if manager.current.is_none() {
+            return;
+        }
+
+        let key_down = input.action_is_down("sleep").unwrap_or(false);
+        let sleep = key_down && !self.key_down && !clock.paused;
+        self.key_down = key_down;
+
+        let days = if sleep { clock.sleep() } else { clock.advance(time.delta_seconds()) };
+        for day in clock.day + 1 - days..=clock.day {
+            new_days.single_write(NewDay { day, slept: sleep });
+        }
+    }
+}
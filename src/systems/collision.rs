@@ -1,16 +1,47 @@
+use std::collections::HashSet;
 use amethyst::core::Transform;
 use amethyst::core::timing::Time;
 use amethyst::derive::SystemDesc;
 use amethyst::ecs::{Entity, Entities, Join, Read, ReadStorage, System, SystemData, WriteStorage};
 use amethyst::renderer::SpriteRender;
+use amethyst::shrev::{EventChannel, ReaderId};
 
 use crate::components::{ProposedMove, ProposedMoveType, Solid, Bound, SpriteAnimation};
+use crate::generation::FLOOR;
+use crate::resources::{MapChange, MapInfo, MapManager, MapSource, Point};
 
+/// Solid tiles this many tiles away from a mover are looked at.  Enough for anything the
+/// size of the player on isometric maps too.
+const TILE_REACH: usize = 2;
+
+/// Moves things which proposed a move unless they would run into something solid.  Solid
+/// tiles come from the loaded chunks of the current map as they are streamed in and only
+/// the ones in `MapChange` events get looked at again.  Tiles of chunks which are not
+/// loaded are all solid since we do not know what is there.  Anything else which is
+/// `Solid` has to be checked one by one.
 #[derive(SystemDesc)]
-pub struct CollisionSystem;
+#[system_desc(name(CollisionSystemDesc))]
+pub struct CollisionSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<MapChange>,
+    #[system_desc(skip)]
+    blocked: HashSet<Point>,
+    #[system_desc(skip)]
+    counted: HashSet<Point>, // chunks whose solid tiles are in blocked
+    #[system_desc(skip)]
+    loaded: Option<(String, usize)>, // map and `MapManager::loads` blocked is for
+}
+
+impl CollisionSystem {
+    pub fn new(reader_id: ReaderId<MapChange>) -> Self {
+        Self { reader_id, blocked: HashSet::new(), counted: HashSet::new(), loaded: None }
+    }
+}
 
 impl<'s> System<'s> for CollisionSystem {
     type SystemData = (
+        Read<'s, EventChannel<MapChange>>,
+        Read<'s, MapManager>,
         WriteStorage<'s, Transform>,
         WriteStorage<'s, ProposedMove>,
         ReadStorage<'s, Solid>,
@@ -22,7 +53,10 @@ impl<'s> System<'s> for CollisionSystem {
     );
 
     // FIXME: More complication animations cannot be combined line this...systems? or type of aninmation so it is one component
-    fn run(&mut self, (mut transforms, mut moves, solids, bounds, mut renders, mut sprite_animations, time, entities): Self::SystemData) {
+    fn run(&mut self, (events, manager, mut transforms, mut moves, solids, bounds, mut renders, mut sprite_animations,
+        time, entities): Self::SystemData) {
+        self.catch_up(&events, &manager);
+
         let mut to_move: Vec<(Entity, f32, f32)> = vec![];
         let mut to_remove: Vec<Entity> = vec![];
 
@@ -45,21 +79,15 @@ impl<'s> System<'s> for CollisionSystem {
             let (x, y) = (transform.translation().x, transform.translation().y);
             let mover_bound = bounds.get(entity).expect("Something moving which has no bound?");
 
-            // FIXME: Only do this join if within a game point vs all possible solids.
-
-            // A lot of searching but we do not have any idea how many solids exist that we might
-            // collide with.  We also can get x and y velocities at the same time and we want to
-            // be able to slide along a solid if either x or y dim is invalid but not both.
+            // We can get x and y velocities at the same time and we want to be able to slide
+            // along a solid if either x or y dim is invalid but not both.
             for (dx, dy) in &[(dx, dy), (0., dy), (dx, 0.)] {
-                let mut should_move = true;
                 let (nx, ny) = (x + dx, y + dy);
-                for (_solid, bound, entity) in (&solids, &bounds, &entities).join() {
+                let hits_tile = manager.info().map_or(false, |info| self.hits_tile(info, (nx, ny), mover_bound));
+                let should_move = !hits_tile && !(&solids, &bounds, &entities).join().any(|(_solid, bound, entity)| {
                     let ot = transforms.get(entity).expect("No Solid Bound").translation();
-                    if bound.intersects((ot.x, ot.y), (nx, ny), mover_bound) {
-                        should_move = false;
-                        break;
-                    }
-                }
+                    bound.intersects((ot.x, ot.y), (nx, ny), mover_bound)
+                });
 
                 if should_move {
                     sprite_render.sprite_number = anim.update(time.delta_seconds(), (*dx, *dy));
@@ -79,4 +107,72 @@ impl<'s> System<'s> for CollisionSystem {
             moves.remove(entity);
         }
     }
-}
\ No newline at end of file
+}
+
+impl CollisionSystem {
+    // Chunks are read whole as they come and forgotten as they go.  Otherwise only changed
+    // tiles are read.
+    fn catch_up(&mut self, events: &EventChannel<MapChange>, manager: &MapManager) {
+        // Always read so old events don't pile up while no map is loaded.
+        let changes: Vec<Point> = events.read(&mut self.reader_id)
+            .filter(|change| Some(&change.map_id) == manager.current.as_ref())
+            .map(|change| change.loc)
+            .collect();
+
+        let (map_id, map) = match (&manager.current, manager.map()) {
+            (Some(map_id), Some(map)) => (map_id, map),
+            _ => {
+                self.blocked.clear();
+                self.counted.clear();
+                self.loaded = None;
+                return;
+            },
+        };
+
+        let loaded = (map_id.clone(), manager.loads());
+        if self.loaded.as_ref() != Some(&loaded) {
+            self.blocked.clear();
+            self.counted.clear();
+            self.loaded = Some(loaded);
+        }
+
+        let resident = map.coords();
+        self.blocked.retain(|loc| resident.contains(&MapSource::chunk_of(loc)));
+        self.counted.retain(|coord| resident.contains(coord));
+        for coord in resident {
+            if self.counted.insert(coord) {
+                let chunk = map.chunk(&coord).unwrap();
+                self.blocked.extend(chunk.tiles().filter(|(_, tile)| tile.id != FLOOR).map(|(loc, _)| loc));
+            }
+        }
+
+        for loc in changes {
+            match map.at(&loc) {
+                Some(tile) if tile.id != FLOOR => self.blocked.insert(loc),
+                _ => self.blocked.remove(&loc),
+            };
+        }
+    }
+
+    // Solid tiles are a tile_bound box in the middle of their tile.  Something off the map
+    // is checked against all of them (but not against chunks which are not loaded).
+    fn hits_tile(&self, info: &MapInfo, (x, y): (f32, f32), mover_bound: &Bound) -> bool {
+        let (width, height) = info.tile_bound();
+        let tile_bound = Bound::new(width, height);
+        let hits = |loc: &Point| {
+            let center = info.world_of(loc, 0.);
+            tile_bound.intersects((center.x, center.y), (x, y), mover_bound)
+        };
+
+        match info.point_of(x, y) {
+            Some(at) => {
+                let (left, top) = (at.x.saturating_sub(TILE_REACH), at.y.saturating_sub(TILE_REACH));
+                (top..=at.y + TILE_REACH)
+                    .flat_map(|j| (left..=at.x + TILE_REACH).map(move |i| Point::new(i, j)))
+                    .filter(|loc| loc.x < info.width && loc.y < info.height)
+                    .any(|loc| (self.blocked.contains(&loc) || !self.counted.contains(&MapSource::chunk_of(&loc))) && hits(&loc))
+            },
+            None => self.blocked.iter().any(hits),
+        }
+    }
+}
@@ -7,8 +7,8 @@ use amethyst_imgui::{
     imgui,
     imgui::im_str,
 };
-use crate::bountiful::{TILE_WIDTH, TILE_HEIGHT};
 use crate::components::Player;
+use crate::resources::MapManager;
 
 
 pub struct DebugSystem {
@@ -30,9 +30,10 @@ impl<'s> System<'s> for DebugSystem {
         ReadStorage<'s, Transform>,
         Read<'s, Time>,
         Read<'s, FpsCounter>,
+        Read<'s, MapManager>,
     );
 
-    fn run(&mut self, (players, transforms, time, fps_counter): Self::SystemData) {
+    fn run(&mut self, (players, transforms, time, fps_counter, manager): Self::SystemData) {
         let mut open = true;
         let window_pos = [DISTANCE, DISTANCE];
         let window_pos_pivot = [0.0, 0.0];
@@ -62,7 +63,9 @@ impl<'s> System<'s> for DebugSystem {
                 for (_player, transform) in (&players, &transforms).join() {
                     let (i, j) = (transform.translation().x, transform.translation().y);
                     ui.text(im_str!("Loc [{},{}]", i, j));
-                    ui.text(im_str!("Point [{},{}]", (i / TILE_WIDTH) as usize, (j / TILE_HEIGHT) as usize));
+                    if let Some(loc) = manager.info().and_then(|info| info.point_of(i, j)) {
+                        ui.text(im_str!("Point [{},{}]", loc.x, loc.y));
+                    }
                 }
             });
         });
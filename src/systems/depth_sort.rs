@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use amethyst::core::Transform;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Entities, Join, Read, ReadStorage, System, SystemData, WriteStorage};
+use amethyst_core::transform::components::Parent;
+
+use crate::bountiful::{HOTBAR_CONTENTS_Z, HOTBAR_Z};
+use crate::components::{DepthSort, HotbarGui, Loose};
+use crate::resources::MapManager;
+
+/// Keeps sprites on the objects layer ordered by how far down the screen their feet are.
+/// The hotbar and whatever is in it are children of the player so they would move through
+/// the objects band along with the player.  They are kept at their own z instead.
+#[derive(SystemDesc)]
+pub struct DepthSortSystem;
+
+impl<'s> System<'s> for DepthSortSystem {
+    type SystemData = (
+        Read<'s, MapManager>,
+        ReadStorage<'s, DepthSort>,
+        ReadStorage<'s, Parent>,
+        ReadStorage<'s, HotbarGui>,
+        ReadStorage<'s, Loose>,
+        WriteStorage<'s, Transform>,
+        Entities<'s>,
+    );
+
+    fn run(&mut self, (manager, sorts, parents, guis, loose, mut transforms, entities): Self::SystemData) {
+        let world_height = match manager.info() {
+            Some(info) => info.pixel_height(),
+            None => return,
+        };
+
+        let mut sorted = HashMap::new();
+        for (entity, sort, transform) in (&entities, &sorts, &mut transforms).join() {
+            let z = sort.z_for(transform.translation().y, world_height);
+            transform.set_translation_z(z);
+            sorted.insert(entity, z);
+        }
+
+        // The camera is a child of the player too but its z does not matter.
+        for (parent, transform, gui, item) in (&parents, &mut transforms, guis.maybe(), loose.maybe()).join() {
+            let z = match (sorted.get(&parent.entity), gui, item) {
+                (Some(parent_z), Some(_), _) => HOTBAR_Z - parent_z,
+                (Some(parent_z), None, Some(_)) => HOTBAR_CONTENTS_Z - parent_z,
+                _ => continue,
+            };
+            transform.set_translation_z(z);
+        }
+    }
+}
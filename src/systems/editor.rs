@@ -0,0 +1,213 @@
+use amethyst::assets::AssetStorage;
+use amethyst::core::Transform;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage};
+use amethyst::input::{InputHandler, StringBindings};
+use amethyst::renderer::{Camera, SpriteRender, SpriteSheet};
+use amethyst_imgui::{
+    imgui,
+    imgui::im_str,
+};
+use amethyst_window::ScreenDimensions;
+use nalgebra::{Point3, Vector2};
+use winit::MouseButton;
+
+use crate::bountiful::LAYER_NAMES;
+use crate::map_loader::nav_tile;
+use crate::resources::editor::{fill_points, rect_points};
+use crate::resources::{AutoTiler, ChunkedMap, Editor, Gids, MapManager, Point, TileUpdate, Tool, WorldChunks, TOOLS};
+use crate::systems::auto_tile::apply_tile_updates;
+
+const PALETTE_COLUMNS: u32 = 4; // same as the tileset image
+const PALETTE_BUTTON: [f32; 2] = [36., 24.];
+const PALETTE_TILE: [f32; 2] = [32., 32.];
+const PALETTE_SELECTED: [f32; 4] = [1., 0.8, 0.2, 1.];
+
+/// Draws the editor window and changes the map where the `Pointer` is clicked while the
+/// `MapEditor` state is active.  Edits change the loaded chunks of the map and are
+/// remembered in `WorldChunks` so saving can put them (and only them) into the map file.
+#[derive(SystemDesc, Default)]
+pub struct EditorSystem {
+    mouse_down: bool,
+    painted: Option<Point>, // last tile painted during this drag
+}
+
+impl<'s> System<'s> for EditorSystem {
+    type SystemData = (
+        Write<'s, Editor>,
+        Write<'s, MapManager>,
+        Write<'s, WorldChunks>,
+        WriteStorage<'s, SpriteRender>,
+        ReadStorage<'s, Camera>,
+        ReadStorage<'s, Transform>,
+        ReadExpect<'s, ScreenDimensions>,
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, AssetStorage<SpriteSheet>>,
+        Read<'s, AutoTiler>,
+    );
+
+    fn run(&mut self, (mut editor, mut manager, mut chunks, mut renders, cameras, transforms, dimensions,
+        input, sprite_sheets, tiler): Self::SystemData) {
+        if !editor.active {
+            self.mouse_down = false;
+            return;
+        }
+
+        let tile_count = chunks.sprite_sheet.as_ref()
+            .and_then(|handle| sprite_sheets.get(handle))
+            .map_or(0, |sheet| sheet.sprites.len() as u32);
+        let mut over_window = false;
+
+        amethyst_imgui::with(|ui| {
+            over_window = ui.io().want_capture_mouse;
+            Self::draw_window(ui, &mut *editor, tile_count);
+        });
+
+        let loc = input.mouse_position().filter(|_| !over_window).and_then(|(x, y)| {
+            let (camera, camera_transform) = (&cameras, &transforms).join().next()?;
+            let pos = camera.projection().screen_to_world_point(Point3::new(x, y, 0.),
+                                                                Vector2::new(dimensions.width(), dimensions.height()),
+                                                                camera_transform);
+            manager.info()?.point_of(pos.x, pos.y)
+        });
+        let mouse_down = input.mouse_button_is_down(MouseButton::Left);
+        let pressed = mouse_down && !self.mouse_down;
+        let released = !mouse_down && self.mouse_down;
+        self.mouse_down = mouse_down;
+
+        let points = match (editor.tool, loc) {
+            (Tool::Paint, Some(loc)) | (Tool::Erase, Some(loc)) if mouse_down && self.painted != Some(loc) => {
+                self.painted = Some(loc);
+                vec![loc]
+            },
+            (Tool::Fill, Some(loc)) if pressed => match manager.map() {
+                Some(map) if map.gid_at(editor.layer, &loc) != Some(editor.brush()) => fill_points(map, editor.layer, &loc),
+                _ => vec![],
+            },
+            (Tool::Rect, Some(loc)) if pressed => {
+                editor.anchor = Some(loc);
+                vec![]
+            },
+            (Tool::Rect, _) if released => match (editor.anchor.take(), loc.or(self.painted)) {
+                (Some(anchor), Some(corner)) => rect_points(&anchor, &corner),
+                _ => vec![],
+            },
+            _ => vec![],
+        };
+
+        // Rect remembers the last tile the mouse was over in case it is let go off the map.
+        if editor.tool == Tool::Rect && loc.is_some() {
+            self.painted = loc;
+        }
+        if released {
+            self.painted = None;
+        }
+
+        let (layer, brush) = (editor.layer, editor.brush());
+        let updates = if std::mem::replace(&mut editor.undo_requested, false) {
+            editor.undo()
+        } else if std::mem::replace(&mut editor.redo_requested, false) {
+            editor.redo()
+        } else {
+            match manager.map() {
+                Some(map) if !points.is_empty() => editor.record(map, layer, brush, &points),
+                _ => vec![],
+            }
+        };
+
+        if let Some(map) = manager.map_mut().filter(|_| !updates.is_empty()) {
+            Self::paint(map, &mut chunks, &mut renders, &tiler, updates);
+        }
+    }
+}
+
+impl EditorSystem {
+    fn draw_window(ui: &imgui::Ui, editor: &mut Editor, tile_count: u32) {
+        imgui::Window::new(im_str!("Editor"))
+            .always_auto_resize(true)
+            .save_settings(false)
+            .build(ui, || {
+                for (tool, name) in TOOLS.iter() {
+                    ui.radio_button(&im_str!("{}", name), &mut editor.tool, *tool);
+                    ui.same_line(0.);
+                }
+                ui.new_line();
+
+                for (layer, name) in LAYER_NAMES.iter().enumerate() {
+                    ui.radio_button(&im_str!("{}", name), &mut editor.layer, layer);
+                    ui.same_line(0.);
+                }
+                ui.new_line();
+
+                // Tiles as they look in the tileset once imgui has been given its texture.
+                ui.separator();
+                let rows = (tile_count + PALETTE_COLUMNS - 1) / PALETTE_COLUMNS;
+                for gid in 1..=tile_count {
+                    let selected = gid == editor.gid;
+                    let clicked = match editor.palette {
+                        Some(texture) => {
+                            let (column, row) = ((gid - 1) % PALETTE_COLUMNS, (gid - 1) / PALETTE_COLUMNS);
+                            let (u, v) = (1. / PALETTE_COLUMNS as f32, 1. / rows as f32);
+                            let id = ui.push_id(gid as i32); // they all share one texture
+                            let clicked = imgui::ImageButton::new(imgui::TextureId::from(texture), PALETTE_TILE)
+                                .uv0([column as f32 * u, row as f32 * v])
+                                .uv1([(column + 1) as f32 * u, (row + 1) as f32 * v])
+                                .bg_col(if selected { PALETTE_SELECTED } else { [0., 0., 0., 0.] })
+                                .build(ui);
+                            id.pop(ui);
+                            clicked
+                        },
+                        None => {
+                            let label = if selected { im_str!("[{}]", gid) } else { im_str!("{}", gid) };
+                            ui.button(&label, PALETTE_BUTTON)
+                        },
+                    };
+                    if clicked {
+                        editor.gid = gid;
+                    }
+                    if gid % PALETTE_COLUMNS != 0 {
+                        ui.same_line(0.);
+                    }
+                }
+                ui.new_line();
+
+                ui.separator();
+                if ui.button(im_str!("Undo"), [0., 0.]) {
+                    editor.undo_requested = true;
+                }
+                ui.same_line(0.);
+                if ui.button(im_str!("Redo"), [0., 0.]) {
+                    editor.redo_requested = true;
+                }
+                ui.same_line(0.);
+                if ui.button(im_str!("Save"), [0., 0.]) {
+                    editor.save_requested = true;
+                }
+                if !editor.status.is_empty() {
+                    ui.text(&editor.status);
+                }
+            });
+    }
+
+    // Solids also block movement so the navigation tiles have to agree with that layer.
+    // Auto tiling then picks the right fence sprites around painted fences.  Other solids
+    // stay as painted.  Fences of the map file get tiled again when it is saved.  An undo
+    // or redo of tiles which are not loaded any more does nothing to them.
+    fn paint(map: &mut ChunkedMap, chunks: &mut WorldChunks, renders: &mut WriteStorage<'_, SpriteRender>,
+             tiler: &AutoTiler, mut updates: Vec<TileUpdate>) {
+        updates.retain(|update| map.gid_at(update.layer, &update.loc).is_some());
+        let solids: Vec<(Point, u32)> = updates.iter()
+            .filter(|update| update.layer == 1)
+            .map(|update| (update.loc, update.gid))
+            .collect();
+
+        for update in &updates {
+            chunks.edit(update.layer, &update.loc, map.gid(update.layer, &update.loc), update.gid);
+        }
+        apply_tile_updates(chunks, map, updates, renders);
+
+        for (loc, gid) in solids {
+            map.set_at(&loc, nav_tile(tiler, gid)).unwrap();
+        }
+    }
+}
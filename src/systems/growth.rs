@@ -0,0 +1,85 @@
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Join, Read, ReadStorage, System, SystemData, Write, WriteStorage};
+use amethyst::renderer::SpriteRender;
+use amethyst::shrev::{EventChannel, ReaderId};
+
+use crate::components::{Crop, Position};
+use crate::resources::{Farm, Gids, Items, MapManager, NewDay, TileUpdate, WorldChunks, SOIL_GID};
+use crate::systems::auto_tile::apply_tile_updates;
+
+/// Grows crops on every `NewDay`.  Crops on watered soil move along a day and then all
+/// soil dries out again.  Crops in evicted chunks (or on other maps) grow too.
+#[derive(SystemDesc)]
+#[system_desc(name(GrowthSystemDesc))]
+pub struct GrowthSystem {
+    #[system_desc(event_channel_reader)]
+    reader_id: ReaderId<NewDay>,
+}
+
+impl GrowthSystem {
+    pub fn new(reader_id: ReaderId<NewDay>) -> Self {
+        Self { reader_id }
+    }
+}
+
+impl<'s> System<'s> for GrowthSystem {
+    type SystemData = (
+        Read<'s, EventChannel<NewDay>>,
+        Option<Read<'s, Items>>, // not until the game starts
+        Write<'s, Farm>,
+        Write<'s, MapManager>,
+        Write<'s, WorldChunks>,
+        ReadStorage<'s, Position>,
+        WriteStorage<'s, Crop>,
+        WriteStorage<'s, SpriteRender>,
+    );
+
+    fn run(&mut self, (new_days, items, mut farm, mut manager, mut chunks, positions, mut crops, mut renders): Self::SystemData) {
+        // Always read so days don't pile up before the game starts.
+        let days = new_days.read(&mut self.reader_id).count();
+        let items = match items {
+            Some(items) if days > 0 => items,
+            _ => return,
+        };
+
+        // Only the first of several days passing at once has watered soil.
+        for day in 0..days {
+            for (crop, position, render) in (&mut crops, &positions, &mut renders).join() {
+                let watered = day == 0 && farm.is_watered(&position.map_id, &position.loc);
+                grow(&items, crop, &mut render.sprite_number, watered);
+            }
+
+            for persisted in chunks.persisted.values_mut().flatten() {
+                if let (Some(crop), Some(sprite)) = (&mut persisted.crop, &mut persisted.sprite) {
+                    let position = &persisted.position;
+                    let watered = day == 0 && farm.is_watered(&position.map_id, &position.loc);
+                    grow(&items, crop, &mut sprite.sprite_number, watered);
+                }
+            }
+        }
+
+        // Soil in chunks which are not loaded (on this map or any other) dries when they are.
+        let streaming = manager.current.is_some() && manager.current == chunks.map_id;
+        let mut dried = vec![];
+        for (map_id, loc) in farm.dry_out() {
+            let loaded = manager.map()
+                .filter(|map| streaming && Some(&map_id) == chunks.map_id.as_ref() && map.gid_at(0, &loc).is_some());
+            match loaded {
+                Some(_) => dried.push(TileUpdate { loc, layer: 0, gid: SOIL_GID }),
+                None => chunks.patch(&map_id, 0, &loc, SOIL_GID),
+            }
+        }
+        if let Some(map) = manager.map_mut().filter(|_| streaming) {
+            apply_tile_updates(&mut chunks, map, dried, &mut renders);
+        }
+    }
+}
+
+// One day of growth for a crop wherever it is.  Seeds no longer in the catalogue stay put.
+fn grow(items: &Items, crop: &mut Crop, sprite_number: &mut usize, watered: bool) {
+    if let Some(growth) = items.get(&crop.seed).and_then(|seed| seed.growth.as_ref()) {
+        if crop.grow(growth, watered) {
+            *sprite_number = crop.sprite(growth);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Read, ReadExpect, System, SystemData};
+use amethyst_imgui::{
+    imgui,
+    imgui::im_str,
+};
+use amethyst_window::ScreenDimensions;
+
+use crate::resources::{GameClock, MapManager};
+
+const DISTANCE: f32 = 10.0;
+
+/// Shows the date and time in the upper right corner while a map is being played.
+#[derive(SystemDesc)]
+pub struct HudSystem;
+
+impl<'s> System<'s> for HudSystem {
+    type SystemData = (
+        Read<'s, GameClock>,
+        Read<'s, MapManager>,
+        ReadExpect<'s, ScreenDimensions>,
+    );
+
+    fn run(&mut self, (clock, manager, dimensions): Self::SystemData) {
+        if manager.current.is_none() {
+            return;
+        }
+
+        amethyst_imgui::with(|ui| {
+            imgui::Window::new(im_str!("##clock"))
+                .bg_alpha(0.35)
+                .no_decoration()
+                .no_inputs()
+                .always_auto_resize(true)
+                .save_settings(false)
+                .focus_on_appearing(false)
+                .no_nav()
+                .position([dimensions.width() - DISTANCE, DISTANCE], imgui::Condition::Always)
+                .position_pivot([1., 0.])
+                .build(ui, || {
+                    ui.text(im_str!("{:?} {} of {:?}, year {}", clock.weekday(), clock.day_of_season(), clock.season(), clock.year()));
+                    ui.text(im_str!("{}{}", clock.time_of_day(), if clock.paused { " (paused)" } else { "" }));
+                });
+        });
+    }
+}
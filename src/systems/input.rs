@@ -1,24 +1,26 @@
+use std::collections::HashSet;
 use amethyst::core::Transform;
 use amethyst::derive::SystemDesc;
-use amethyst::ecs::{Entity, Entities, Join, Read, ReadExpect, ReadStorage, System, SystemData, WriteExpect, WriteStorage};
+use amethyst::ecs::{Entity, Entities, Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteExpect, WriteStorage};
 use amethyst::ecs::shred::DefaultProvider;
 use amethyst::core::timing::Time;
 use amethyst::input::{InputHandler, StringBindings};
-use amethyst::renderer::{Camera, SpriteRender};
+use amethyst::renderer::{Camera, Hidden, SpriteRender};
 use amethyst_core::transform::components::Parent;
 use amethyst_window::ScreenDimensions;
 use winit::MouseButton;
 
-use crate::components::{Player, Pointer, ProposedMove, Loose};
-use crate::bountiful::{center_of_tile, POINTER_Z, TILE_WIDTH, TILE_HEIGHT, HOTBAR_CONTENTS_Z, HOTBAR_SLOTS};
-use nalgebra::{Point3, Vector2};
-use crate::resources::Hotbar;
+use crate::components::{Inventory, ItemStack, Player, Pointer, Position, ProposedMove, Loose};
+use crate::bountiful::{POINTER_Z, TILE_WIDTH, TILE_HEIGHT, GROUND_ITEMS_Z, HOTBAR_CONTENTS_Z, HOTBAR_SLOTS};
+use crate::generation::FLOOR;
+use nalgebra::{Point3, Vector2, Vector3};
+use crate::resources::{drop_on_slot, drop_target, slot_gui, slot_of, visible_slots, DropTarget, Editor, Hotbar, InventoryScreen, Items, MapManager, Point, Tile};
 
 #[derive(SystemDesc)]
 pub struct InputSystem {
     mouse_down: bool,
     dragged_item: Option<Entity>,
-    original_dragged_location: Option<(f32, f32)>,
+    split: Option<(usize, u32)>, // slot and how many when dragging part of a stack
 }
 
 impl Default for InputSystem {
@@ -26,13 +28,15 @@ impl Default for InputSystem {
         Self {
             mouse_down: false,
             dragged_item: None,
-            original_dragged_location: None,
+            split: None,
         }
     }
 }
 
 const VELOCITY: f32 = 200.0;
 const UNARM: usize = HOTBAR_SLOTS + 1;
+const PICKUP_DISTANCE: usize = 2; // in tiles from the player
+const DROP_DISTANCE: usize = 3;
 
 // Input can generate actions and moves.  Moves are proposed and collision system will decide
 // whether they can occur.
@@ -48,16 +52,26 @@ impl<'s> System<'s> for InputSystem {
         Read<'s, Time>,
         Read<'s, InputHandler<StringBindings>>,
         WriteExpect<'s, Hotbar>,
-        ReadStorage<'s, Loose>,
+        WriteStorage<'s, Loose>,
         Entities<'s>,
-        ReadStorage<'s, Parent>,
+        WriteStorage<'s, Parent>,
+        Read<'s, MapManager>,
+        Read<'s, Editor>,
+        WriteStorage<'s, Inventory>,
+        Write<'s, InventoryScreen>,
+        ReadStorage<'s, Hidden>,
+        WriteStorage<'s, ItemStack>,
+        WriteStorage<'s, Position>,
+        Option<Read<'s, Items>>, // not until the game starts
     );
 
     // FIXME: pointer should probably just be a resource?  There is only one
     fn run(&mut self, (mut moves, mut transforms, players, pointers, dimensions, mut renders,
-        cameras, time, input, mut hotbars, loose, entities, parents): Self::SystemData) {
+        cameras, time, input, mut hotbars, mut loose, entities, mut parents, manager, editor, mut inventories, mut screen,
+        hiddens, mut stacks, mut positions, items): Self::SystemData) {
         let mut pointer: Option<Point3<f32>> = None;
         let mut drag_check = false;
+        let mut clicked = false;
         let mut player_pos: (f32, f32) = (0., 0.);
         let mut player_entity: Option<Entity> = None;
 
@@ -69,20 +83,9 @@ impl<'s> System<'s> for InputSystem {
                 let shift = input.action_is_down("shift").unwrap_or(false);
                 let hotbar_selected = Self::process_hotbar_select(&input);
 
-                // FIXME: This is a tangle of state...
+                // InventorySystem highlights whichever slot ends up selected.
                 if let Some(index) = hotbar_selected {
-                    let selected = hotbars.selected;
-                    let unarm = index == UNARM && selected.is_some();
-                    let index = if unarm { selected.unwrap() } else { index };
-                    let gui = hotbars.contents.get(index).unwrap().hotbar_gui;
-                    renders.get_mut(gui).unwrap().sprite_number = 1;
-                    if let Some(selected_index) = hotbars.selected {
-                        if selected_index != index || unarm {
-                            let gui = hotbars.contents.get(selected_index).unwrap().hotbar_gui;
-                            renders.get_mut(gui).unwrap().sprite_number = 0;
-                        }
-                    }
-                    hotbars.selected = Some(index);
+                    hotbars.selected = if index == UNARM { None } else { Some(index) };
                 }
 
                 // FIXME: I think this should cancel mouse entered destination if one is in progress (if not stop)
@@ -94,13 +97,14 @@ impl<'s> System<'s> for InputSystem {
             // 1. Move character (left mouse click to destination)
             // 2. Select item to interact with (left mouse click with shift)
             // 3. Drag loose item (left mouse held down with drag)
+            // 4. Split a stack (right mouse or shift held down with drag)
             if let Some((x, y)) = input.mouse_position() {
                 pointer = Some(camera
                     .projection()
                     .screen_to_world_point(Point3::new(x, y, 0.),
                                            Vector2::new(dimensions.width(), dimensions.height()),
                                            camera_transform));
-                let mouse_down = input.mouse_button_is_down(MouseButton::Left);
+                let mouse_down = input.mouse_button_is_down(MouseButton::Left) || input.mouse_button_is_down(MouseButton::Right);
 
                 if self.mouse_down {
                     if !mouse_down { // mouse button raised
@@ -110,25 +114,61 @@ impl<'s> System<'s> for InputSystem {
                     }
                 } else {            // mouse button pressed
                     self.mouse_down = mouse_down;
+                    clicked = mouse_down;
                 }
             }
         }
 
-        // FIXME: Add inventory where picked up items get stuffed.
-        // FIXME: on dragging to map it will drop next to or worst case where player is and remove Loose from the item.
-        // FIXME: on shift-click an item on map will be picked up.
+        // Shift-click picks up an item lying on the map if the player is close enough.  Items
+        // on the map are Loose with a Position instead of a Parent.
+        if let (Some(pos), Some(player), Some(items)) = (pointer, player_entity, &items) {
+            if clicked && input.action_is_down("shift").unwrap_or(false) && !editor.active {
+                let target = manager.info().and_then(|info| info.point_of(pos.x, pos.y));
+                let player_loc = positions.get(player).map(|position| position.loc);
+                let reachable = |loc: &Point| match (manager.map(), player_loc) {
+                    (Some(nav), Some(player_loc)) => nav.distance(&player_loc, loc) <= PICKUP_DISTANCE,
+                    _ => false,
+                };
+                let item = (&loose, &entities, &positions, &stacks, !&parents).join()
+                    .find(|(_, _, position, _, _)| Some(position.loc) == target && manager.current.as_ref() == Some(&position.map_id))
+                    .filter(|(_, _, position, _, _)| reachable(&position.loc))
+                    .map(|(_, entity, _, stack, _)| (entity, stack.clone()));
+
+                if let (Some((item, stack)), Some(inventory)) = (item, inventories.get_mut(player)) {
+                    let max_stack = items.get(&stack.id).map_or(1, |item| item.max_stack);
+
+                    match inventory.add(stack, max_stack) {
+                        Ok(slot) => {
+                            stacks.remove(item);
+                            let gui = slot_gui(&mut hotbars, &mut screen, slot).unwrap();
+                            if gui.contents.is_none() { // has a slot of its own so carry it
+                                positions.remove(item);
+                                parents.insert(item, Parent { entity: player }).unwrap();
+                                gui.contents = Some(item);
+                            } else {                    // all went onto existing stacks
+                                entities.delete(item).unwrap();
+                            }
+                        },
+                        Err(rest) => {                  // whatever did not fit stays put
+                            stacks.insert(item, rest).unwrap();
+                        },
+                    }
+                }
+            }
+        }
 
         // Handle dragging items around between hotbar, iventory, and dropping on the ground.
         // All loose items in hotbar and inventory aer children of the Player (via Parent).
         // This means all their locations are relative to the location of the Player.
         if let Some(pos) = pointer {
-            if drag_check {
+            if drag_check && !editor.active { // clicks belong to the editor while it is open
                 if let Some(entity) = &self.dragged_item {
                     let transform = transforms.get_mut(*entity).unwrap();
                     transform.set_translation_xyz(pos.x - player_pos.0, pos.y - player_pos.1, HOTBAR_CONTENTS_Z);
                 } else {
                     // Items placed in hotbar or invenctories are in transforms relative to parent and not map.
-                    for (_loose, parent, entity, transform) in (&loose, &parents, &entities, &transforms).join() {
+                    let mut grabbed = None;
+                    for (_loose, parent, entity, transform, _) in (&loose, &parents, &entities, &transforms, !&hiddens).join() {
                         // Safe-guard against use of Parent for more than just the player.
                         if player_entity.unwrap() != parent.entity {
                             continue;
@@ -138,39 +178,109 @@ impl<'s> System<'s> for InputSystem {
                         let (ai, aj) = (player_pos.0 + i, player_pos.1 + j);
                         //println!("drag check items loose: {},{}. item: {},{}. player: {},{}. adj: {},{}.", pos.x, pos.y, i, j, player_pos.0, player_pos.1, ai, aj);
                         if (pos.x - ai).abs() <= TILE_WIDTH && (pos.y - aj).abs() <= TILE_HEIGHT {
-                            self.dragged_item = Some(entity);
-                            self.original_dragged_location = Some((i, j));
+                            grabbed = Some(entity);
                             break;
                         }
                     }
+
+                    // Splitting drags a new entity for half of the stack.  The stack itself is
+                    // only changed once it is dropped somewhere.
+                    let splitting = input.mouse_button_is_down(MouseButton::Right) || input.action_is_down("shift").unwrap_or(false);
+                    let slot = grabbed.and_then(|item| slot_of(&hotbars, &screen, item));
+                    let half = slot.and_then(|slot| player_entity.and_then(|player| inventories.get(player))
+                        .and_then(|inventory| inventory.get(slot))
+                        .map(|stack| stack.quantity / 2));
+                    self.dragged_item = match (grabbed, slot, half) {
+                        (Some(item), Some(slot), Some(half)) if splitting && half > 0 => {
+                            self.split = Some((slot, half));
+                            let render = renders.get(item).cloned().unwrap();
+                            let transform = transforms.get(item).cloned().unwrap();
+                            Some(entities.build_entity()
+                                .with(render, &mut renders)
+                                .with(Loose {}, &mut loose)
+                                .with(Parent { entity: player_entity.unwrap() }, &mut parents)
+                                .with(transform, &mut transforms)
+                                .build())
+                        },
+                        _ => grabbed,
+                    };
+                    screen.dragged = self.dragged_item;
                 }
             } else { // highlight tile
-                // Drop item somewhere or return it to where it was.
+                // Drop item into a slot, merging with the same item or swapping with whatever else
+                // was there.  Outside of the slots it goes onto the map near the player.  Anywhere else and it goes back to its
+                // slot since InventorySystem puts items where their slot is.
                 if let Some(item) = self.dragged_item {
-                    let item_transform = transforms.get(item).unwrap();
-                    let item_translation = item_transform.translation();
-                    let mut found_loc: Option<(f32, f32)> = None;
-                    for hotbar in &hotbars.contents {
-                        let slot_translation = transforms.get(hotbar.hotbar_gui).unwrap().translation();
-
-                        if (item_translation.x - slot_translation.x).abs() <= TILE_WIDTH / 2. &&
-                            (item_translation.y - slot_translation.y).abs() <= TILE_HEIGHT / 2. {
-                            found_loc = Some((slot_translation.x, slot_translation.y));
-                            break;
-                        }
-                    }
+                    // InventorySystem leaves the dragged item wherever the pointer last put it.
+                    let item_translation = *transforms.get(item).unwrap().translation();
+                    let slots: Vec<(usize, Vector3<f32>)> = visible_slots(&hotbars, &screen).into_iter()
+                        .map(|(index, gui)| (index, *transforms.get(gui).unwrap().translation()))
+                        .collect();
+                    let target = drop_target(&item_translation, &slots);
+
+                    let split = self.split.take();
+                    let from = split.map(|(from, _)| from).or_else(|| slot_of(&hotbars, &screen, item));
+                    let quantity = split.map_or(std::u32::MAX, |(_, quantity)| quantity);
+                    let mut placed = false; // whether a split off entity found a home
+                    let player_loc = player_entity.and_then(|player| positions.get(player)).map(|position| position.loc);
+                    let inventory = player_entity.and_then(|player| inventories.get_mut(player));
+                    match (from, target, inventory) {
+                        (Some(from), DropTarget::Slot(to), Some(inventory)) => {
+                            let max_stack = items.as_ref()
+                                .and_then(|items| inventory.get(from).and_then(|stack| items.get(&stack.id)))
+                                .map_or(1, |item| item.max_stack);
+                            let (home, emptied) = drop_on_slot(inventory, &mut hotbars, &mut screen, item, (from, to),
+                                                               split.map(|(_, quantity)| quantity), max_stack);
+                            placed = home;
+                            if let Some(emptied) = emptied {
+                                entities.delete(emptied).unwrap();
+                            }
+                        },
+                        (Some(from), DropTarget::Map, Some(inventory)) => {
+                            let occupied: HashSet<Point> = (&loose, &positions, !&parents).join()
+                                .filter(|(_, position, _)| manager.current.as_ref() == Some(&position.map_id))
+                                .map(|(_, position, _)| position.loc)
+                                .collect();
 
-                    // return to where it came from
-                    let loc = found_loc.or_else(|| Some(self.original_dragged_location.unwrap())).unwrap();
-                    let item_transform = transforms.get_mut(item).unwrap();
-                    item_transform.set_translation_xyz(loc.0, loc.1, HOTBAR_CONTENTS_Z);
+                            if let (Some(info), Some(nav), Some(player_loc)) = (manager.info(), manager.map(), player_loc) {
+                                // Next to the player or worst case right where they stand.  Only somewhere
+                                // they could walk to so nothing lands on the other side of a fence.
+                                let passable = |_: &Point, tile: &Tile| tile.id == FLOOR;
+                                let free = |loc: &Point, tile: &Tile| passable(loc, tile) && !occupied.contains(loc);
+                                let spot = nav.nearest_reachable(&player_loc, DROP_DISTANCE, passable, |loc, tile| *loc != player_loc && free(loc, tile))
+                                    .or_else(|| nav.nearest_reachable(&player_loc, 0, passable, |loc, tile| free(loc, tile)))
+                                    .map(|(loc, _)| loc);
 
+                                if let (Some(loc), Some(stack)) = (spot, inventory.take_some(from, quantity)) {
+                                    if inventory.get(from).is_none() {
+                                        slot_gui(&mut hotbars, &mut screen, from).unwrap().contents = None;
+                                    }
+                                    parents.remove(item);
+                                    positions.insert(item, Position::new(info.id.clone(), loc)).unwrap();
+                                    stacks.insert(item, stack).unwrap();
+                                    transforms.get_mut(item).unwrap().set_translation(info.world_of(&loc, GROUND_ITEMS_Z));
+                                    placed = true;
+                                }
+                            }
+                        },
+                        _ => {},
+                    }
+
+                    if split.is_some() && !placed {
+                        entities.delete(item).unwrap();
+                    }
                 }
                 self.dragged_item = None;
+                screen.dragged = None;
 
+                // Pointer stays put while the mouse is off the map.
+                let tile = manager.info()
+                    .and_then(|info| info.point_of(pos.x, pos.y).map(|loc| info.world_of(&loc, POINTER_Z)));
                 for (_pointer, render, transform) in (&pointers, &mut renders, &mut transforms).join() {
                     render.sprite_number = if self.mouse_down { 1 } else { 0 };
-                    transform.set_translation(center_of_tile(&pos, Some(POINTER_Z)));
+                    if let Some(tile) = tile {
+                        transform.set_translation(tile);
+                    }
                 }
             }
         }
@@ -0,0 +1,117 @@
+use amethyst::core::Transform;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteStorage};
+use amethyst::input::{InputHandler, StringBindings};
+use amethyst::renderer::{Camera, Hidden, SpriteRender};
+use amethyst_imgui::{
+    imgui,
+    imgui::im_str,
+};
+use amethyst_window::ScreenDimensions;
+use nalgebra::{Point3, Vector2};
+
+use crate::bountiful::{HOTBAR_CONTENTS_Z, TILE_HEIGHT, TILE_WIDTH};
+use crate::components::{Inventory, Player};
+use crate::resources::{Hotbar, InventoryScreen};
+
+/// Opens and closes the inventory screen and makes the slots look like what `Hotbar` and
+/// `InventoryScreen` say is in them.  Closed, the screen's slots and their items are hidden.
+/// Stacks of more than one get their quantity drawn in the corner of their slot.
+#[derive(SystemDesc, Default)]
+pub struct InventorySystem {
+    key_down: bool,
+}
+
+impl<'s> System<'s> for InventorySystem {
+    type SystemData = (
+        Read<'s, InputHandler<StringBindings>>,
+        ReadExpect<'s, Hotbar>,
+        Write<'s, InventoryScreen>,
+        WriteStorage<'s, Hidden>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Inventory>,
+        ReadStorage<'s, Camera>,
+        ReadExpect<'s, ScreenDimensions>,
+    );
+
+    fn run(&mut self, (input, hotbar, mut screen, mut hiddens, mut transforms, mut renders, players, inventories,
+        cameras, dimensions): Self::SystemData) {
+        let key_down = input.action_is_down("inventory").unwrap_or(false);
+        if key_down && !self.key_down {
+            screen.open = !screen.open;
+        }
+        self.key_down = key_down;
+
+        for (index, slot) in hotbar.contents.iter().enumerate() {
+            if let Some(render) = renders.get_mut(slot.hotbar_gui) {
+                render.sprite_number = if hotbar.selected == Some(index) { 1 } else { 0 };
+            }
+        }
+
+        let inventory = (&players, &inventories).join().map(|(_, inventory)| inventory).next();
+        let camera = (&cameras, &transforms).join().map(|(camera, transform)| (camera.clone(), transform.clone())).next();
+        let mut badges = vec![];
+
+        let shown = hotbar.contents.iter().map(|slot| (slot, true))
+            .chain(screen.slots.iter().map(|slot| (slot, screen.open)));
+        for (index, (slot, visible)) in shown.enumerate() {
+            set_hidden(&mut hiddens, slot.hotbar_gui, !visible);
+
+            if let Some(item) = slot.contents {
+                set_hidden(&mut hiddens, item, !visible);
+
+                let quantity = inventory.and_then(|inventory| inventory.get(index)).map_or(0, |stack| stack.quantity);
+                if visible && quantity > 1 {
+                    if let (Some((camera, camera_transform)), Some(transform)) = (&camera, transforms.get(slot.hotbar_gui)) {
+                        // Bottom right corner of the slot in screen coordinates.
+                        let matrix = transform.global_matrix();
+                        let corner = Point3::new(matrix[(0, 3)] + TILE_WIDTH / 2., matrix[(1, 3)] - TILE_HEIGHT / 2., 0.);
+                        let dims = Vector2::new(dimensions.width(), dimensions.height());
+                        let screen_corner = camera.projection().world_to_screen(corner, dims, camera_transform);
+                        badges.push((index, [screen_corner.x, screen_corner.y], quantity));
+                    }
+                }
+
+                // Items are children of the player like the slots so they can share translations.
+                // The dragged one follows the pointer until InputSystem drops it somewhere.
+                if screen.dragged == Some(item) {
+                    continue;
+                }
+                let slot_translation = transforms.get(slot.hotbar_gui).map(|transform| *transform.translation());
+                if let (Some(translation), Some(transform)) = (slot_translation, transforms.get_mut(item)) {
+                    transform.set_translation_xyz(translation.x, translation.y, HOTBAR_CONTENTS_Z);
+                }
+            }
+        }
+
+        if !badges.is_empty() {
+            amethyst_imgui::with(|ui| {
+                for (index, position, quantity) in badges {
+                    let title = im_str!("##quantity{}", index);
+                    imgui::Window::new(&title)
+                        .bg_alpha(0.)
+                        .no_decoration()
+                        .no_inputs()
+                        .always_auto_resize(true)
+                        .save_settings(false)
+                        .focus_on_appearing(false)
+                        .no_nav()
+                        .position(position, imgui::Condition::Always)
+                        .position_pivot([1., 1.])
+                        .build(ui, || ui.text(quantity.to_string()));
+                }
+            });
+        }
+    }
+}
+
+// Only touch Hidden when it changes so nothing gets flagged every frame.
+fn set_hidden(hiddens: &mut WriteStorage<'_, Hidden>, entity: Entity, hidden: bool) {
+    if hidden && !hiddens.contains(entity) {
+        hiddens.insert(entity, Hidden).unwrap();
+    } else if !hidden && hiddens.contains(entity) {
+        hiddens.remove(entity);
+    }
+}
@@ -0,0 +1,25 @@
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Read, System, SystemData, WriteStorage};
+use amethyst::ui::UiImage;
+
+use crate::resources::{GameClock, Lighting};
+
+/// Colors the overlay in `Lighting` for the time of day.
+#[derive(SystemDesc)]
+pub struct LightingSystem;
+
+impl<'s> System<'s> for LightingSystem {
+    type SystemData = (
+        Read<'s, GameClock>,
+        Read<'s, Lighting>,
+        WriteStorage<'s, UiImage>,
+    );
+
+    fn run(&mut self, (clock, lighting, mut images): Self::SystemData) {
+        if let Some(overlay) = lighting.overlay {
+            if let Some(UiImage::SolidColor(color)) = images.get_mut(overlay) {
+                *color = clock.tint();
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{System, SystemData, Write};
+use amethyst::shrev::EventChannel;
+
+use crate::resources::{MapChange, MapManager};
+
+/// Turns tiles changed in the current `Map` (by `set_at`, undo or redo) into `MapChange`
+/// events so drawing and collisions can catch up without polling the map.
+#[derive(SystemDesc)]
+pub struct MapChangeSystem;
+
+impl<'s> System<'s> for MapChangeSystem {
+    type SystemData = (
+        Write<'s, MapManager>,
+        Write<'s, EventChannel<MapChange>>,
+    );
+
+    fn run(&mut self, (mut manager, mut events): Self::SystemData) {
+        let map_id = match manager.current.clone() {
+            Some(map_id) => map_id,
+            None => return,
+        };
+        let changes = match manager.map_mut() {
+            Some(map) => map.take_changes(),
+            None => return,
+        };
+
+        events.iter_write(changes.into_iter().map(|loc| MapChange { map_id: map_id.clone(), loc }));
+    }
+}
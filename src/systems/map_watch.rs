@@ -0,0 +1,34 @@
+use amethyst::core::timing::Time;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Read, System, SystemData, Write};
+use std::path::Path;
+
+use crate::bountiful::TILESET_PATH;
+use crate::map_loader::tmx_path;
+use crate::resources::{FileWatcher, MapManager, Transition};
+
+/// Notices the current map (or the tileset) being saved from tiled while the game runs and
+/// asks for it to be read again.  The `Bountiful` state does the reload since it needs the
+/// whole world.  Systems keep running while the `MapEditor` is on top so this does too.
+#[derive(SystemDesc)]
+pub struct MapWatchSystem;
+
+impl<'s> System<'s> for MapWatchSystem {
+    type SystemData = (
+        Write<'s, FileWatcher>,
+        Write<'s, MapManager>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (mut watcher, mut manager, time): Self::SystemData) {
+        let map_path = match &manager.current {
+            Some(map_id) if manager.transition == Transition::None => tmx_path(map_id),
+            _ => return, // nothing loaded yet or in the middle of a warp
+        };
+
+        watcher.watch_only(&[&map_path, Path::new(TILESET_PATH)]);
+        if !watcher.poll(time.delta_seconds()).is_empty() {
+            manager.reload = true;
+        }
+    }
+}
@@ -1,7 +1,35 @@
+pub mod auto_tile;
+pub mod chunk_streaming;
+pub mod clock;
 pub mod collision;
 pub mod debug;
+pub mod depth_sort;
+pub mod editor;
+pub mod growth;
+pub mod hud;
 pub mod input;
+pub mod inventory;
+pub mod lighting;
+pub mod map_change;
+pub mod map_watch;
+pub mod tile_animation;
+pub mod tool;
+pub mod warp;
 
-pub use self::collision::CollisionSystem;
+pub use self::auto_tile::{AutoTileSystem, AutoTileSystemDesc};
+pub use self::chunk_streaming::ChunkStreamingSystem;
+pub use self::clock::ClockSystem;
+pub use self::collision::{CollisionSystem, CollisionSystemDesc};
 pub use self::debug::DebugSystem;
+pub use self::depth_sort::DepthSortSystem;
+pub use self::editor::EditorSystem;
+pub use self::growth::{GrowthSystem, GrowthSystemDesc};
+pub use self::hud::HudSystem;
 pub use self::input::InputSystem;
+pub use self::inventory::InventorySystem;
+pub use self::lighting::LightingSystem;
+pub use self::map_change::MapChangeSystem;
+pub use self::map_watch::MapWatchSystem;
+pub use self::tile_animation::TileAnimationSystem;
+pub use self::tool::ToolSystem;
+pub use self::warp::WarpSystem;
@@ -0,0 +1,27 @@
+use amethyst::core::timing::Time;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Join, Read, ReadStorage, System, SystemData, WriteStorage};
+use amethyst::renderer::SpriteRender;
+
+use crate::components::TileAnimation;
+
+/// Advances animated map tiles.  Every tile reads the same clock so tiles sharing
+/// an animation never drift apart.
+#[derive(SystemDesc)]
+pub struct TileAnimationSystem;
+
+impl<'s> System<'s> for TileAnimationSystem {
+    type SystemData = (
+        ReadStorage<'s, TileAnimation>,
+        WriteStorage<'s, SpriteRender>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (animations, mut renders, time): Self::SystemData) {
+        let millis = time.absolute_time().as_millis() as u64;
+
+        for (animation, render) in (&animations, &mut renders).join() {
+            render.sprite_number = animation.sprite_at(millis);
+        }
+    }
+}
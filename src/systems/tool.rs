@@ -0,0 +1,284 @@
+use amethyst::core::{Time, Transform};
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, Write, WriteExpect, WriteStorage};
+use amethyst::input::{InputHandler, StringBindings};
+use amethyst::renderer::{Camera, SpriteRender};
+use amethyst_core::transform::components::Parent;
+use amethyst_window::ScreenDimensions;
+use nalgebra::{Point3, Vector2};
+use winit::MouseButton;
+
+use crate::bountiful::{GROUND_ITEMS_Z, MAP_LAYERS_Z, SWING_Z, TILE_HEIGHT, TILE_WIDTH};
+use crate::components::{Crop, DepthSort, Inventory, ItemStack, Loose, Player, Position};
+use crate::generation::FLOOR;
+use crate::map_loader::GROUND_GIDS;
+use crate::resources::{slot_gui, visible_slots, Editor, Farm, Hit, Hotbar, InventoryScreen, Items, MapManager, Mining,
+                       Tile, TileUpdate, ToolKind, WorldChunks, SOIL_GID, WET_SOIL_GID};
+use crate::systems::auto_tile::apply_tile_updates;
+
+/// How far away and how often things can be planted or harvested by hand.
+pub const HAND_REACH: usize = 1;
+pub const HAND_COOLDOWN: f32 = 0.25;
+
+// A tool turning through its swing angles.
+struct Swing {
+    entity: Entity,
+    angles: Vec<f32>,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Uses the item in the selected hotbar slot on the tile under the pointer while the left
+/// mouse button is held.  How far it reaches, how often it can be used and how it swings
+/// all come from the item catalogue.  Tools mine and farm, seeds get planted and a ready
+/// crop is harvested whatever is in hand.
+#[derive(SystemDesc, Default)]
+pub struct ToolSystem {
+    using: bool, // mouse went down on the map and not on a slot
+    cooldown: f32,
+    swing: Option<Swing>,
+}
+
+impl<'s> System<'s> for ToolSystem {
+    type SystemData = (
+        Read<'s, InputHandler<StringBindings>>,
+        Read<'s, Time>,
+        WriteExpect<'s, Hotbar>,
+        Write<'s, InventoryScreen>,
+        Option<Read<'s, Items>>, // not until the game starts
+        Read<'s, Editor>,
+        Write<'s, MapManager>,
+        Write<'s, WorldChunks>,
+        Write<'s, Mining>,
+        Write<'s, Farm>,
+        ReadStorage<'s, Player>,
+        WriteStorage<'s, Inventory>,
+        ReadStorage<'s, Camera>,
+        ReadExpect<'s, ScreenDimensions>,
+        Entities<'s>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, SpriteRender>,
+        WriteStorage<'s, Loose>,
+        WriteStorage<'s, ItemStack>,
+        WriteStorage<'s, Position>,
+        WriteStorage<'s, Parent>,
+        WriteStorage<'s, Crop>,
+        WriteStorage<'s, DepthSort>,
+    );
+
+    fn run(&mut self, (input, time, mut hotbar, mut screen, items, editor, mut manager, mut chunks, mut mining,
+        mut farm, players, mut inventories, cameras, dimensions, entities, mut transforms, mut renders, mut loose,
+        mut stacks, mut positions, mut parents, mut crops, mut sorts): Self::SystemData) {
+        self.cooldown = (self.cooldown - time.delta_seconds()).max(0.);
+        self.animate_swing(&entities, &mut transforms, time.delta_seconds());
+
+        let items = match items {
+            Some(items) => items,
+            None => return,
+        };
+        let pos = input.mouse_position().and_then(|(x, y)| {
+            let (camera, camera_transform) = (&cameras, &transforms).join().next()?;
+            Some(camera.projection().screen_to_world_point(Point3::new(x, y, 0.),
+                                                           Vector2::new(dimensions.width(), dimensions.height()),
+                                                           camera_transform))
+        });
+        let mouse_down = input.mouse_button_is_down(MouseButton::Left);
+        let shift = input.action_is_down("shift").unwrap_or(false);
+
+        // Presses on a slot are for dragging items and shift-clicks pick things up.
+        if !mouse_down {
+            self.using = false;
+        } else if !self.using {
+            let over_slot = pos.map_or(true, |pos| visible_slots(&hotbar, &screen).iter()
+                .filter_map(|(_, gui)| transforms.get(*gui))
+                .map(|transform| transform.global_matrix())
+                .any(|matrix| (pos.x - matrix[(0, 3)]).abs() <= TILE_WIDTH / 2. && (pos.y - matrix[(1, 3)]).abs() <= TILE_HEIGHT / 2.));
+            self.using = !over_slot && !shift && !editor.active;
+        }
+
+        if !self.using || self.cooldown > 0. {
+            return;
+        }
+
+        let player = match players.join().next() {
+            Some(player) => player.entity,
+            None => return,
+        };
+        let selected = hotbar.selected
+            .and_then(|slot| inventories.get(player).and_then(|inventory| inventory.get(slot)).map(|stack| (slot, stack)))
+            .and_then(|(slot, stack)| items.get(&stack.id).map(|item| (slot, item)));
+        let (reach, cooldown) = match selected.and_then(|(_, item)| item.tool_use.as_ref()) {
+            Some(tool_use) => (tool_use.reach, tool_use.cooldown),
+            None => (HAND_REACH, HAND_COOLDOWN),
+        };
+        let (info, nav) = match (manager.info(), manager.map()) {
+            (Some(info), Some(nav)) => (info, nav),
+            _ => return,
+        };
+        let target = match (pos.and_then(|pos| info.point_of(pos.x, pos.y)), positions.get(player)) {
+            (Some(target), Some(position)) if nav.distance(&position.loc, &target) <= reach => target,
+            _ => return,
+        };
+        let (map_id, target_translation) = (info.id.clone(), info.world_of(&target, SWING_Z));
+        let drop_translation = info.world_of(&target, GROUND_ITEMS_Z);
+        let (crop_translation, crop_foot) = (info.world_of(&target, MAP_LAYERS_Z[2]), info.tile_height / 2.);
+        let crop = (&entities, &crops, &positions).join()
+            .find(|(_, _, position)| position.map_id == map_id && position.loc == target)
+            .map(|(entity, crop, _)| (entity, crop.clone()));
+
+        // Ready crops come out of the ground whatever is in hand.
+        let harvest = crop.as_ref().and_then(|(entity, crop)| items.get(&crop.seed)
+            .and_then(|seed| seed.growth.as_ref())
+            .filter(|growth| crop.ready(growth))
+            .and_then(|growth| items.get(&growth.harvest).map(|item| (*entity, item, growth.amount))));
+        if let Some((entity, item, amount)) = harvest {
+            self.cooldown = HAND_COOLDOWN;
+            entities.delete(entity).unwrap();
+
+            let render = SpriteRender { sprite_sheet: items.textures.clone(), sprite_number: item.sprite };
+            match inventories.get_mut(player).unwrap().add(ItemStack::new(&item.id, amount), item.max_stack) {
+                Ok(slot) => {
+                    let gui = slot_gui(&mut hotbar, &mut screen, slot).unwrap();
+                    if gui.contents.is_none() { // InventorySystem puts it where the slot is
+                        gui.contents = Some(entities.build_entity()
+                            .with(render, &mut renders)
+                            .with(Loose {}, &mut loose)
+                            .with(Parent { entity: player }, &mut parents)
+                            .with(Transform::default(), &mut transforms)
+                            .build());
+                    }
+                },
+                Err(rest) => { // no room so it stays on the ground
+                    let mut transform = Transform::default();
+                    transform.set_translation(drop_translation);
+                    entities.build_entity()
+                        .with(transform, &mut transforms)
+                        .with(render, &mut renders)
+                        .with(Loose {}, &mut loose)
+                        .with(rest, &mut stacks)
+                        .with(Position::new(map_id, target), &mut positions)
+                        .build();
+                },
+            }
+            return;
+        }
+
+        let (slot, item) = match selected {
+            Some(selected) => selected,
+            None => return,
+        };
+        self.cooldown = cooldown;
+
+        // Seeds go into tilled soil with nothing growing in it yet.
+        if let Some(growth) = &item.growth {
+            if crop.is_some() || farm.plot(&map_id, &target).is_none() {
+                return;
+            }
+
+            let planted = Crop::new(&item.id);
+            let mut transform = Transform::default();
+            transform.set_translation(crop_translation);
+            entities.build_entity()
+                .with(transform, &mut transforms)
+                .with(SpriteRender { sprite_sheet: items.textures.clone(), sprite_number: planted.sprite(growth) }, &mut renders)
+                .with(DepthSort::new(crop_foot), &mut sorts)
+                .with(Position::new(map_id, target), &mut positions)
+                .with(planted, &mut crops)
+                .build();
+
+            let inventory = inventories.get_mut(player).unwrap();
+            inventory.take_some(slot, 1);
+            if inventory.get(slot).is_none() {
+                if let Some(seeds) = slot_gui(&mut hotbar, &mut screen, slot).and_then(|gui| gui.contents.take()) {
+                    entities.delete(seeds).unwrap();
+                }
+            }
+            return;
+        }
+
+        let (kind, tool_use) = match (item.tool, &item.tool_use) {
+            (Some(kind), Some(tool_use)) => (kind, tool_use),
+            _ => return,
+        };
+
+        if let Some(swing) = self.swing.take() {
+            entities.delete(swing.entity).unwrap();
+        }
+        if !tool_use.swing.is_empty() {
+            let mut transform = Transform::default();
+            transform.set_translation(target_translation);
+            let entity = entities.build_entity()
+                .with(transform, &mut transforms)
+                .with(SpriteRender { sprite_sheet: items.textures.clone(), sprite_number: item.sprite }, &mut renders)
+                .build();
+            self.swing = Some(Swing { entity, angles: tool_use.swing.clone(), elapsed: 0., duration: tool_use.cooldown });
+        }
+
+        let gid = |layer: usize| manager.map().map_or(0, |map| map.gid(layer, &target));
+        match kind {
+            ToolKind::PickAxe => {
+                if let Hit::Broken(drop) = mining.hit(&map_id, &target, gid(1), tool_use.power) {
+                    // Gone from the solids layer, no longer blocking and auto tiling fixes up
+                    // the fences around it.
+                    if let Some(map) = manager.map_mut() {
+                        map.set_gid(1, &target, 0);
+                        map.set_at(&target, Tile::new(FLOOR, 1)).unwrap();
+                    }
+                    if let Some(solid) = chunks.take_tile(&target, 1) {
+                        entities.delete(solid).unwrap();
+                    }
+
+                    if let Some(drop) = drop.as_ref().and_then(|id| items.get(id)) {
+                        let mut transform = Transform::default();
+                        transform.set_translation(drop_translation);
+                        entities.build_entity()
+                            .with(transform, &mut transforms)
+                            .with(SpriteRender { sprite_sheet: items.textures.clone(), sprite_number: drop.sprite }, &mut renders)
+                            .with(Loose {}, &mut loose)
+                            .with(ItemStack::new(&drop.id, 1), &mut stacks)
+                            .with(Position::new(map_id, target), &mut positions)
+                            .build();
+                    }
+                }
+            },
+            ToolKind::Hoe => {
+                // Only plain ground with nothing standing on it.
+                let ground = gid(0);
+                if ground >= GROUND_GIDS.0 && ground <= GROUND_GIDS.1 && gid(1) == 0 && gid(2) == 0 && farm.till(&map_id, &target) {
+                    let update = TileUpdate { loc: target, layer: 0, gid: SOIL_GID };
+                    apply_tile_updates(&mut chunks, manager.map_mut().unwrap(), vec![update], &mut renders);
+                }
+            },
+            ToolKind::WateringCan => {
+                if farm.water(&map_id, &target) {
+                    let update = TileUpdate { loc: target, layer: 0, gid: WET_SOIL_GID };
+                    apply_tile_updates(&mut chunks, manager.map_mut().unwrap(), vec![update], &mut renders);
+                }
+            },
+        }
+    }
+}
+
+impl ToolSystem {
+    fn animate_swing(&mut self, entities: &Entities<'_>, transforms: &mut WriteStorage<'_, Transform>, delta_seconds: f32) {
+        let done = match &mut self.swing {
+            Some(swing) => {
+                swing.elapsed += delta_seconds;
+                let frame = (swing.elapsed / swing.duration * swing.angles.len() as f32) as usize;
+                match (swing.angles.get(frame), transforms.get_mut(swing.entity)) {
+                    (Some(angle), Some(transform)) => {
+                        transform.set_rotation_2d(angle.to_radians());
+                        false
+                    },
+                    _ => true,
+                }
+            },
+            None => false,
+        };
+
+        if done {
+            let swing = self.swing.take().unwrap();
+            entities.delete(swing.entity).unwrap();
+        }
+    }
+}
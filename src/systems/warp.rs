@@ -0,0 +1,71 @@
+use amethyst::core::Transform;
+use amethyst::core::timing::Time;
+use amethyst::derive::SystemDesc;
+use amethyst::ecs::{Join, Read, ReadStorage, System, SystemData, Write, WriteStorage};
+use amethyst::ui::UiImage;
+
+use crate::components::{Player, Position};
+use crate::resources::{MapManager, Transition, FADE_SECONDS};
+
+/// Keeps the player's `Position` up to date and starts a warp when the player steps
+/// onto a warp tile.  The actual map swap happens in the `Bountiful` state once the
+/// screen has faded out since it needs the whole world to load the next map.
+#[derive(SystemDesc)]
+pub struct WarpSystem;
+
+impl<'s> System<'s> for WarpSystem {
+    type SystemData = (
+        ReadStorage<'s, Player>,
+        ReadStorage<'s, Transform>,
+        WriteStorage<'s, Position>,
+        WriteStorage<'s, UiImage>,
+        Write<'s, MapManager>,
+        Read<'s, Time>,
+    );
+
+    fn run(&mut self, (players, transforms, mut positions, mut images, mut manager, time): Self::SystemData) {
+        let step = time.delta_seconds() / FADE_SECONDS;
+
+        match manager.transition {
+            Transition::None => {
+                let mut warp = None;
+
+                if let Some(info) = manager.info() {
+                    for (_player, transform, position) in (&players, &transforms, &mut positions).join() {
+                        let translation = transform.translation();
+                        if let Some(loc) = info.point_of(translation.x, translation.y) {
+                            // Only warp when stepping onto the tile so arriving on one does not bounce us back.
+                            if loc != position.loc {
+                                position.loc = loc;
+                                warp = info.warps.get(&loc).cloned();
+                            }
+                        }
+                    }
+                }
+
+                if let Some(warp) = warp {
+                    manager.start_warp(warp);
+                }
+            },
+            Transition::FadeOut => {
+                manager.fade = (manager.fade + step).min(1.);
+                if manager.fade >= 1. {
+                    manager.transition = Transition::Swap;
+                }
+            },
+            Transition::Swap => (),
+            Transition::FadeIn => {
+                manager.fade = (manager.fade - step).max(0.);
+                if manager.fade <= 0. {
+                    manager.transition = Transition::None;
+                }
+            },
+        }
+
+        if let Some(overlay) = manager.fade_overlay {
+            if let Some(UiImage::SolidColor(color)) = images.get_mut(overlay) {
+                color[3] = manager.fade;
+            }
+        }
+    }
+}
@@ -2,19 +2,74 @@ use amethyst::{
     ecs::prelude::Entity,
     input::{is_close_requested, is_key_down},
     prelude::{GameData, SimpleState, SimpleTrans, StateData, StateEvent, Trans, WorldExt},
-    ui::UiCreator,
+    ui::{UiCreator, UiFinder, UiText},
     winit::VirtualKeyCode,
 };
 
+use crate::options::Options;
+use crate::save_game::{list_slots, SAVE_SLOTS};
+
+const SLOT_KEYS: [VirtualKeyCode; SAVE_SLOTS] = [VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3];
+
+/// Splash screen listing the save slots.  A number key continues (or starts) the game in
+/// that slot and space starts a new game in the first empty one.
 #[derive(Default, Debug)]
 pub struct WelcomeScreen {
     splash_screen: Option<Entity>,
+    lines: Vec<String>, // first is for a new game then one per slot
+    playable: Vec<usize>, // slots which can be picked
+    new_slot: Option<usize>,
+    shown: bool,
 }
 
 impl SimpleState for WelcomeScreen {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         self.splash_screen =
             Some(data.world.exec(|mut creator: UiCreator<'_>| creator.create("ui/welcome.ron", ())));
+
+        let slots = list_slots();
+        self.new_slot = slots.iter().find(|(_, save)| matches!(save, Ok(None))).map(|(slot, _)| *slot);
+        self.lines = vec![match self.new_slot {
+            Some(slot) => format!("Space: new game in slot {}", slot),
+            None => "Space: new game (every slot is used so it will not be saved)".to_string(),
+        }];
+        for (slot, save) in slots {
+            self.lines.push(match save {
+                Ok(Some(save)) => format!("{}: {}", slot, save.summary()),
+                Ok(None) => format!("{}: empty", slot),
+                Err(e) => format!("{}: unreadable ({})", slot, e),
+            });
+            if save.is_ok() {
+                self.playable.push(slot);
+            }
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        // The labels only exist once the ui file has been loaded.
+        if !self.shown {
+            let labels: Vec<Option<Entity>> = (0..self.lines.len())
+                .map(|line| data.world.exec(|finder: UiFinder<'_>| finder.find(&format!("slot_{}", line))))
+                .collect();
+            if labels.iter().all(Option::is_some) {
+                let mut texts = data.world.write_storage::<UiText>();
+                for (label, line) in labels.into_iter().zip(&self.lines) {
+                    if let Some(text) = label.and_then(|label| texts.get_mut(label)) {
+                        text.text = line.clone();
+                    }
+                }
+                self.shown = true;
+            }
+        }
+
+        let options = data.world.read_resource::<Options>();
+
+        // Nobody is around to press space during an unattended run.
+        if options.skip_welcome || options.unattended() {
+            Trans::Switch(Box::new(crate::bountiful::Bountiful::default()))
+        } else {
+            Trans::None
+        }
     }
 
     fn on_stop(&mut self, data: StateData<GameData>) {
@@ -25,10 +80,18 @@ impl SimpleState for WelcomeScreen {
         self.splash_screen = None;
     }
 
-    fn handle_event(&mut self, _: StateData<'_, GameData<'_, '_>>, event: StateEvent, ) -> SimpleTrans {
+    fn handle_event(&mut self, data: StateData<'_, GameData<'_, '_>>, event: StateEvent, ) -> SimpleTrans {
         if let StateEvent::Window(event) = &event {
-            if is_key_down(&event, VirtualKeyCode::Space) ||
+            let picked = SLOT_KEYS.iter().position(|key| is_key_down(&event, *key))
+                .map(|index| index + 1)
+                .filter(|slot| self.playable.contains(slot));
+
+            if let Some(slot) = picked {
+                data.world.write_resource::<Options>().save_slot = Some(slot);
+                Trans::Switch(Box::new(crate::bountiful::Bountiful::default()))
+            } else if is_key_down(&event, VirtualKeyCode::Space) ||
                 is_key_down(&event, VirtualKeyCode::Tab) {
+                data.world.write_resource::<Options>().save_slot = self.new_slot;
                 Trans::Switch(Box::new(crate::bountiful::Bountiful::default()))
             } else if is_close_requested(&event) ||
                 is_key_down(&event, VirtualKeyCode::Q) ||
@@ -41,4 +104,4 @@ impl SimpleState for WelcomeScreen {
             Trans::None
         }
     }
-}
\ No newline at end of file
+}